@@ -0,0 +1,163 @@
+use anyhow::Result;
+use log::info;
+use crate::{
+    config::{BackendType, Config},
+    cpu::CPUContext,
+    gpu::GPUContext,
+    boundary::WallLink,
+    lattice::LatticePoint,
+    Float,
+};
+
+/// Common interface for the devices that can advance the lattice.
+///
+/// A backend owns the lattice state between steps: the solver uploads the
+/// initial lattice once, calls `step` every iteration and reads the state
+/// back only when it needs to write output.
+pub trait ComputeBackend {
+    /// Human readable description of the backend (used in logs)
+    fn name(&self) -> String;
+
+    /// Replace the backend's lattice state. The bounce-back links are kept
+    /// and have to be uploaded separately for a new layout of walls.
+    fn upload_lattice_data(&mut self, data: &[LatticePoint]) -> Result<()>;
+
+    /// Replace the bounce-back links from fluid nodes into no-slip and
+    /// moving walls (see `Geometry::wall_links`)
+    fn upload_wall_links(&mut self, links: &[WallLink]) -> Result<()>;
+
+    /// Set the full-strength velocity of every node for a spatial inlet
+    /// profile (see `inlet::inlet_profile`)
+    fn upload_inlet_profile(&mut self, profile: &[[Float; 3]]) -> Result<()>;
+
+    /// Replace the per-face boundary parameters (see
+    /// `BoundaryConfig::face_values`), e.g. for time-varying inlets
+    fn update_face_values(&mut self, faces: &[[Float; 4]; 7]) -> Result<()>;
+
+    /// Perform one collision, streaming and boundary step
+    fn step(&mut self) -> Result<()>;
+
+    /// Copy the current lattice state back to the host
+    fn read_lattice_data(&self) -> Result<Vec<LatticePoint>>;
+}
+
+impl ComputeBackend for GPUContext {
+    fn name(&self) -> String {
+        let info = self.adapter_info();
+        format!("GPU ({}, {:?}, {:?})", info.name, info.backend, info.device_type)
+    }
+
+    fn upload_lattice_data(&mut self, data: &[LatticePoint]) -> Result<()> {
+        GPUContext::upload_lattice_data(self, data);
+        Ok(())
+    }
+
+    fn upload_wall_links(&mut self, links: &[WallLink]) -> Result<()> {
+        GPUContext::upload_wall_links(self, links);
+        Ok(())
+    }
+
+    fn upload_inlet_profile(&mut self, profile: &[[Float; 3]]) -> Result<()> {
+        GPUContext::upload_inlet_profile(self, profile);
+        Ok(())
+    }
+
+    fn update_face_values(&mut self, faces: &[[Float; 4]; 7]) -> Result<()> {
+        GPUContext::update_face_values(self, faces);
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<()> {
+        GPUContext::step(self)
+    }
+
+    fn read_lattice_data(&self) -> Result<Vec<LatticePoint>> {
+        pollster::block_on(GPUContext::read_lattice_data(self))
+    }
+}
+
+impl ComputeBackend for CPUContext {
+    fn name(&self) -> String {
+        "CPU (multithreaded)".to_string()
+    }
+
+    fn upload_lattice_data(&mut self, data: &[LatticePoint]) -> Result<()> {
+        CPUContext::upload_lattice_data(self, data)
+    }
+
+    fn upload_wall_links(&mut self, links: &[WallLink]) -> Result<()> {
+        CPUContext::upload_wall_links(self, links)
+    }
+
+    fn upload_inlet_profile(&mut self, profile: &[[Float; 3]]) -> Result<()> {
+        CPUContext::upload_inlet_profile(self, profile)
+    }
+
+    fn update_face_values(&mut self, faces: &[[Float; 4]; 7]) -> Result<()> {
+        CPUContext::update_face_values(self, faces);
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<()> {
+        CPUContext::step(self)
+    }
+
+    fn read_lattice_data(&self) -> Result<Vec<LatticePoint>> {
+        Ok(CPUContext::read_lattice_data(self))
+    }
+}
+
+/// Create the backend selected in the configuration
+pub async fn create_backend(config: &Config) -> Result<Box<dyn ComputeBackend>> {
+    let backend: Box<dyn ComputeBackend> = match config.simulation.backend {
+        BackendType::Gpu => Box::new(GPUContext::new(config).await?),
+        BackendType::Cpu => Box::new(CPUContext::new(config)?),
+    };
+
+    info!("Compute backend: {}", backend.name());
+    Ok(backend)
+}
+
+/// Approximate memory needed by a run, before the geometry's wall links
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryEstimate {
+    pub nodes: usize,
+    /// Size of one copy of the lattice state on the backend (the largest
+    /// single buffer on the GPU)
+    pub lattice_bytes: u64,
+    /// Memory of the backend: lattice and scratch copies, the inlet profile
+    /// and, on the GPU, the staging buffer used for output
+    pub backend_bytes: u64,
+    /// Host memory of the solver's own copy of the lattice
+    pub host_bytes: u64,
+}
+
+/// Estimate the memory of the backend selected in the configuration
+pub fn memory_estimate(config: &Config) -> MemoryEstimate {
+    let nodes = config.domain.nx * config.domain.ny * config.domain.nz;
+    let q = config.simulation.lattice.lattice().q();
+    let point_size = std::mem::size_of::<LatticePoint>() as u64;
+    let profile = config.boundaries.inlet_profile.is_some();
+
+    let (lattice_bytes, backend_bytes) = match config.simulation.backend {
+        BackendType::Gpu => {
+            // Only the Q distributions in use are stored per node; lattice,
+            // temp and staging buffers plus a vec4 per node for the profile
+            let lattice = nodes as u64 * ((q + LatticePoint::EXTRA_WORDS) * std::mem::size_of::<u32>()) as u64;
+            let profile = if profile { nodes as u64 * 16 } else { 0 };
+            (lattice, 3 * lattice + profile)
+        }
+        BackendType::Cpu => {
+            let lattice = nodes as u64 * point_size;
+            let profile = if profile { (nodes * std::mem::size_of::<[Float; 3]>()) as u64 } else { 0 };
+            (lattice, 2 * lattice + profile)
+        }
+    };
+
+    MemoryEstimate {
+        nodes,
+        lattice_bytes,
+        backend_bytes,
+        host_bytes: nodes as u64 * point_size,
+    }
+}
@@ -0,0 +1,265 @@
+use bytemuck::{Pod, Zeroable};
+use crate::{
+    config::WallScheme,
+    lattice::{Lattice, LatticePoint},
+    Float,
+};
+
+/// Link from a fluid node into a bounce-back or moving-wall solid node, whose
+/// population is reflected back to the fluid node by the wall links pass (see
+/// `WallLink` in `boundary.wgsl`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct WallLink {
+    /// Fluid node
+    pub node: u32,
+    /// Direction from the fluid node into the wall
+    pub direction: u32,
+    /// Distance from the fluid node to the wall as a fraction of the link, in (0, 1]
+    /// (1/2 for half-way bounce-back)
+    pub q: Float,
+    /// Solid node at the other end of the link
+    pub solid: u32,
+}
+
+/// Inward unit normal of a domain face (0: -x, 1: +x, 2: -y, 3: +y, 4: -z, 5: +z)
+pub fn face_normal(face: u32) -> [i32; 3] {
+    let mut normal = [0; 3];
+    normal[(face / 2) as usize] = if face.is_multiple_of(2) { 1 } else { -1 };
+    normal
+}
+
+/// Component of `c` along the inward normal of `face`
+fn normal_component(c: &[i32; 3], face: u32) -> i32 {
+    let normal = face_normal(face);
+    c[0] * normal[0] + c[1] * normal[1] + c[2] * normal[2]
+}
+
+/// Sums of the populations parallel to and leaving through the node's face,
+/// which are known after streaming
+fn known_populations(lattice: &dyn Lattice, point: &LatticePoint) -> (Float, Float) {
+    let mut parallel = 0.0;
+    let mut outgoing = 0.0;
+    for (f, c) in point.f[..lattice.q()].iter().zip(lattice.velocities()) {
+        match normal_component(c, point.face) {
+            0 => parallel += f,
+            cn if cn < 0 => outgoing += f,
+            _ => {}
+        }
+    }
+    (parallel, outgoing)
+}
+
+/// Zou-He velocity boundary (non-equilibrium bounce-back) on the face
+/// `point.face`, applied after streaming (see `zou_he_velocity` in
+/// `boundary.wgsl`).
+///
+/// The populations with c·n > 0 enter from outside the domain and are
+/// unknown. The density follows from the known ones,
+/// rho = (sum_{c·n=0} f + 2 sum_{c·n<0} f) / (1 - u·n), and the unknowns are
+/// rebuilt by `zou_he_reconstruct`. The node then carries exactly `velocity`.
+pub fn zou_he_velocity(lattice: &dyn Lattice, point: &mut LatticePoint, velocity: [Float; 3]) {
+    let normal = face_normal(point.face);
+    let un: Float = (0..3).map(|a| velocity[a] * normal[a] as Float).sum();
+    let (parallel, outgoing) = known_populations(lattice, point);
+    zou_he_reconstruct(lattice, point, (parallel + 2.0 * outgoing) / (1.0 - un), velocity);
+}
+
+/// Zou-He pressure boundary on the face `point.face`: imposes `density`
+/// (p = cs^2 rho) with the normal velocity u·n = 1 - (sum_{c·n=0} f +
+/// 2 sum_{c·n<0} f) / rho that follows from the known populations and zero
+/// tangential velocity (see `zou_he_pressure` in `boundary.wgsl`)
+pub fn zou_he_pressure(lattice: &dyn Lattice, point: &mut LatticePoint, density: Float) {
+    let normal = face_normal(point.face);
+    let (parallel, outgoing) = known_populations(lattice, point);
+    let un = 1.0 - (parallel + 2.0 * outgoing) / density;
+    zou_he_reconstruct(lattice, point, density, normal.map(|n| un * n as Float));
+}
+
+/// Set the unknown populations (c·n > 0) for the given density and velocity:
+/// f_i = f_opp(i) + 2 w_i rho c_i·u / cs^2, after which the remaining
+/// tangential momentum error is spread over the unknowns in proportion to
+/// c_i, which leaves mass and normal momentum unchanged
+fn zou_he_reconstruct(lattice: &dyn Lattice, point: &mut LatticePoint, density: Float, velocity: [Float; 3]) {
+    let q = lattice.q();
+    let velocities = lattice.velocities();
+    let normal = face_normal(point.face);
+    let unknown: Vec<bool> = velocities.iter().map(|c| normal_component(c, point.face) > 0).collect();
+
+    // Bounce back the non-equilibrium part of the opposite population
+    for i in (0..q).filter(|&i| unknown[i]) {
+        let c = velocities[i];
+        let cu: Float = (0..3).map(|a| c[a] as Float * velocity[a]).sum();
+        point.f[i] = point.f[lattice.opposite()[i]] + 2.0 * lattice.weights()[i] * density * cu / lattice.cs2();
+    }
+
+    // Correct the tangential momentum with the unknown populations
+    let mut momentum = [0.0; 3];
+    let mut spread = [0.0; 3];
+    for (i, (f, c)) in point.f[..q].iter().zip(velocities).enumerate() {
+        for a in 0..3 {
+            momentum[a] += f * c[a] as Float;
+            if unknown[i] {
+                spread[a] += (c[a] * c[a]) as Float;
+            }
+        }
+    }
+    let correction: [Float; 3] = [0, 1, 2].map(|a| {
+        if normal[a] != 0 || spread[a] == 0.0 {
+            0.0
+        } else {
+            (density * velocity[a] - momentum[a]) / spread[a]
+        }
+    });
+    for i in (0..q).filter(|&i| unknown[i]) {
+        let c = velocities[i];
+        point.f[i] += (0..3).map(|a| c[a] as Float * correction[a]).sum::<Float>();
+    }
+
+    point.density = density;
+    point.velocity = velocity;
+}
+
+/// Direction with the wall-normal component of `direction` reversed
+pub fn reflected_direction(lattice: &dyn Lattice, direction: usize, face: u32) -> usize {
+    let normal = face_normal(face);
+    let c = lattice.velocities()[direction];
+    let cn = normal_component(&c, face);
+    let reflected = [0, 1, 2].map(|a| c[a] - 2 * cn * normal[a]);
+    lattice.velocities().iter().position(|c| *c == reflected).unwrap_or(direction)
+}
+
+/// Free-slip wall on the face `point.face` (see `free_slip` in
+/// `boundary.wgsl`): the populations streamed into the wall node are
+/// reflected specularly, reversing only their wall-normal component, so
+/// the wall exerts no shear on the flow. The node keeps the tangential
+/// velocity of the reflected populations.
+pub fn free_slip(lattice: &dyn Lattice, point: &mut LatticePoint) {
+    let streamed = point.f;
+    for i in 0..lattice.q() {
+        point.f[i] = streamed[reflected_direction(lattice, i, point.face)];
+    }
+    point.calculate_macroscopic(lattice);
+    point.velocity[(point.face / 2) as usize] = 0.0;
+}
+
+/// Half-way bounce-back links (q = 1/2) from every non-solid node of
+/// `points` into the bounce-back and moving-wall solid nodes, wrapping across
+/// periodic axes. Free-slip walls reflect on the wall node instead and get no
+/// links.
+pub fn wall_links(lattice: &dyn Lattice, points: &[LatticePoint], dims: [usize; 3], periodic: [bool; 3]) -> Vec<WallLink> {
+    let bounces = |point: &LatticePoint| point.node_type == 1 && point.boundary != WallScheme::FreeSlip as u32;
+    let mut links = Vec::new();
+    for node in (0..points.len()).filter(|&n| points[n].node_type != 1) {
+        for (direction, &c) in lattice.velocities().iter().enumerate() {
+            if let Some(solid) = neighbor(node, c, dims, periodic).filter(|&n| bounces(&points[n])) {
+                links.push(WallLink { node: node as u32, direction: direction as u32, q: 0.5, solid: solid as u32 });
+            }
+        }
+    }
+    links
+}
+
+/// Index of the node one step inwards from boundary node `idx` on its face,
+/// if it lies inside the domain
+pub fn interior_neighbor(idx: usize, face: u32, [nx, ny, nz]: [usize; 3]) -> Option<usize> {
+    let position = [idx % nx, (idx / nx) % ny, idx / (nx * ny)];
+    let size = [nx, ny, nz];
+    let normal = face_normal(face);
+    let mut interior = [0; 3];
+    for a in 0..3 {
+        let p = position[a] as i64 + normal[a] as i64;
+        if p < 0 || p >= size[a] as i64 {
+            return None;
+        }
+        interior[a] = p as usize;
+    }
+    Some(interior[0] + interior[1] * nx + interior[2] * nx * ny)
+}
+
+/// Convective outflow df/dt + U df/dn = 0 on the face `point.face`,
+/// discretized implicitly with upwind differences as
+/// f(t+1) = (f(t) + U f_interior(t+1)) / (1 + U) (see `convective_outflow` in
+/// `boundary.wgsl`).
+///
+/// `previous` holds the node's populations of the previous step and
+/// `interior` the streamed interior neighbour. `convection_velocity` is U;
+/// when `None` the neighbour's outflow velocity (clamped to [0, 1]) is used.
+/// Without a non-solid neighbour the previous populations are kept.
+pub fn convective_outflow(
+    lattice: &dyn Lattice,
+    point: &mut LatticePoint,
+    previous: &LatticePoint,
+    interior: Option<&LatticePoint>,
+    convection_velocity: Option<Float>,
+) {
+    let normal = face_normal(point.face);
+    let (u, interior) = match interior.filter(|n| n.node_type != 1) {
+        Some(n) => {
+            let outflow = -(0..3).map(|a| n.velocity[a] * normal[a] as Float).sum::<Float>();
+            (convection_velocity.unwrap_or(outflow.clamp(0.0, 1.0)), n.f)
+        }
+        None => (0.0, previous.f),
+    };
+
+    let q = lattice.q();
+    for ((f, previous), interior) in point.f[..q].iter_mut().zip(&previous.f).zip(&interior) {
+        *f = (previous + u * interior) / (1.0 + u);
+    }
+    point.calculate_macroscopic(lattice);
+}
+
+/// Interpolated bounce-back (Bouzidi, Firdaouss and Lallemand) of one wall
+/// link (see `wall_links` in `boundary.wgsl`). Returns the population
+/// f_-i(x_f) entering the fluid node from the wall, from the post-collision
+/// populations f*:
+///
+/// - q < 1/2: f_-i(x_f) = 2q f*_i(x_f) + (1 - 2q) f*_i(x_f - c_i)
+/// - q >= 1/2: f_-i(x_f) = f*_i(x_f) / 2q + (2q - 1) / 2q f*_-i(x_f)
+///
+/// plus the wall momentum 2 w_i rho c_-i·u_w / cs^2 of a moving wall, scaled
+/// by 1/2q for q >= 1/2. At q = 1/2 this is half-way bounce-back. Links with
+/// q < 1/2 whose upwind node x_f - c_i is solid or outside the domain fall
+/// back to half-way bounce-back.
+pub fn interpolated_bounce_back(
+    lattice: &dyn Lattice,
+    post_collision: &[LatticePoint],
+    link: &WallLink,
+    dims: [usize; 3],
+    periodic: [bool; 3],
+    density: Float,
+    wall_velocity: [Float; 3],
+) -> Float {
+    let (node, i) = (link.node as usize, link.direction as usize);
+    let back = lattice.opposite()[i];
+    let c = lattice.velocities()[back];
+    let cu: Float = (0..3).map(|a| c[a] as Float * wall_velocity[a]).sum();
+    let momentum = 2.0 * lattice.weights()[back] * density * cu / lattice.cs2();
+
+    let f = &post_collision[node].f;
+    let q = link.q;
+    if q >= 0.5 {
+        return (f[i] + (2.0 * q - 1.0) * f[back] + momentum) / (2.0 * q);
+    }
+    match neighbor(node, c, dims, periodic).filter(|&n| post_collision[n].node_type != 1) {
+        Some(upwind) => 2.0 * q * f[i] + (1.0 - 2.0 * q) * post_collision[upwind].f[i] + momentum,
+        None => f[i] + momentum,
+    }
+}
+
+/// Index of the node one step along `c` from `idx`, wrapped on periodic
+/// axes, if it lies inside the domain
+fn neighbor(idx: usize, c: [i32; 3], [nx, ny, nz]: [usize; 3], periodic: [bool; 3]) -> Option<usize> {
+    let position = [idx % nx, (idx / nx) % ny, idx / (nx * ny)];
+    let size = [nx, ny, nz];
+    let mut next = [0; 3];
+    for a in 0..3 {
+        let p = position[a] as i64 + c[a] as i64;
+        next[a] = match p {
+            p if (0..size[a] as i64).contains(&p) => p as usize,
+            _ if periodic[a] => p.rem_euclid(size[a] as i64) as usize,
+            _ => return None,
+        };
+    }
+    Some(next[0] + next[1] * nx + next[2] * nx * ny)
+}
@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use bytemuck::Zeroable;
+use std::io::{BufReader, BufWriter, Read, Write};
+use crate::{config::Config, lattice::LatticePoint};
+
+/// File signature and format version of checkpoints
+const MAGIC: &[u8; 8] = b"LBMCKPT1";
+
+/// Full lattice state at one iteration, from which a run can be resumed.
+///
+/// The file holds a short header (signature, grid size, Q and iteration)
+/// followed by the raw `LatticePoint`s in node order, so it is only
+/// portable between machines of the same endianness.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub iteration: usize,
+    /// Grid size (nx, ny, nz)
+    pub dims: [usize; 3],
+    /// Number of discrete velocities of the lattice model
+    pub q: usize,
+    pub lattice: Vec<LatticePoint>,
+}
+
+impl Checkpoint {
+    pub fn new(config: &Config, iteration: usize, lattice: Vec<LatticePoint>) -> Self {
+        Self {
+            iteration,
+            dims: [config.domain.nx, config.domain.ny, config.domain.nz],
+            q: config.simulation.lattice.lattice().q(),
+            lattice,
+        }
+    }
+
+    pub fn write(&self, path: &str) -> Result<()> {
+        let file = std::fs::File::create(path).with_context(|| format!("Cannot create checkpoint {}", path))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MAGIC)?;
+        for value in [self.dims[0], self.dims[1], self.dims[2], self.q, self.iteration] {
+            writer.write_all(&(value as u64).to_le_bytes())?;
+        }
+        writer.write_all(bytemuck::cast_slice(&self.lattice))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read a checkpoint written for the grid and lattice model of `config`.
+    ///
+    /// The header is checked against `config` and the file length before the
+    /// lattice is allocated, so a corrupt or foreign file fails cleanly.
+    pub fn read(path: &str, config: &Config) -> Result<Self> {
+        let file = std::fs::File::open(path).with_context(|| format!("Cannot open checkpoint {}", path))?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        anyhow::ensure!(&magic == MAGIC, "{} is not a checkpoint file", path);
+        let mut header = [0usize; 5];
+        for value in header.iter_mut() {
+            let mut bytes = [0u8; 8];
+            reader.read_exact(&mut bytes)?;
+            *value = usize::try_from(u64::from_le_bytes(bytes))
+                .with_context(|| format!("Checkpoint {} has a corrupt header", path))?;
+        }
+        let [nx, ny, nz, q, iteration] = header;
+        let mut checkpoint = Self { iteration, dims: [nx, ny, nz], q, lattice: Vec::new() };
+        checkpoint.check_compatible(config)?;
+
+        let body_len = nx
+            .checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .and_then(|n| n.checked_mul(std::mem::size_of::<LatticePoint>()))
+            .with_context(|| format!("Checkpoint {} has a corrupt header", path))?;
+        let header_len = (MAGIC.len() + 8 * header.len()) as u64;
+        anyhow::ensure!(
+            file_len.checked_sub(header_len) == Some(body_len as u64),
+            "Checkpoint {} holds {} bytes of lattice data, expected {}",
+            path,
+            file_len.saturating_sub(header_len),
+            body_len
+        );
+
+        checkpoint.lattice = vec![LatticePoint::zeroed(); nx * ny * nz];
+        reader
+            .read_exact(bytemuck::cast_slice_mut(&mut checkpoint.lattice))
+            .with_context(|| format!("Checkpoint {} is truncated", path))?;
+        anyhow::ensure!(reader.read(&mut [0u8])? == 0, "Checkpoint {} has trailing data", path);
+
+        Ok(checkpoint)
+    }
+
+    /// Check that the checkpoint was written for the grid and lattice model of `config`
+    pub fn check_compatible(&self, config: &Config) -> Result<()> {
+        let dims = [config.domain.nx, config.domain.ny, config.domain.nz];
+        anyhow::ensure!(
+            self.dims == dims,
+            "Checkpoint grid {:?} does not match the domain {:?}",
+            self.dims,
+            dims
+        );
+        let q = config.simulation.lattice.lattice().q();
+        anyhow::ensure!(
+            self.q == q,
+            "Checkpoint has {} velocities per node, the {:?} lattice has {}",
+            self.q,
+            config.simulation.lattice,
+            q
+        );
+        Ok(())
+    }
+}
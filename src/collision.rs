@@ -0,0 +1,475 @@
+use std::fmt::Write;
+use crate::{
+    config::{CollisionModel, Config},
+    lattice::{Lattice, LatticePoint, MAX_Q},
+    Float,
+};
+
+/// Relaxation group of a moment in the MRT basis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MomentGroup {
+    /// Density and momentum
+    Conserved = 0,
+    /// Trace of the second-order moments (bulk viscosity)
+    Bulk = 1,
+    /// Deviatoric second-order moments (shear viscosity)
+    Shear = 2,
+    /// Third- and higher-order (non-hydrodynamic) moments
+    Ghost = 3,
+}
+
+/// Orthogonal moment basis of a lattice for the MRT collision operator.
+///
+/// The basis is built by Gram-Schmidt orthogonalisation of the polynomials
+/// 1, c_a, |c|^2, the traceless second-order products and the higher-order
+/// monomials (each component to at most the second power), using the lattice
+/// weights as inner product. Orthogonality makes the inverse transform a
+/// scaled transpose: f_i = w_i sum_k M_ki m_k / N_k with N_k = sum_i w_i M_ki^2.
+#[derive(Debug, Clone)]
+pub struct MomentBasis {
+    q: usize,
+    /// Row-major `Q x Q` transform, m_k = sum_i matrix[k * Q + i] f_i
+    matrix: Vec<Float>,
+    /// Row-major `Q x Q` inverse, f_i = sum_k inverse[i * Q + k] m_k
+    inverse: Vec<Float>,
+    groups: Vec<MomentGroup>,
+}
+
+impl MomentBasis {
+    pub fn new(lattice: &dyn Lattice) -> Self {
+        let q = lattice.q();
+        let weights: Vec<f64> = lattice.weights().iter().map(|&w| w as f64).collect();
+        let velocities: Vec<[f64; 3]> = lattice.velocities().iter()
+            .map(|c| [c[0] as f64, c[1] as f64, c[2] as f64])
+            .collect();
+        let dot = |a: &[f64], b: &[f64]| -> f64 {
+            a.iter().zip(b).zip(&weights).map(|((x, y), w)| w * x * y).sum()
+        };
+
+        // Candidate polynomials in order of increasing tensor order
+        let monomial = |[a, b, c]: [i32; 3]| -> Vec<f64> {
+            velocities.iter().map(|v| v[0].powi(a) * v[1].powi(b) * v[2].powi(c)).collect()
+        };
+        let combine = |terms: &[(f64, [i32; 3])]| -> Vec<f64> {
+            let mut values = vec![0.0; q];
+            for (scale, exponents) in terms {
+                for (value, term) in values.iter_mut().zip(monomial(*exponents)) {
+                    *value += scale * term;
+                }
+            }
+            values
+        };
+
+        let planar = lattice.dimensions() == 2;
+        let mut candidates: Vec<(MomentGroup, Vec<f64>)> = vec![
+            (MomentGroup::Conserved, monomial([0, 0, 0])),
+            (MomentGroup::Conserved, monomial([1, 0, 0])),
+            (MomentGroup::Conserved, monomial([0, 1, 0])),
+        ];
+        if planar {
+            candidates.extend([
+                (MomentGroup::Bulk, combine(&[(1.0, [2, 0, 0]), (1.0, [0, 2, 0])])),
+                (MomentGroup::Shear, combine(&[(1.0, [2, 0, 0]), (-1.0, [0, 2, 0])])),
+                (MomentGroup::Shear, monomial([1, 1, 0])),
+            ]);
+        } else {
+            candidates.extend([
+                (MomentGroup::Conserved, monomial([0, 0, 1])),
+                (MomentGroup::Bulk, combine(&[(1.0, [2, 0, 0]), (1.0, [0, 2, 0]), (1.0, [0, 0, 2])])),
+                (MomentGroup::Shear, combine(&[(1.0, [2, 0, 0]), (-1.0, [0, 2, 0])])),
+                (MomentGroup::Shear, combine(&[(1.0, [0, 2, 0]), (-1.0, [0, 0, 2])])),
+                (MomentGroup::Shear, monomial([1, 1, 0])),
+                (MomentGroup::Shear, monomial([1, 0, 1])),
+                (MomentGroup::Shear, monomial([0, 1, 1])),
+            ]);
+        }
+
+        let max_z = if planar { 0 } else { 2 };
+        for order in 3..=6 {
+            for a in (0..=2).rev() {
+                for b in (0..=2).rev() {
+                    for c in (0..=max_z).rev() {
+                        if a + b + c == order {
+                            candidates.push((MomentGroup::Ghost, monomial([a, b, c])));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Gram-Schmidt, dropping candidates that are linearly dependent on
+        // the lattice velocities
+        let mut rows: Vec<(MomentGroup, Vec<f64>, f64)> = Vec::with_capacity(q);
+        for (group, mut values) in candidates {
+            if rows.len() == q {
+                break;
+            }
+            for (_, row, norm) in &rows {
+                let projection = dot(&values, row) / norm;
+                for (value, r) in values.iter_mut().zip(row) {
+                    *value -= projection * r;
+                }
+            }
+            let norm = dot(&values, &values);
+            if norm > 1e-10 {
+                rows.push((group, values, norm));
+            }
+        }
+        assert_eq!(rows.len(), q, "Incomplete moment basis for {}", lattice.name());
+
+        let mut matrix = vec![0.0; q * q];
+        let mut inverse = vec![0.0; q * q];
+        for (k, (_, row, norm)) in rows.iter().enumerate() {
+            for i in 0..q {
+                matrix[k * q + i] = row[i] as Float;
+                inverse[i * q + k] = (weights[i] * row[i] / norm) as Float;
+            }
+        }
+
+        Self {
+            q,
+            matrix,
+            inverse,
+            groups: rows.iter().map(|(group, _, _)| *group).collect(),
+        }
+    }
+
+    /// Relaxation group of each moment
+    pub fn groups(&self) -> &[MomentGroup] {
+        &self.groups
+    }
+
+    /// Transform populations to moments
+    pub fn to_moments(&self, f: &[Float]) -> [Float; MAX_Q] {
+        let mut m = [0.0; MAX_Q];
+        for (k, moment) in m[..self.q].iter_mut().enumerate() {
+            let row = &self.matrix[k * self.q..(k + 1) * self.q];
+            *moment = row.iter().zip(f).map(|(a, b)| a * b).sum();
+        }
+        m
+    }
+
+    /// Transform moments back to populations
+    pub fn to_populations(&self, m: &[Float]) -> [Float; MAX_Q] {
+        let mut f = [0.0; MAX_Q];
+        for (i, population) in f[..self.q].iter_mut().enumerate() {
+            let row = &self.inverse[i * self.q..(i + 1) * self.q];
+            *population = row.iter().zip(m).map(|(a, b)| a * b).sum();
+        }
+        f
+    }
+
+    /// WGSL functions `mrt_moments` and `mrt_populations` with the transforms
+    /// unrolled (zero coefficients skipped) and the `MRT_GROUP` table
+    pub fn wgsl_functions(&self) -> String {
+        let q = self.q;
+        let mut wgsl = String::new();
+
+        writeln!(wgsl, "// MRT moment basis (generated from collision.rs)").unwrap();
+        writeln!(wgsl, "const MRT_GROUP = array<u32, {}>(", q).unwrap();
+        for (k, group) in self.groups.iter().enumerate() {
+            writeln!(wgsl, "    {}u,  // {} {:?}", *group as u32, k, group).unwrap();
+        }
+        writeln!(wgsl, ");").unwrap();
+
+        for (name, input, output, coefficients) in [
+            ("mrt_moments", "f", "m", &self.matrix),
+            ("mrt_populations", "m", "f", &self.inverse),
+        ] {
+            writeln!(wgsl, "fn {}({}: array<f32, Q>) -> array<f32, Q> {{", name, input).unwrap();
+            writeln!(wgsl, "    var {}: array<f32, Q>;", output).unwrap();
+            for row in 0..q {
+                let terms: Vec<String> = (0..q)
+                    .filter(|&col| coefficients[row * q + col].abs() > 1e-7)
+                    .map(|col| format!("{:?} * {}[{}]", coefficients[row * q + col], input, col))
+                    .collect();
+                writeln!(wgsl, "    {}[{}] = {};", output, row, terms.join(" + ")).unwrap();
+            }
+            writeln!(wgsl, "    return {};", output).unwrap();
+            writeln!(wgsl, "}}").unwrap();
+        }
+
+        wgsl
+    }
+}
+
+/// Relaxation rates of the MRT moment groups
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MrtRates {
+    /// Shear rate, 1/tau unless overridden
+    pub shear: Float,
+    pub bulk: Float,
+    pub ghost: Float,
+}
+
+impl MrtRates {
+    /// Rate for a moment group. Conserved moments use the shear rate, which
+    /// only matters when the macroscopic safeguards changed density or velocity.
+    pub fn for_group(&self, group: MomentGroup) -> Float {
+        match group {
+            MomentGroup::Conserved | MomentGroup::Shear => self.shear,
+            MomentGroup::Bulk => self.bulk,
+            MomentGroup::Ghost => self.ghost,
+        }
+    }
+}
+
+/// Relaxation rates of the symmetric and antisymmetric TRT parts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrtRates {
+    /// Rate of the even (symmetric) part, 1/tau
+    pub plus: Float,
+    /// Rate of the odd (antisymmetric) part, set by the magic parameter
+    pub minus: Float,
+}
+
+/// Relaxation rates of the cumulant groups
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CumulantRates {
+    /// Rate of the deviatoric second-order cumulants, 1/tau
+    pub shear: Float,
+    /// Rate of the trace of the second-order cumulants
+    pub bulk: Float,
+    pub third: Float,
+    pub fourth: Float,
+    pub fifth: Float,
+    pub sixth: Float,
+}
+
+/// Collision operator of fluid nodes, resolved from the config
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollisionOperator {
+    Bgk { tau: Float },
+    Mrt(MrtRates),
+    Trt(TrtRates),
+    Cumulant(CumulantRates),
+}
+
+impl CollisionOperator {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let tau = config.calculate_tau();
+        Ok(match config.simulation.collision_model {
+            CollisionModel::Bgk => CollisionOperator::Bgk { tau },
+            CollisionModel::Mrt => CollisionOperator::Mrt(config.simulation.mrt.rates(tau)),
+            CollisionModel::Trt => CollisionOperator::Trt(config.simulation.trt.rates(tau)?),
+            CollisionModel::Cumulant => {
+                let lattice = config.simulation.lattice.lattice();
+                anyhow::ensure!(
+                    lattice.q() == 27,
+                    "The cumulant collision operator requires the D3Q27 lattice, got {}",
+                    lattice.name()
+                );
+                CollisionOperator::Cumulant(config.simulation.cumulant.rates(tau))
+            }
+        })
+    }
+
+    /// Shear relaxation time (tau for BGK, 1/rate of the shear moments otherwise)
+    pub fn tau(&self) -> Float {
+        match self {
+            CollisionOperator::Bgk { tau } => *tau,
+            CollisionOperator::Mrt(rates) => 1.0 / rates.shear,
+            CollisionOperator::Trt(rates) => 1.0 / rates.plus,
+            CollisionOperator::Cumulant(rates) => 1.0 / rates.shear,
+        }
+    }
+
+    /// The same operator with the shear relaxation time replaced, e.g. by the
+    /// effective tau of a subgrid model. TRT keeps its magic parameter.
+    pub fn with_tau(&self, tau: Float) -> Self {
+        match *self {
+            CollisionOperator::Bgk { .. } => CollisionOperator::Bgk { tau },
+            CollisionOperator::Mrt(rates) => CollisionOperator::Mrt(MrtRates { shear: 1.0 / tau, ..rates }),
+            CollisionOperator::Trt(rates) => {
+                let magic = (1.0 / rates.plus - 0.5) * (1.0 / rates.minus - 0.5);
+                CollisionOperator::Trt(TrtRates {
+                    plus: 1.0 / tau,
+                    minus: 1.0 / (magic / (tau - 0.5) + 0.5),
+                })
+            }
+            CollisionOperator::Cumulant(rates) => CollisionOperator::Cumulant(CumulantRates { shear: 1.0 / tau, ..rates }),
+        }
+    }
+
+    /// Whether the macroscopic velocity is clamped to |u| <= 0.3 before
+    /// collision. The cumulant operator is stable without it, and clamping
+    /// would break its exact momentum conservation.
+    pub fn clamps_velocity(&self) -> bool {
+        !matches!(self, CollisionOperator::Cumulant(_))
+    }
+
+    /// Relax `point` towards equilibrium using its current macroscopic fields
+    pub fn apply(&self, lattice: &dyn Lattice, point: &mut LatticePoint) {
+        match self {
+            CollisionOperator::Bgk { tau } => point.collide(lattice, *tau),
+            CollisionOperator::Mrt(rates) => point.collide_mrt(lattice, rates),
+            CollisionOperator::Trt(rates) => point.collide_trt(lattice, rates),
+            CollisionOperator::Cumulant(rates) => point.collide_cumulant(lattice, rates),
+        }
+    }
+}
+
+/// Central moments k[a][b][c] of order (a, b, c), or populations indexed by
+/// velocity component + 1 before the forward transform
+type Moments = [[[Float; 3]; 3]; 3];
+
+/// Chimera transform of the populations (c = -1, 0, 1) along one axis to
+/// central moments of order 0, 1, 2 about `u`
+fn chimera_forward([f_m, f_0, f_p]: [Float; 3], u: Float) -> [Float; 3] {
+    let sum = f_m + f_0 + f_p;
+    let diff = f_p - f_m;
+    [
+        sum,
+        diff - u * sum,
+        (f_p + f_m) - 2.0 * u * diff + u * u * sum,
+    ]
+}
+
+/// Inverse of `chimera_forward`
+fn chimera_backward([k0, k1, k2]: [Float; 3], u: Float) -> [Float; 3] {
+    let m1 = k1 + u * k0;
+    let m2 = k2 + 2.0 * u * k1 + u * u * k0;
+    [0.5 * (m2 - m1), k0 - m2, 0.5 * (m2 + m1)]
+}
+
+/// Apply the chimera transform along all three axes (`forward` selects the
+/// direction)
+fn chimera(m: &mut Moments, velocity: [Float; 3], forward: bool) {
+    let transform = |values: [Float; 3], u: Float| {
+        if forward { chimera_forward(values, u) } else { chimera_backward(values, u) }
+    };
+    let axes: [usize; 3] = if forward { [2, 1, 0] } else { [0, 1, 2] };
+
+    for axis in axes {
+        for i in 0..3 {
+            for j in 0..3 {
+                let index = |n: usize| match axis {
+                    0 => (n, i, j),
+                    1 => (i, n, j),
+                    _ => (i, j, n),
+                };
+                let values = [0, 1, 2].map(|n| {
+                    let (a, b, c) = index(n);
+                    m[a][b][c]
+                });
+                for (n, value) in transform(values, velocity[axis]).into_iter().enumerate() {
+                    let (a, b, c) = index(n);
+                    m[a][b][c] = value;
+                }
+            }
+        }
+    }
+}
+
+/// Cumulant collision (Geier et al. 2015, without the parametrised
+/// fourth-order rates) of D3Q27 populations `f` with macroscopic `density`
+/// and `velocity`
+pub(crate) fn cumulant_relax(
+    lattice: &dyn Lattice,
+    f: &mut [Float],
+    density: Float,
+    velocity: [Float; 3],
+    rates: &CumulantRates,
+) {
+    let velocities = lattice.velocities();
+    let slot = |c: [i32; 3]| [(c[0] + 1) as usize, (c[1] + 1) as usize, (c[2] + 1) as usize];
+
+    let mut k: Moments = [[[0.0; 3]; 3]; 3];
+    for (f, &c) in f.iter().zip(velocities) {
+        let [a, b, c] = slot(c);
+        k[a][b][c] = *f;
+    }
+    chimera(&mut k, velocity, true);
+
+    let rho = density;
+    let inv_rho = 1.0 / rho;
+
+    // Fourth- to sixth-order cumulants (orders two and three equal the central moments)
+    let c211 = k[2][1][1] - (k[2][0][0] * k[0][1][1] + 2.0 * k[1][1][0] * k[1][0][1]) * inv_rho;
+    let c121 = k[1][2][1] - (k[0][2][0] * k[1][0][1] + 2.0 * k[1][1][0] * k[0][1][1]) * inv_rho;
+    let c112 = k[1][1][2] - (k[0][0][2] * k[1][1][0] + 2.0 * k[1][0][1] * k[0][1][1]) * inv_rho;
+    let c220 = k[2][2][0] - (k[2][0][0] * k[0][2][0] + 2.0 * k[1][1][0] * k[1][1][0]) * inv_rho;
+    let c202 = k[2][0][2] - (k[2][0][0] * k[0][0][2] + 2.0 * k[1][0][1] * k[1][0][1]) * inv_rho;
+    let c022 = k[0][2][2] - (k[0][2][0] * k[0][0][2] + 2.0 * k[0][1][1] * k[0][1][1]) * inv_rho;
+
+    let c122 = k[1][2][2] - (k[0][0][2] * k[1][2][0] + k[0][2][0] * k[1][0][2]
+        + 4.0 * k[0][1][1] * k[1][1][1]
+        + 2.0 * (k[1][0][1] * k[0][2][1] + k[1][1][0] * k[0][1][2])) * inv_rho;
+    let c212 = k[2][1][2] - (k[0][0][2] * k[2][1][0] + k[2][0][0] * k[0][1][2]
+        + 4.0 * k[1][0][1] * k[1][1][1]
+        + 2.0 * (k[0][1][1] * k[2][0][1] + k[1][1][0] * k[1][0][2])) * inv_rho;
+    let c221 = k[2][2][1] - (k[2][0][0] * k[0][2][1] + k[0][2][0] * k[2][0][1]
+        + 4.0 * k[1][1][0] * k[1][1][1]
+        + 2.0 * (k[1][0][1] * k[1][2][0] + k[0][1][1] * k[2][1][0])) * inv_rho;
+
+    let c222 = k[2][2][2] - sixth_order_correction(&k, inv_rho);
+
+    // Second order: trace relaxes with the bulk rate towards rho * cs^2 * 3,
+    // the deviatoric part with the shear rate towards zero
+    let mut trace = k[2][0][0] + k[0][2][0] + k[0][0][2];
+    let mut xx_yy = k[2][0][0] - k[0][2][0];
+    let mut xx_zz = k[2][0][0] - k[0][0][2];
+    trace += rates.bulk * (rho - trace);
+    xx_yy *= 1.0 - rates.shear;
+    xx_zz *= 1.0 - rates.shear;
+    k[2][0][0] = (xx_yy + xx_zz + trace) / 3.0;
+    k[0][2][0] = (trace - 2.0 * xx_yy + xx_zz) / 3.0;
+    k[0][0][2] = (trace + xx_yy - 2.0 * xx_zz) / 3.0;
+    k[1][1][0] *= 1.0 - rates.shear;
+    k[1][0][1] *= 1.0 - rates.shear;
+    k[0][1][1] *= 1.0 - rates.shear;
+
+    // Third order
+    for [a, b, c] in [[2, 1, 0], [1, 2, 0], [2, 0, 1], [1, 0, 2], [0, 2, 1], [0, 1, 2], [1, 1, 1]] {
+        k[a][b][c] *= 1.0 - rates.third;
+    }
+
+    // Fourth order, back to central moments with the post-collision lower orders
+    let relax4 = 1.0 - rates.fourth;
+    k[2][1][1] = relax4 * c211 + (k[2][0][0] * k[0][1][1] + 2.0 * k[1][1][0] * k[1][0][1]) * inv_rho;
+    k[1][2][1] = relax4 * c121 + (k[0][2][0] * k[1][0][1] + 2.0 * k[1][1][0] * k[0][1][1]) * inv_rho;
+    k[1][1][2] = relax4 * c112 + (k[0][0][2] * k[1][1][0] + 2.0 * k[1][0][1] * k[0][1][1]) * inv_rho;
+    k[2][2][0] = relax4 * c220 + (k[2][0][0] * k[0][2][0] + 2.0 * k[1][1][0] * k[1][1][0]) * inv_rho;
+    k[2][0][2] = relax4 * c202 + (k[2][0][0] * k[0][0][2] + 2.0 * k[1][0][1] * k[1][0][1]) * inv_rho;
+    k[0][2][2] = relax4 * c022 + (k[0][2][0] * k[0][0][2] + 2.0 * k[0][1][1] * k[0][1][1]) * inv_rho;
+
+    // Fifth order
+    let relax5 = 1.0 - rates.fifth;
+    k[1][2][2] = relax5 * c122 + (k[0][0][2] * k[1][2][0] + k[0][2][0] * k[1][0][2]
+        + 4.0 * k[0][1][1] * k[1][1][1]
+        + 2.0 * (k[1][0][1] * k[0][2][1] + k[1][1][0] * k[0][1][2])) * inv_rho;
+    k[2][1][2] = relax5 * c212 + (k[0][0][2] * k[2][1][0] + k[2][0][0] * k[0][1][2]
+        + 4.0 * k[1][0][1] * k[1][1][1]
+        + 2.0 * (k[0][1][1] * k[2][0][1] + k[1][1][0] * k[1][0][2])) * inv_rho;
+    k[2][2][1] = relax5 * c221 + (k[2][0][0] * k[0][2][1] + k[0][2][0] * k[2][0][1]
+        + 4.0 * k[1][1][0] * k[1][1][1]
+        + 2.0 * (k[1][0][1] * k[1][2][0] + k[0][1][1] * k[2][1][0])) * inv_rho;
+
+    // Sixth order
+    k[2][2][2] = (1.0 - rates.sixth) * c222 + sixth_order_correction(&k, inv_rho);
+
+    // Conserved moments
+    k[0][0][0] = rho;
+    k[1][0][0] = 0.0;
+    k[0][1][0] = 0.0;
+    k[0][0][1] = 0.0;
+
+    chimera(&mut k, velocity, false);
+    for (f, &c) in f.iter_mut().zip(velocities) {
+        let [a, b, c] = slot(c);
+        *f = k[a][b][c];
+    }
+}
+
+/// Lower-order part of the sixth-order central moment, k222 - C222
+fn sixth_order_correction(k: &Moments, inv_rho: Float) -> Float {
+    (4.0 * k[1][1][1] * k[1][1][1]
+        + k[2][0][0] * k[0][2][2] + k[0][2][0] * k[2][0][2] + k[0][0][2] * k[2][2][0]
+        + 4.0 * (k[0][1][1] * k[2][1][1] + k[1][0][1] * k[1][2][1] + k[1][1][0] * k[1][1][2])
+        + 2.0 * (k[1][2][0] * k[1][0][2] + k[2][1][0] * k[0][1][2] + k[2][0][1] * k[0][2][1])) * inv_rho
+        - (16.0 * k[1][1][0] * k[1][0][1] * k[0][1][1]
+            + 4.0 * (k[1][0][1] * k[1][0][1] * k[0][2][0]
+                + k[0][1][1] * k[0][1][1] * k[2][0][0]
+                + k[1][1][0] * k[1][1][0] * k[0][0][2])
+            + 2.0 * k[2][0][0] * k[0][2][0] * k[0][0][2]) * inv_rho * inv_rho
+}
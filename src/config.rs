@@ -1,15 +1,31 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use crate::Float;
+use crate::{
+    collision::{CumulantRates, MrtRates, TrtRates},
+    lattice::{Lattice, D2Q9, D3Q15, D3Q19, D3Q27},
+    units::UnitConverter,
+    validation::{self, Validation},
+    Float,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     pub domain: DomainConfig,
     pub physics: PhysicsConfig,
+    /// Physical units of the physics and boundary values, lattice units when unset
+    #[serde(default)]
+    pub units: Option<UnitsConfig>,
     pub simulation: SimulationConfig,
     pub output: OutputConfig,
+    #[serde(default)]
+    pub boundaries: BoundaryConfig,
+    #[serde(default)]
+    pub gpu: GpuConfig,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct DomainConfig {
     pub nx: usize,
     pub ny: usize,
@@ -17,55 +33,950 @@ pub struct DomainConfig {
     pub dx: Float,
     pub dy: Float,
     pub dz: Float,
+    /// Height of the plane through the STL used as the 2D outline
+    /// (defaults to the middle of the STL bounding box)
+    #[serde(default)]
+    pub slice_z: Option<Float>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct PhysicsConfig {
+    /// Reynolds number U L / ν of the inlet speed and the characteristic
+    /// length, which sets the viscosity unless `viscosity` is given
     pub reynolds_number: Float,
     pub inlet_velocity: [Float; 3],
     pub density: Float,
     pub viscosity: Option<Float>,
+    /// Length L of the Reynolds number, one grid spacing when unset
+    #[serde(default)]
+    pub characteristic_length: Option<CharacteristicLength>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Characteristic length of the flow, e.g. the diameter of a cylinder
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum CharacteristicLength {
+    /// Length in the units of `domain.dx`
+    Length(Float),
+    /// Extent of the geometry's bounding box along an axis, replaced by the
+    /// length once the geometry is known (see `Config::resolve_characteristic_length`)
+    FromGeometry { from_geometry: Axis },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+/// Physical (SI) units. With this section the inlet, wall and convection
+/// velocities are given in m/s, densities in kg/m³, `physics.viscosity` in
+/// m²/s and `domain.dx` in metres; the lattice time step follows from the
+/// lattice Mach number `mach` or, when it is unset, from `simulation.tau`
+/// (see `units::UnitConverter`).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UnitsConfig {
+    /// Deprecated: characteristic length [m] of the Reynolds number U L / ν,
+    /// use `physics.characteristic_length` instead. Setting both is an error.
+    #[serde(default)]
+    pub length: Option<Float>,
+    /// Characteristic velocity [m/s], defaults to the magnitude of
+    /// `physics.inlet_velocity`
+    #[serde(default)]
+    pub velocity: Option<Float>,
+    /// Lattice Mach number of the characteristic velocity, u_lb / c_s;
+    /// 0.1 when neither it nor `simulation.tau` is set
+    #[serde(default)]
+    pub mach: Option<Float>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct SimulationConfig {
     pub max_iterations: usize,
     pub convergence_tolerance: Float,
     pub tau: Option<Float>, // relaxation time
+    #[serde(default)]
+    pub backend: BackendType, // "gpu" or "cpu"
+    #[serde(default)]
+    pub lattice: LatticeType, // "D2Q9", "D3Q15", "D3Q19" or "D3Q27"
+    #[serde(default)]
+    pub collision_model: CollisionModel, // "bgk", "mrt", "trt" or "cumulant"
+    #[serde(default)]
+    pub mrt: MrtConfig,
+    #[serde(default)]
+    pub trt: TrtConfig,
+    #[serde(default)]
+    pub cumulant: CumulantConfig,
+    #[serde(default)]
+    pub les: LesConfig,
+}
+
+/// Collision operator applied to fluid nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionModel {
+    /// Single-relaxation-time BGK
+    #[default]
+    Bgk = 0,
+    /// Multiple-relaxation-time, see `simulation.mrt`
+    Mrt = 1,
+    /// Two-relaxation-time, see `simulation.trt`
+    Trt = 2,
+    /// Cumulant operator (D3Q27 only), see `simulation.cumulant`
+    Cumulant = 3,
+}
+
+/// Relaxation rates of the MRT moment groups
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MrtConfig {
+    /// Rate of the deviatoric second-order moments (sets the viscosity),
+    /// defaults to 1/tau
+    #[serde(default)]
+    pub shear_rate: Option<Float>,
+    /// Rate of the trace of the second-order moments (sets the bulk viscosity)
+    #[serde(default = "default_mrt_rate")]
+    pub bulk_rate: Float,
+    /// Rate of the third- and higher-order (ghost) moments
+    #[serde(default = "default_mrt_rate")]
+    pub ghost_rate: Float,
+}
+
+impl Default for MrtConfig {
+    fn default() -> Self {
+        Self {
+            shear_rate: None,
+            bulk_rate: default_mrt_rate(),
+            ghost_rate: default_mrt_rate(),
+        }
+    }
+}
+
+impl MrtConfig {
+    pub fn rates(&self, tau: Float) -> MrtRates {
+        MrtRates {
+            shear: self.shear_rate.unwrap_or(1.0 / tau),
+            bulk: self.bulk_rate,
+            ghost: self.ghost_rate,
+        }
+    }
+}
+
+fn default_mrt_rate() -> Float {
+    1.0
+}
+
+/// Two-relaxation-time parameters
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TrtConfig {
+    /// Magic parameter Λ = (τ+ - 1/2)(τ- - 1/2) fixing the antisymmetric
+    /// relaxation time from tau. 1/4 gives the best stability, 3/16 places
+    /// bounce-back walls exactly half-way between nodes for any viscosity.
+    #[serde(default = "default_magic_parameter")]
+    pub magic_parameter: Float,
+}
+
+impl Default for TrtConfig {
+    fn default() -> Self {
+        Self {
+            magic_parameter: default_magic_parameter(),
+        }
+    }
+}
+
+impl TrtConfig {
+    /// Symmetric and antisymmetric relaxation rates for the given tau
+    pub fn rates(&self, tau: Float) -> anyhow::Result<TrtRates> {
+        let magic = self.magic_parameter;
+        anyhow::ensure!(
+            magic.is_finite() && magic > 0.0,
+            "simulation.trt.magic_parameter must be positive, got {} \
+             (presets: 0.25 = 1/4 for optimal stability, \
+             0.1875 = 3/16 for viscosity-independent bounce-back wall locations)",
+            magic
+        );
+        anyhow::ensure!(
+            tau > 0.5,
+            "TRT needs tau > 0.5 to derive the antisymmetric relaxation time, got {}",
+            tau
+        );
+        
+        let tau_minus = magic / (tau - 0.5) + 0.5;
+        Ok(TrtRates {
+            plus: 1.0 / tau,
+            minus: 1.0 / tau_minus,
+        })
+    }
+}
+
+fn default_magic_parameter() -> Float {
+    0.25
+}
+
+/// Relaxation rates of the cumulant operator (the shear rate is 1/tau).
+/// A rate of 1 sets the cumulants of that order to their equilibrium.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CumulantConfig {
+    /// Rate of the trace of the second-order cumulants (sets the bulk viscosity)
+    #[serde(default = "default_mrt_rate")]
+    pub bulk_rate: Float,
+    #[serde(default = "default_mrt_rate")]
+    pub third_order_rate: Float,
+    #[serde(default = "default_mrt_rate")]
+    pub fourth_order_rate: Float,
+    #[serde(default = "default_mrt_rate")]
+    pub fifth_order_rate: Float,
+    #[serde(default = "default_mrt_rate")]
+    pub sixth_order_rate: Float,
+}
+
+impl Default for CumulantConfig {
+    fn default() -> Self {
+        Self {
+            bulk_rate: default_mrt_rate(),
+            third_order_rate: default_mrt_rate(),
+            fourth_order_rate: default_mrt_rate(),
+            fifth_order_rate: default_mrt_rate(),
+            sixth_order_rate: default_mrt_rate(),
+        }
+    }
+}
+
+impl CumulantConfig {
+    pub fn rates(&self, tau: Float) -> CumulantRates {
+        CumulantRates {
+            shear: 1.0 / tau,
+            bulk: self.bulk_rate,
+            third: self.third_order_rate,
+            fourth: self.fourth_order_rate,
+            fifth: self.fifth_order_rate,
+            sixth: self.sixth_order_rate,
+        }
+    }
+}
+
+/// Large-eddy simulation (subgrid-scale) settings
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LesConfig {
+    #[serde(default)]
+    pub model: LesModel, // "none", "smagorinsky", "wale" or "vreman"
+    /// Smagorinsky constant Cs
+    #[serde(default = "default_smagorinsky_constant")]
+    pub smagorinsky_constant: Float,
+    /// WALE constant Cw
+    #[serde(default = "default_wale_constant")]
+    pub wale_constant: Float,
+    /// Vreman constant c (about 2.5 Cs^2)
+    #[serde(default = "default_vreman_constant")]
+    pub vreman_constant: Float,
+}
+
+impl Default for LesConfig {
+    fn default() -> Self {
+        Self {
+            model: LesModel::default(),
+            smagorinsky_constant: default_smagorinsky_constant(),
+            wale_constant: default_wale_constant(),
+            vreman_constant: default_vreman_constant(),
+        }
+    }
+}
+
+fn default_smagorinsky_constant() -> Float {
+    0.17
+}
+
+fn default_wale_constant() -> Float {
+    0.5
+}
+
+fn default_vreman_constant() -> Float {
+    0.07
+}
+
+/// Subgrid-scale eddy-viscosity model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LesModel {
+    /// No subgrid model (direct simulation)
+    #[default]
+    None = 0,
+    /// Smagorinsky model with the strain rate from the non-equilibrium moments
+    Smagorinsky = 1,
+    /// Wall-adapting local eddy viscosity, from finite-difference velocity gradients
+    Wale = 2,
+    /// Vreman model, from finite-difference velocity gradients
+    Vreman = 3,
+}
+
+/// Discrete velocity set used by the simulation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+pub enum LatticeType {
+    /// Two-dimensional model, requires `domain.nz == 1`
+    #[serde(alias = "d2q9")]
+    D2Q9,
+    #[serde(alias = "d3q15")]
+    D3Q15,
+    #[serde(alias = "d3q19")]
+    D3Q19,
+    #[default]
+    #[serde(alias = "d3q27")]
+    D3Q27,
+}
+
+impl LatticeType {
+    pub fn lattice(&self) -> &'static dyn Lattice {
+        match self {
+            LatticeType::D2Q9 => &D2Q9,
+            LatticeType::D3Q15 => &D3Q15,
+            LatticeType::D3Q19 => &D3Q19,
+            LatticeType::D3Q27 => &D3Q27,
+        }
+    }
+}
+
+/// Compute backend used to advance the lattice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendType {
+    /// WGPU compute shaders
+    #[default]
+    Gpu,
+    /// Multithreaded reference implementation on the host
+    Cpu,
+}
+
+impl std::str::FromStr for BackendType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "gpu" => Ok(BackendType::Gpu),
+            "cpu" => Ok(BackendType::Cpu),
+            other => anyhow::bail!("Unknown backend '{}' (expected 'gpu' or 'cpu')", other),
+        }
+    }
+}
+
+/// Domain face names in `LatticePoint::face` order
+pub const FACE_NAMES: [&str; 6] = ["x_min", "x_max", "y_min", "y_max", "z_min", "z_max"];
+
+/// `LatticePoint::face` of the STL body's solid nodes, whose parameters
+/// follow those of the six domain faces
+pub const BODY_FACE: u32 = 6;
+
+/// Boundary conditions of the six domain faces.
+///
+/// Faces without an entry keep the original layout: an inlet at x_min using
+/// the `inlet` scheme, an outlet at x_max using the `outlet` scheme and
+/// clamped streaming on the remaining faces.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BoundaryConfig {
+    #[serde(default)]
+    pub inlet: InletScheme, // "equilibrium" or "zou_he"
+    /// Time dependence of the velocity inlets, constant when unset
+    #[serde(default)]
+    pub inlet_waveform: Option<InletWaveform>,
+    /// Spatial shape of the velocity inlets, uniform when unset
+    #[serde(default)]
+    pub inlet_profile: Option<InletProfile>,
+    #[serde(default)]
+    pub outlet: OutletScheme, // "zero_gradient", "pressure" or "convective"
+    /// Density imposed by pressure outlets (p = cs^2 rho), defaults to `physics.density`
+    #[serde(default)]
+    pub outlet_density: Option<Float>,
+    /// Convection velocity U of convective outlets (lattice units, > 0); when
+    /// unset the local outflow velocity of the interior neighbour is used
+    #[serde(default)]
+    pub convection_velocity: Option<Float>,
+    /// Tangential velocity of the STL body's surface (lattice units); when
+    /// set the body uses moving-wall bounce-back
+    #[serde(default)]
+    pub body_velocity: Option<[Float; 3]>,
+    /// Interpolated (Bouzidi) bounce-back on the STL body, placing the wall
+    /// where each link crosses the surface instead of halfway between nodes
+    #[serde(default)]
+    pub interpolated_bounce_back: bool,
+    #[serde(default)]
+    pub x_min: Option<FaceBoundary>,
+    #[serde(default)]
+    pub x_max: Option<FaceBoundary>,
+    #[serde(default)]
+    pub y_min: Option<FaceBoundary>,
+    #[serde(default)]
+    pub y_max: Option<FaceBoundary>,
+    #[serde(default)]
+    pub z_min: Option<FaceBoundary>,
+    #[serde(default)]
+    pub z_max: Option<FaceBoundary>,
+}
+
+impl BoundaryConfig {
+    /// Boundary of each face in `FACE_NAMES` order, with the `inlet` and
+    /// `outlet` settings filling in unset x faces
+    pub fn faces(&self) -> [Option<FaceBoundary>; 6] {
+        let x_min = self.x_min.unwrap_or(FaceBoundary::VelocityInlet { velocity: None, scheme: self.inlet });
+        let x_max = self.x_max.unwrap_or(match self.outlet {
+            OutletScheme::ZeroGradient => FaceBoundary::Outflow,
+            OutletScheme::Pressure => FaceBoundary::PressureOutlet { density: self.outlet_density },
+            OutletScheme::Convective => FaceBoundary::ConvectiveOutflow {
+                convection_velocity: self.convection_velocity,
+            },
+        });
+        [Some(x_min), Some(x_max), self.y_min, self.y_max, self.z_min, self.z_max]
+    }
+
+    /// Periodicity of the x, y and z axes. Both faces of a periodic axis
+    /// must be periodic.
+    pub fn periodic_axes(&self) -> anyhow::Result<[bool; 3]> {
+        let faces = self.faces();
+        let mut periodic = [false; 3];
+        for (axis, periodic) in periodic.iter_mut().enumerate() {
+            let (low, high) = (2 * axis, 2 * axis + 1);
+            let is_periodic = |face: usize| faces[face] == Some(FaceBoundary::Periodic);
+            anyhow::ensure!(
+                is_periodic(low) == is_periodic(high),
+                "Periodic boundaries must be set on both {} and {}",
+                FACE_NAMES[low],
+                FACE_NAMES[high]
+            );
+            *periodic = is_periodic(low);
+        }
+        Ok(periodic)
+    }
+
+    /// Per-face parameters as stored on the GPU (see `FaceBoundary::values`),
+    /// followed by the body velocity at `BODY_FACE`
+    pub fn face_values(&self, physics: &PhysicsConfig) -> [[Float; 4]; 7] {
+        let faces = self.faces().map(|face| face.map_or([0.0; 4], |face| face.values(physics)));
+        let [u, v, w] = self.body_velocity.unwrap_or([0.0; 3]);
+        [faces[0], faces[1], faces[2], faces[3], faces[4], faces[5], [u, v, w, 0.0]]
+    }
+
+    /// `LatticePoint::boundary` scheme of the STL body's solid nodes
+    pub fn body_scheme(&self) -> WallScheme {
+        if self.body_velocity.is_some() { WallScheme::MovingWall } else { WallScheme::BounceBack }
+    }
+}
+
+/// Boundary condition of one domain face
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FaceBoundary {
+    /// Prescribed velocity, defaulting to `physics.inlet_velocity`
+    VelocityInlet {
+        #[serde(default)]
+        velocity: Option<[Float; 3]>,
+        #[serde(default)]
+        scheme: InletScheme,
+    },
+    /// Zou-He pressure outlet, defaulting to `physics.density`
+    PressureOutlet {
+        #[serde(default)]
+        density: Option<Float>,
+    },
+    /// Zero-gradient outflow copying the interior neighbour
+    #[serde(deserialize_with = "no_fields")]
+    Outflow,
+    /// Convective outflow, with the local outflow velocity unless given
+    ConvectiveOutflow {
+        #[serde(default)]
+        convection_velocity: Option<Float>,
+    },
+    /// Solid wall (bounce-back)
+    #[serde(deserialize_with = "no_fields")]
+    NoSlip,
+    /// Free-slip wall (specular reflection), for symmetry planes and far-field
+    /// lateral boundaries
+    #[serde(deserialize_with = "no_fields")]
+    FreeSlip,
+    /// Wall moving tangentially with `velocity` (lattice units), e.g. the lid
+    /// of a cavity or a Couette plate
+    MovingWall { velocity: [Float; 3] },
+    /// Periodic, must be set on both faces of an axis
+    #[serde(deserialize_with = "no_fields")]
+    Periodic,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl FaceBoundary {
+    /// Node type and `LatticePoint::boundary` scheme of the face's nodes
+    pub fn node_type(&self) -> (u32, u32) {
+        match self {
+            FaceBoundary::VelocityInlet { scheme, .. } => (2, *scheme as u32),
+            FaceBoundary::PressureOutlet { .. } => (3, OutletScheme::Pressure as u32),
+            FaceBoundary::Outflow => (3, OutletScheme::ZeroGradient as u32),
+            FaceBoundary::ConvectiveOutflow { .. } => (3, OutletScheme::Convective as u32),
+            FaceBoundary::NoSlip => (1, WallScheme::BounceBack as u32),
+            FaceBoundary::FreeSlip => (1, WallScheme::FreeSlip as u32),
+            FaceBoundary::MovingWall { .. } => (1, WallScheme::MovingWall as u32),
+            FaceBoundary::Periodic => (0, 0), // Fluid, wrapped by the streaming pass
+        }
+    }
+
+    /// Face parameters: xyz is the inlet or wall velocity, w the outlet
+    /// density, the convection velocity (<= 0: local outflow velocity) or
+    /// the inlet waveform factor applied to per-node inlet profiles
+    pub fn values(&self, physics: &PhysicsConfig) -> [Float; 4] {
+        match *self {
+            FaceBoundary::VelocityInlet { velocity, .. } => {
+                let [u, v, w] = velocity.unwrap_or(physics.inlet_velocity);
+                [u, v, w, 1.0]
+            }
+            FaceBoundary::MovingWall { velocity: [u, v, w] } => [u, v, w, 0.0],
+            FaceBoundary::PressureOutlet { density } => [0.0, 0.0, 0.0, density.unwrap_or(physics.density)],
+            FaceBoundary::ConvectiveOutflow { convection_velocity } => [0.0, 0.0, 0.0, convection_velocity.unwrap_or(0.0)],
+            FaceBoundary::Outflow | FaceBoundary::NoSlip | FaceBoundary::FreeSlip | FaceBoundary::Periodic => [0.0; 4],
+        }
+    }
+}
+
+/// How inlet nodes impose the inlet velocity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InletScheme {
+    /// Overwrite the node with a relaxed equilibrium in the collision pass
+    #[default]
+    Equilibrium = 0,
+    /// Zou-He non-equilibrium bounce-back of the unknown populations in the boundary pass
+    ZouHe = 1,
+}
+
+/// Time dependence of the inlet velocity, as a factor on the velocity of
+/// every velocity inlet. Times are in time steps, or in seconds when the
+/// config is in SI units (see `UnitConverter::lattice_config`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum InletWaveform {
+    /// Linear ramp from 0 to 1 over `duration`, then constant, which avoids
+    /// the pressure wave of an impulsive start
+    Ramp { duration: Float },
+    /// Pulsation 1 + amplitude sin(2π t / period + phase), e.g. for Womersley flow
+    Sinusoidal {
+        period: Float,
+        amplitude: Float,
+        #[serde(default)]
+        phase: Float,
+    },
+    /// Factor interpolated linearly between the `time, factor` rows of a CSV
+    /// file. Outside the table the end values are held, or with `repeat` the
+    /// table is repeated with its duration as period.
+    Table {
+        file: String,
+        #[serde(default)]
+        repeat: bool,
+        /// Factor from the time column to the time unit of the config, e.g.
+        /// 0.001 for a table in milliseconds (default 1)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time_scale: Option<Float>,
+    },
+}
+
+/// Spatial shape of the inlet velocity. Parabolic and power-law profiles
+/// scale the face's inlet velocity, which they reach on the centreline, with
+/// the walls half-way outside the outermost inlet nodes; axes one node thick
+/// (z in 2D) are left uniform.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum InletProfile {
+    /// Fully developed laminar profile, 1 - (r/R)^2 across each axis of a
+    /// rectangular inlet or radially across a circular one
+    Parabolic {
+        #[serde(default)]
+        cross_section: CrossSection,
+    },
+    /// Turbulent power-law profile (1 - r/R)^(1/n), with n = 7 by default
+    PowerLaw {
+        #[serde(default)]
+        cross_section: CrossSection,
+        #[serde(default = "default_power_law_exponent")]
+        exponent: Float,
+    },
+    /// Velocities (lattice units) read from a file and mapped onto each inlet
+    /// node from the nearest sample: CSV rows `a, b, u, v, w` with a and b the
+    /// face's in-plane coordinates in x, y, z order, or a legacy ASCII VTK
+    /// file with points and a velocity vector field, such as a slice of this
+    /// solver's output
+    File { file: String },
+}
+
+fn default_power_law_exponent() -> Float {
+    7.0
+}
+
+/// Inlet cross-section of parabolic and power-law profiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossSection {
+    /// The bounding rectangle of the inlet nodes
+    #[default]
+    Rectangular,
+    /// The circle inscribed in the bounding rectangle of the inlet nodes
+    Circular,
+}
+
+/// How outlet nodes are updated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutletScheme {
+    /// Copy the populations of the upstream neighbour
+    #[default]
+    ZeroGradient = 0,
+    /// Zou-He boundary imposing `outlet_density`, with zero tangential velocity
+    Pressure = 1,
+    /// Convective outflow df/dt + U df/dn = 0, which lets vortices leave the domain
+    Convective = 2,
+}
+
+/// How solid nodes reflect the populations streamed into them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WallScheme {
+    /// Reverse every population (no-slip)
+    #[default]
+    BounceBack = 0,
+    /// Reverse only the wall-normal component (free-slip)
+    FreeSlip = 1,
+    /// Bounce-back with the momentum of a tangentially moving wall
+    MovingWall = 2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct OutputConfig {
     pub output_directory: String,
     pub output_frequency: usize,
     pub output_format: String, // "vtk" or "vtu"
+    /// Write the LES eddy viscosity as an extra field
+    #[serde(default)]
+    pub eddy_viscosity: bool,
+    /// Iterations between checkpoints of the full lattice state (see
+    /// `checkpoint::Checkpoint`), none by default
+    #[serde(default)]
+    pub checkpoint_frequency: Option<usize>,
+}
+
+/// Adapter selection for the GPU backend
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GpuConfig {
+    /// Comma separated wgpu backends, e.g. "vulkan,gl", or "all"
+    #[serde(default = "default_gpu_backends")]
+    pub backends: String,
+    #[serde(default)]
+    pub power_preference: PowerPreference,
+    /// Only use an adapter whose name contains this substring (case-insensitive)
+    #[serde(default)]
+    pub adapter_name: Option<String>,
+    /// Retry on a software adapter (e.g. lavapipe/llvmpipe) when no hardware adapter is found
+    #[serde(default = "default_allow_fallback")]
+    pub allow_fallback: bool,
+}
+
+impl Default for GpuConfig {
+    fn default() -> Self {
+        Self {
+            backends: default_gpu_backends(),
+            power_preference: PowerPreference::default(),
+            adapter_name: None,
+            allow_fallback: default_allow_fallback(),
+        }
+    }
+}
+
+fn default_gpu_backends() -> String {
+    "all".to_string()
+}
+
+fn default_allow_fallback() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerPreference {
+    #[default]
+    HighPerformance,
+    LowPower,
+    None,
+}
+
+impl std::str::FromStr for PowerPreference {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "high" | "high_performance" => Ok(PowerPreference::HighPerformance),
+            "low" | "low_power" => Ok(PowerPreference::LowPower),
+            "none" => Ok(PowerPreference::None),
+            other => anyhow::bail!("Unknown power preference '{}' (expected 'high', 'low' or 'none')", other),
+        }
+    }
+}
+
+/// Config file format, chosen by file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// `.toml` and `.yaml`/`.yml` files, JSON otherwise
+    pub fn from_path(path: &str) -> Self {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
 }
 
 impl Config {
+    /// Load a JSON, TOML or YAML config file (see `ConfigFormat::from_path`).
+    ///
+    /// Relative paths of inlet waveform tables and profile files are taken
+    /// relative to the directory of the config file.
     pub fn from_file(path: &str) -> anyhow::Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: Config = serde_json::from_str(&content)?;
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read config {}: {}", path, e))?;
+        let mut config = Self::parse(&content, ConfigFormat::from_path(path))
+            .map_err(|e| anyhow::anyhow!("Invalid config {}: {}", path, e))?;
+        if let Some(directory) = std::path::Path::new(path).parent() {
+            config.resolve_paths(directory);
+        }
         Ok(config)
     }
 
+    /// Make the relative input file paths of the config relative to `directory`
+    fn resolve_paths(&mut self, directory: &std::path::Path) {
+        let boundaries = &mut self.boundaries;
+        let files = [
+            match &mut boundaries.inlet_waveform {
+                Some(InletWaveform::Table { file, .. }) => Some(file),
+                _ => None,
+            },
+            match &mut boundaries.inlet_profile {
+                Some(InletProfile::File { file }) => Some(file),
+                _ => None,
+            },
+        ];
+        for file in files.into_iter().flatten() {
+            if std::path::Path::new(file.as_str()).is_relative() {
+                *file = directory.join(&*file).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    /// Parse a config in the given format.
+    ///
+    /// Values of the wrong type and unknown fields (typos such as
+    /// `physics.viscosty`) are errors naming the path of the field. A
+    /// top-level `$schema` key for editors is allowed.
+    pub fn parse(content: &str, format: ConfigFormat) -> anyhow::Result<Self> {
+        let mut value: serde_json::Value = match format {
+            ConfigFormat::Json => serde_json::from_str(content)?,
+            ConfigFormat::Toml => toml::from_str(content)?,
+            ConfigFormat::Yaml => serde_yaml::from_str(content)?,
+        };
+
+        if let Some(fields) = value.as_object_mut() {
+            fields.remove("$schema");
+        }
+
+        serde_path_to_error::deserialize(&value).map_err(|e| {
+            let path = e.path().to_string();
+            let message = e.inner().to_string();
+            match unknown_field(&path, &message) {
+                Some(unknown) => anyhow::anyhow!("{}", unknown),
+                None if path == "." => anyhow::anyhow!("{}", message),
+                None => anyhow::anyhow!("{}: {}", path, message),
+            }
+        })
+    }
+
+    /// Write the config, in the format of the file extension
+    pub fn to_file(&self, path: &str) -> anyhow::Result<()> {
+        let content = match ConfigFormat::from_path(path) {
+            ConfigFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+            ConfigFormat::Toml => toml::to_string_pretty(self)?,
+            ConfigFormat::Yaml => serde_yaml::to_string(self)?,
+        };
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// JSON Schema of config files, for editor completion and checks
+    pub fn json_schema() -> serde_json::Value {
+        let mut schema = schemars::schema_for!(Config).to_value();
+        // Unknown fields are errors, except the schema reference itself
+        schema["properties"]["$schema"] = serde_json::json!({ "type": "string" });
+        schema
+    }
+
+    /// Set one field from a `path=value` assignment such as `domain.nx=200`.
+    ///
+    /// The path names nested fields (and array indices) with dots, the value
+    /// is parsed as JSON and taken as a plain string when that fails or does
+    /// not fit the field, so `output.output_directory=2024` sets a string.
+    /// Unknown fields and values of the wrong type are errors.
+    pub fn apply_override(&mut self, assignment: &str) -> anyhow::Result<()> {
+        let (path, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid override '{}' (expected path=value)", assignment))?;
+        let (path, value) = (path.trim(), value.trim());
+        let raw = serde_json::Value::String(value.to_string());
+
+        let config = match serde_json::from_str::<serde_json::Value>(value) {
+            Ok(parsed) if !parsed.is_string() => match self.with_override(path, parsed) {
+                Ok(config) => config,
+                Err(e) => self.with_override(path, raw).map_err(|_| e)?,
+            },
+            Ok(parsed) => self.with_override(path, parsed)?,
+            Err(_) => self.with_override(path, raw)?,
+        };
+        *self = config;
+        Ok(())
+    }
+
+    /// Copy of the config with the field at dotted `path` set to `value`
+    fn with_override(&self, path: &str, value: serde_json::Value) -> anyhow::Result<Config> {
+        let mut root = serde_json::to_value(self)?;
+        let mut node = &mut root;
+        for key in path.split('.') {
+            // Optional sections left out of the config are created on demand
+            if node.is_null() {
+                *node = serde_json::Value::Object(Default::default());
+            }
+            node = match node {
+                // Unknown fields are rejected when the config is parsed back
+                serde_json::Value::Object(fields) => fields.entry(key).or_insert(serde_json::Value::Null),
+                serde_json::Value::Array(items) => key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get_mut(index))
+                    .ok_or_else(|| anyhow::anyhow!("Invalid index '{}' in override '{}'", key, path))?,
+                _ => anyhow::bail!("Unknown config field '{}' in override '{}'", key, path),
+            };
+        }
+        *node = value;
+
+        serde_json::from_value(root).map_err(|e| match e.to_string().starts_with("unknown field") {
+            true => anyhow::anyhow!("Unknown config field in override '{}'", path),
+            false => anyhow::anyhow!("Invalid value in override '{}': {}", path, e),
+        })
+    }
+
+    /// Pre-flight checks of the config (see `validation::validate`), with
+    /// `body` the bounding box of the geometry when known
+    pub fn validate(&self, body: Option<[[Float; 3]; 2]>) -> Validation {
+        validation::validate(self, body)
+    }
+
+    /// Whether the selected lattice is a planar (2D) model
+    pub fn is_2d(&self) -> bool {
+        self.simulation.lattice.lattice().dimensions() == 2
+    }
+
+    /// Characteristic length in the units of `domain.dx`: `units.length`,
+    /// the resolved `physics.characteristic_length`, or one grid spacing
+    pub fn characteristic_length(&self) -> Float {
+        match (self.units.as_ref().and_then(|units| units.length), self.physics.characteristic_length) {
+            (Some(length), _) => length,
+            (None, Some(CharacteristicLength::Length(length))) => length,
+            _ => self.domain.dx,
+        }
+    }
+
+    /// Replace a characteristic length taken from the geometry with the
+    /// extent of `body`, its bounding box (min and max corners)
+    pub fn resolve_characteristic_length(&mut self, body: Option<[[Float; 3]; 2]>) -> anyhow::Result<()> {
+        let Some(CharacteristicLength::FromGeometry { from_geometry: axis }) = self.physics.characteristic_length else {
+            return Ok(());
+        };
+        let [low, high] = body.ok_or_else(|| {
+            anyhow::anyhow!("physics.characteristic_length is taken from the geometry, but its bounds are unknown")
+        })?;
+        let length = high[axis as usize] - low[axis as usize];
+        anyhow::ensure!(
+            length > 0.0,
+            "The geometry has no extent along {:?} for physics.characteristic_length",
+            axis
+        );
+        log::info!("Characteristic length: {} (geometry extent along {:?})", length, axis);
+        self.physics.characteristic_length = Some(CharacteristicLength::Length(length));
+        Ok(())
+    }
+
+    /// Relaxation time of the configured viscosity, see `UnitConverter` for
+    /// configs in physical units.
+    ///
+    /// Without an explicit `simulation.tau` the lattice viscosity is
+    /// `physics.viscosity` or U L / Re, with U the inlet speed and L the
+    /// characteristic length in grid spacings.
     pub fn calculate_tau(&self) -> Float {
-        if let Some(tau) = self.simulation.tau {
+        if self.units.is_some() {
+            UnitConverter::new(self).tau
+        } else if let Some(tau) = self.simulation.tau {
             tau
         } else {
-            // Calculate tau from Reynolds number and domain characteristics
-            let characteristic_length = self.domain.dx;
-            let characteristic_velocity = self.physics.inlet_velocity[0].max(
-                self.physics.inlet_velocity[1].max(self.physics.inlet_velocity[2])
-            );
-            
-            let viscosity = if let Some(nu) = self.physics.viscosity {
-                nu
-            } else {
-                (characteristic_velocity * characteristic_length) / self.physics.reynolds_number
-            };
-            
-            let cs2 = 1.0 / 3.0; // Speed of sound squared in lattice units
-            3.0 * viscosity / cs2 + 0.5
+            let length = self.characteristic_length() / self.domain.dx;
+            let viscosity = self
+                .physics
+                .viscosity
+                .unwrap_or(self.inlet_speed() * length / self.physics.reynolds_number);
+            viscosity / self.simulation.lattice.lattice().cs2() + 0.5
         }
     }
+
+    /// Reynolds number U L / ν of the simulated flow, with ν set by the
+    /// relaxation time
+    pub fn effective_reynolds_number(&self) -> Float {
+        if self.units.is_some() {
+            return UnitConverter::new(self).reynolds_number;
+        }
+        let viscosity = self.simulation.lattice.lattice().cs2() * (self.calculate_tau() - 0.5);
+        self.inlet_speed() * (self.characteristic_length() / self.domain.dx) / viscosity
+    }
+
+    /// Magnitude of `physics.inlet_velocity`
+    pub fn inlet_speed(&self) -> Float {
+        let [u, v, w] = self.physics.inlet_velocity;
+        (u * u + v * v + w * w).sqrt()
+    }
+}
+
+/// Unit variants of internally tagged enums otherwise ignore extra fields
+fn no_fields<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<(), D::Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct NoFields {}
+
+    NoFields::deserialize(deserializer).map(|_| ())
+}
+
+/// Rewrite serde's unknown field error at `path` to name the full path of
+/// the field, with the fields expected instead
+fn unknown_field(path: &str, message: &str) -> Option<String> {
+    let (field, expected) = message.strip_prefix("unknown field `")?.split_once('`')?;
+    let field = match path {
+        "." => field.to_string(),
+        // Struct fields are part of the path, fields of enum variants are not
+        path if path == field || path.ends_with(&format!(".{}", field)) => path.to_string(),
+        path => format!("{}.{}", path, field),
+    };
+    let expected = expected.trim_start_matches(", ").replace("expected one of ", "expected one of: ").replace('`', "");
+    Some(format!("unknown field `{}` ({})", field, expected))
 }
@@ -0,0 +1,419 @@
+use anyhow::Result;
+use log::info;
+use crate::{
+    boundary::{
+        convective_outflow, free_slip, interior_neighbor, interpolated_bounce_back, zou_he_pressure,
+        zou_he_velocity, WallLink,
+    },
+    collision::CollisionOperator,
+    config::{Config, InletScheme, OutletScheme, WallScheme},
+    turbulence::{velocity_gradient, SubgridModel, VelocityGradient},
+    lattice::{Lattice, LatticePoint},
+    Float,
+};
+
+/// Multithreaded host implementation of the LBM step.
+///
+/// Mirrors the collision, streaming and boundary passes of the WGSL shaders
+/// one-to-one so that it can run on machines without a GPU and serve as the
+/// reference the GPU results are checked against.
+pub struct CPUContext {
+    lattice: Vec<LatticePoint>,
+    temp: Vec<LatticePoint>,
+    model: &'static dyn Lattice,
+
+    collision: CollisionOperator,
+    subgrid: Option<SubgridModel>,
+    density: Float,
+    /// Per-face boundary parameters (see `FaceBoundary::values`) and the body velocity
+    faces: [[Float; 4]; 7],
+    /// Axes whose opposite faces are joined
+    periodic: [bool; 3],
+    /// Links from fluid nodes into bounce-back and moving walls
+    links: Vec<WallLink>,
+    /// Full-strength inlet velocity of every node for spatial inlet profiles,
+    /// scaled by the w component of the face values
+    profile: Option<Vec<[Float; 3]>>,
+
+    // Dimensions
+    nx: usize,
+    ny: usize,
+    nz: usize,
+
+    threads: usize,
+}
+
+impl CPUContext {
+    pub fn new(config: &Config) -> Result<Self> {
+        let nx = config.domain.nx;
+        let ny = config.domain.ny;
+        let nz = config.domain.nz;
+
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(nz.max(1));
+        info!("CPU backend using {} worker threads", threads);
+
+        Ok(Self {
+            lattice: vec![LatticePoint::default(); nx * ny * nz],
+            temp: vec![LatticePoint::default(); nx * ny * nz],
+            model: config.simulation.lattice.lattice(),
+            collision: CollisionOperator::from_config(config)?,
+            subgrid: SubgridModel::from_config(config),
+            density: config.physics.density,
+            faces: config.boundaries.face_values(&config.physics),
+            periodic: config.boundaries.periodic_axes()?,
+            links: Vec::new(),
+            profile: None,
+            nx,
+            ny,
+            nz,
+            threads,
+        })
+    }
+
+    pub fn upload_lattice_data(&mut self, data: &[LatticePoint]) -> Result<()> {
+        anyhow::ensure!(
+            data.len() == self.lattice.len(),
+            "Lattice size mismatch: expected {} nodes, got {}",
+            self.lattice.len(),
+            data.len()
+        );
+        self.lattice.copy_from_slice(data);
+        Ok(())
+    }
+
+    pub fn upload_wall_links(&mut self, links: &[WallLink]) -> Result<()> {
+        let nodes = self.lattice.len() as u32;
+        anyhow::ensure!(
+            links.iter().all(|link| link.node < nodes && link.solid < nodes && (link.direction as usize) < self.model.q()),
+            "Wall link outside the lattice"
+        );
+        self.links = links.to_vec();
+        Ok(())
+    }
+
+    pub fn upload_inlet_profile(&mut self, profile: &[[Float; 3]]) -> Result<()> {
+        anyhow::ensure!(
+            profile.len() == self.lattice.len(),
+            "Inlet profile size mismatch: expected {} nodes, got {}",
+            self.lattice.len(),
+            profile.len()
+        );
+        self.profile = Some(profile.to_vec());
+        Ok(())
+    }
+
+    pub fn update_face_values(&mut self, faces: &[[Float; 4]; 7]) {
+        self.faces = *faces;
+    }
+
+    pub fn read_lattice_data(&self) -> Vec<LatticePoint> {
+        self.lattice.clone()
+    }
+
+    pub fn step(&mut self) -> Result<()> {
+        let start_time = std::time::Instant::now();
+
+        let (nx, ny, nz) = (self.nx, self.ny, self.nz);
+        let threads = self.threads;
+        let model = self.model;
+
+        // Collision step - read from lattice, write to temp
+        {
+            let lattice = &self.lattice;
+            let (collision, subgrid) = (self.collision, self.subgrid);
+            let (density, faces, periodic) = (self.density, self.faces, self.periodic);
+            let profile = self.profile.as_deref();
+            for_each_slice_chunk(&mut self.temp, nx * ny, threads, |first, chunk| {
+                for (offset, out) in chunk.iter_mut().enumerate() {
+                    let idx = first + offset;
+                    let gradient = |center| velocity_gradient(model, lattice, idx, center, [nx, ny, nz], periodic);
+                    let face = node_face_values(profile, &faces, idx, &lattice[idx]);
+                    *out = collide_node(model, &lattice[idx], &collision, subgrid.as_ref(), density, face, gradient);
+                }
+            });
+        }
+
+        // Streaming step - read from temp, write to lattice
+        {
+            let temp = &self.temp;
+            let periodic = self.periodic;
+            for_each_slice_chunk(&mut self.lattice, nx * ny, threads, |first, chunk| {
+                for (offset, out) in chunk.iter_mut().enumerate() {
+                    let idx = first + offset;
+                    let x = idx % nx;
+                    let y = (idx / nx) % ny;
+                    let z = idx / (nx * ny);
+
+                    // Copy node properties
+                    *out = temp[idx];
+
+                    // Streaming step: f_i(x, t + 1) = f_i(x - c_i, t) (pull-based)
+                    for i in 0..model.q() {
+                        let source_idx = source_index(model, [x, y, z], i, [nx, ny, nz], periodic);
+                        out.f[i] = temp[source_idx].f[i];
+                    }
+                }
+            });
+        }
+
+        // Bounce-back - the populations entering fluid nodes from the wall are rebuilt
+        // from the post-collision populations, which are still in temp
+        for link in &self.links {
+            let solid = &self.lattice[link.solid as usize];
+            let wall_velocity = if solid.boundary == WallScheme::MovingWall as u32 {
+                let [u, v, w, _] = self.faces[solid.face as usize];
+                [u, v, w]
+            } else {
+                [0.0; 3]
+            };
+            let f = interpolated_bounce_back(
+                model,
+                &self.temp,
+                link,
+                [nx, ny, nz],
+                self.periodic,
+                self.density,
+                wall_velocity,
+            );
+            self.lattice[link.node as usize].f[model.opposite()[link.direction as usize]] = f;
+        }
+
+        // Boundary conditions - evaluated from the streamed lattice into temp,
+        // which still holds the collision-pass copy of the boundary nodes
+        // (their previous-step populations), then copied back
+        {
+            let lattice = &self.lattice;
+            let (density, faces) = (self.density, self.faces);
+            let profile = self.profile.as_deref();
+            for_each_slice_chunk(&mut self.temp, nx * ny, threads, |first, chunk| {
+                for (offset, out) in chunk.iter_mut().enumerate() {
+                    let idx = first + offset;
+                    if lattice[idx].node_type != 0 {
+                        let face = node_face_values(profile, &faces, idx, &lattice[idx]);
+                        *out = boundary_node(model, lattice, out, idx, [nx, ny, nz], density, face);
+                    }
+                }
+            });
+
+            let temp = &self.temp;
+            for_each_slice_chunk(&mut self.lattice, nx * ny, threads, |first, chunk| {
+                for (offset, out) in chunk.iter_mut().enumerate() {
+                    if out.node_type != 0 {
+                        *out = temp[first + offset];
+                    }
+                }
+            });
+        }
+
+        let elapsed = start_time.elapsed();
+        if elapsed.as_millis() > 10 {
+            log::debug!("CPU step took: {:.2}ms", elapsed.as_secs_f64() * 1000.0);
+        }
+
+        Ok(())
+    }
+}
+
+/// Split `data` into runs of whole z-slices and process them on scoped
+/// worker threads. The closure receives the global index of the first node
+/// in its chunk.
+fn for_each_slice_chunk<F>(data: &mut [LatticePoint], slice_len: usize, threads: usize, f: F)
+where
+    F: Fn(usize, &mut [LatticePoint]) + Sync,
+{
+    let slices = data.len() / slice_len.max(1);
+    let slices_per_chunk = slices.div_ceil(threads.max(1)).max(1);
+    let chunk_len = (slice_len * slices_per_chunk).max(1);
+
+    std::thread::scope(|scope| {
+        for (n, chunk) in data.chunks_mut(chunk_len).enumerate() {
+            let f = &f;
+            scope.spawn(move || f(n * chunk_len, chunk));
+        }
+    });
+}
+
+/// Boundary parameters of the face of node `idx`, with the velocity of inlet
+/// nodes taken from the inlet profile scaled by the waveform factor
+fn node_face_values(profile: Option<&[[Float; 3]]>, faces: &[[Float; 4]; 7], idx: usize, node: &LatticePoint) -> [Float; 4] {
+    let [u, v, w, factor] = faces[node.face as usize];
+    match profile {
+        Some(profile) if node.node_type == 2 => {
+            let [u, v, w] = profile[idx].map(|c| c * factor);
+            [u, v, w, factor]
+        }
+        _ => [u, v, w, factor],
+    }
+}
+
+/// Collision pass for a single node (see `collision.wgsl`). `gradient`
+/// evaluates the velocity gradient around the node from its updated velocity
+/// and is only called by the gradient-based subgrid models.
+fn collide_node(
+    model: &dyn Lattice,
+    node: &LatticePoint,
+    collision: &CollisionOperator,
+    subgrid: Option<&SubgridModel>,
+    density: Float,
+    face: [Float; 4],
+    gradient: impl FnOnce([Float; 3]) -> VelocityGradient,
+) -> LatticePoint {
+    let mut out = *node;
+
+    match node.node_type {
+        0 => collide_fluid(model, &mut out, collision, subgrid, gradient), // Fluid nodes
+        2 if node.boundary == InletScheme::ZouHe as u32 => {
+            // Zou-He inlet nodes collide like fluid, the velocity is imposed in the boundary pass
+            collide_fluid(model, &mut out, collision, subgrid, gradient);
+        }
+        2 => {
+            // Inlet nodes - prescribed velocity with relaxation
+            let inlet_velocity = [face[0], face[1], face[2]];
+            out.calculate_macroscopic(model);
+            clamp_macroscopic(&mut out, collision.clamps_velocity());
+
+            let relax_factor = 0.9;
+            let relaxed_vel = [
+                relax_factor * inlet_velocity[0] + (1.0 - relax_factor) * out.velocity[0],
+                relax_factor * inlet_velocity[1] + (1.0 - relax_factor) * out.velocity[1],
+                relax_factor * inlet_velocity[2] + (1.0 - relax_factor) * out.velocity[2],
+            ];
+            let relaxed_density = relax_factor * density + (1.0 - relax_factor) * out.density;
+
+            out = LatticePoint::new_equilibrium(model, relaxed_density, relaxed_vel, node.node_type);
+            out.boundary = node.boundary;
+            out.face = node.face;
+        }
+        3 if node.boundary == OutletScheme::Pressure as u32 => {
+            // Pressure outlet nodes collide like fluid, the density is imposed in the boundary pass
+            collide_fluid(model, &mut out, collision, subgrid, gradient);
+        }
+        _ => {} // Solid, zero-gradient outlet, and other nodes - copy unchanged
+    }
+
+    out
+}
+
+/// Fluid collision - BGK, MRT, TRT or cumulant, with the subgrid model
+/// applied to the shear relaxation time
+fn collide_fluid(
+    model: &dyn Lattice,
+    out: &mut LatticePoint,
+    collision: &CollisionOperator,
+    subgrid: Option<&SubgridModel>,
+    gradient: impl FnOnce([Float; 3]) -> VelocityGradient,
+) {
+    out.calculate_macroscopic(model);
+    clamp_macroscopic(out, collision.clamps_velocity());
+
+    // Shear relaxation, adjusted by the subgrid model
+    let tau0 = collision.tau();
+    let tau = subgrid.map_or(tau0, |les| {
+        let gradient = if les.uses_velocity_gradient() { gradient(out.velocity) } else { [[0.0; 3]; 3] };
+        les.effective_tau(model, out, &gradient, tau0)
+    });
+    out.eddy_viscosity = model.cs2() * (tau - tau0);
+    collision.with_tau(tau).apply(model, out);
+}
+
+/// Same density and velocity safeguards as `calculate_macroscopic` in the
+/// collision shader
+fn clamp_macroscopic(point: &mut LatticePoint, clamp_velocity: bool) {
+    if point.density <= 1e-10 || point.density.is_nan() {
+        point.density = 1.0;
+        point.velocity = [0.0; 3];
+        return;
+    }
+
+    let vel_mag = (point.velocity[0].powi(2) +
+                   point.velocity[1].powi(2) +
+                   point.velocity[2].powi(2)).sqrt();
+    if clamp_velocity && vel_mag > 0.3 {
+        let scale = 0.3 / vel_mag;
+        for v in point.velocity.iter_mut() {
+            *v *= scale;
+        }
+    }
+}
+
+/// Pull-streaming source node, wrapped on periodic axes and clamped to the
+/// domain otherwise (see `streaming.wgsl`)
+fn source_index(
+    model: &dyn Lattice,
+    [x, y, z]: [usize; 3],
+    direction: usize,
+    [nx, ny, nz]: [usize; 3],
+    periodic: [bool; 3],
+) -> usize {
+    let c = model.velocities()[direction];
+    let coordinate = |position: usize, c: i32, size: usize, periodic: bool| {
+        let source = position as i32 - c;
+        if periodic {
+            source.rem_euclid(size as i32) as usize
+        } else {
+            source.clamp(0, size as i32 - 1) as usize
+        }
+    };
+    let sx = coordinate(x, c[0], nx, periodic[0]);
+    let sy = coordinate(y, c[1], ny, periodic[1]);
+    let sz = coordinate(z, c[2], nz, periodic[2]);
+    sx + sy * nx + sz * nx * ny
+}
+
+/// Boundary pass for a single non-fluid node of the streamed `lattice`
+/// (see `boundary.wgsl`). `previous` is the node as copied by the collision
+/// pass and `face` the boundary parameters of its face.
+fn boundary_node(
+    model: &dyn Lattice,
+    lattice: &[LatticePoint],
+    previous: &LatticePoint,
+    idx: usize,
+    dims: [usize; 3],
+    density: Float,
+    face: [Float; 4],
+) -> LatticePoint {
+    let mut point = lattice[idx];
+    let [u, v, w, value] = face;
+    match point.node_type {
+        1 if point.boundary == WallScheme::FreeSlip as u32 => {
+            // Free-slip wall - specular reflection
+            free_slip(model, &mut point);
+        }
+        1 => {
+            // Bounce-back or moving wall - reflected link-wise by the wall links pass,
+            // the node only reports the wall state
+            point.density = density;
+            point.velocity = if point.boundary == WallScheme::MovingWall as u32 { [u, v, w] } else { [0.0; 3] };
+        }
+        2 if point.boundary == InletScheme::ZouHe as u32 => {
+            // Inlet - Zou-He velocity (equilibrium inlets are set in the collision pass)
+            zou_he_velocity(model, &mut point, [u, v, w]);
+        }
+        3 if point.boundary == OutletScheme::Pressure as u32 => {
+            // Outlet - Zou-He pressure
+            zou_he_pressure(model, &mut point, value);
+        }
+        3 if point.boundary == OutletScheme::Convective as u32 => {
+            // Outlet - convective outflow from the previous-step populations
+            let interior = interior_neighbor(idx, point.face, dims).map(|n| &lattice[n]);
+            convective_outflow(model, &mut point, previous, interior, (value > 0.0).then_some(value));
+        }
+        3 => {
+            // Outlet - zero gradient (Neumann BC), copied from a fluid interior
+            // neighbour, otherwise the streamed populations are kept
+            match interior_neighbor(idx, point.face, dims).map(|n| lattice[n]).filter(|n| n.node_type == 0) {
+                Some(neighbor) => {
+                    point.f = neighbor.f;
+                    point.density = neighbor.density;
+                    point.velocity = neighbor.velocity;
+                }
+                None => point.calculate_macroscopic(model),
+            }
+        }
+        _ => {} // Fluid nodes - no modification needed
+    }
+    point
+}
@@ -1,8 +1,24 @@
-use nalgebra::Point3;
+use nalgebra::{Point2, Point3, Vector2, Vector3};
 use stl_io::read_stl;
-use std::collections::HashSet;
-use log::info;
-use crate::config::DomainConfig;
+use std::collections::{HashMap, HashSet};
+use log::{info, warn};
+use crate::{
+    boundary::{wall_links, WallLink},
+    config::{BoundaryConfig, Config, DomainConfig, FaceBoundary, FACE_NAMES},
+    lattice::{Lattice, LatticePoint},
+};
+
+/// Set of `(i, j, k)` node coordinates
+type NodeSet = HashSet<(usize, usize, usize)>;
+
+/// Body surface the solid nodes were voxelized from
+#[derive(Debug, Clone)]
+pub enum Surface {
+    /// STL triangles (3D)
+    Mesh(Vec<[Point3<f32>; 3]>),
+    /// Outline segments in the x-y plane (2D)
+    Outline(Vec<(Point2<f32>, Point2<f32>)>),
+}
 
 #[derive(Debug, Clone)]
 pub struct Geometry {
@@ -11,15 +27,19 @@ pub struct Geometry {
     pub fluid_nodes: HashSet<(usize, usize, usize)>,
     pub inlet_nodes: HashSet<(usize, usize, usize)>,
     pub outlet_nodes: HashSet<(usize, usize, usize)>,
+    /// Domain face (`FACE_NAMES` index) of each inlet, outlet and wall face node
+    pub boundary_faces: HashMap<(usize, usize, usize), u32>,
+    pub surface: Surface,
 }
 
 impl Geometry {
-    pub fn from_stl(stl_path: &str, domain: &DomainConfig) -> anyhow::Result<Self> {
+    pub fn from_stl(stl_path: &str, domain: &DomainConfig, boundaries: &BoundaryConfig) -> anyhow::Result<Self> {
         let mut file = std::fs::File::open(stl_path)?;
         let stl = read_stl(&mut file)?;
         
         let mut solid_nodes = HashSet::new();
         let mut boundary_nodes = HashSet::new();
+        let mut triangles = Vec::with_capacity(stl.faces.len());
         
         // Convert STL mesh to voxelized geometry
         for face in stl.faces {
@@ -31,15 +51,23 @@ impl Geometry {
             
             // Voxelize triangle using scanline algorithm
             Self::voxelize_triangle(&vertices, domain, &mut solid_nodes, &mut boundary_nodes);
+            triangles.push(vertices);
         }
         
         info!("Initial surface voxelization: {} solid nodes", solid_nodes.len());
         
         // Fill interior of closed volumes using flood fill from exterior
-        Self::fill_interior_volumes(domain, &mut solid_nodes);
+        Self::fill_interior_volumes(domain, boundaries.periodic_axes()?, &mut solid_nodes);
         
         info!("After interior filling: {} solid nodes", solid_nodes.len());
         
+        // The voxelized shell reaches past the surface, so interpolated
+        // bounce-back keeps only the nodes whose cell centre lies inside it
+        if boundaries.interpolated_bounce_back {
+            let removed = Self::remove_nodes_outside(&triangles, domain, &mut solid_nodes);
+            info!("Removed {} solid nodes outside the surface", removed);
+        }
+        
         // Generate fluid nodes (all nodes not solid)
         let mut fluid_nodes = HashSet::new();
         for i in 0..domain.nx {
@@ -52,52 +80,322 @@ impl Geometry {
             }
         }
         
-        // Define inlet and outlet based on domain boundaries
-        let mut inlet_nodes = HashSet::new();
-        let mut outlet_nodes = HashSet::new();
+        // Define inlets, outlets and walls from the face boundaries
+        let (inlet_nodes, outlet_nodes, boundary_faces) =
+            Self::mark_faces(domain, boundaries, &mut solid_nodes, &mut fluid_nodes);
         
-        // Inlet at x=0 plane - force ALL these to be inlet nodes
-        for j in 0..domain.ny {
-            for k in 0..domain.nz {
-                let node = (0, j, k);
-                // Remove from solid nodes if accidentally marked
-                solid_nodes.remove(&node);
-                // Ensure it's in fluid nodes
-                fluid_nodes.insert(node);
-                // Add to inlet
-                inlet_nodes.insert(node);
+        // Log geometry statistics
+        info!("Geometry loaded: {} solid, {} fluid, {} inlet, {} outlet nodes", 
+              solid_nodes.len(), fluid_nodes.len(), inlet_nodes.len(), outlet_nodes.len());
+        
+        Ok(Geometry {
+            solid_nodes,
+            boundary_nodes,
+            fluid_nodes,
+            inlet_nodes,
+            outlet_nodes,
+            boundary_faces,
+            surface: Surface::Mesh(triangles),
+        })
+    }
+    
+    /// Load the geometry for the configured dimensionality.
+    ///
+    /// 3D runs voxelize an STL surface. 2D (D2Q9) runs take an outline in the
+    /// x-y plane: either a slice through an STL at `domain.slice_z` or a
+    /// polygon file (see `from_polygon_file`).
+    pub fn load(path: &str, config: &Config) -> anyhow::Result<Self> {
+        if !config.is_2d() {
+            return Self::from_stl(path, &config.domain, &config.boundaries);
+        }
+        
+        anyhow::ensure!(
+            config.domain.nz == 1,
+            "2D lattice {} requires domain.nz = 1 (got {})",
+            config.simulation.lattice.lattice().name(),
+            config.domain.nz
+        );
+        
+        let is_stl = std::path::Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("stl"));
+        if is_stl {
+            Self::from_stl_slice(path, &config.domain, &config.boundaries)
+        } else {
+            Self::from_polygon_file(path, &config.domain, &config.boundaries)
+        }
+    }
+    
+    /// 2D geometry from the intersection of an STL surface with the plane
+    /// `z = domain.slice_z`
+    pub fn from_stl_slice(stl_path: &str, domain: &DomainConfig, boundaries: &BoundaryConfig) -> anyhow::Result<Self> {
+        let mut file = std::fs::File::open(stl_path)?;
+        let stl = read_stl(&mut file)?;
+        
+        let slice_z = domain.slice_z.unwrap_or_else(|| {
+            let min_z = stl.vertices.iter().map(|v| v[2]).fold(f32::INFINITY, f32::min);
+            let max_z = stl.vertices.iter().map(|v| v[2]).fold(f32::NEG_INFINITY, f32::max);
+            0.5 * (min_z + max_z)
+        });
+        
+        let mut segments = Vec::new();
+        for face in &stl.faces {
+            let v = face.vertices.map(|index| {
+                let p = stl.vertices[index];
+                Point3::new(p[0], p[1], p[2])
+            });
+            
+            // Collect the points where the triangle edges cross the plane
+            let mut crossings = Vec::with_capacity(2);
+            for (a, b) in [(v[0], v[1]), (v[1], v[2]), (v[2], v[0])] {
+                if (a.z < slice_z) != (b.z < slice_z) {
+                    let t = (slice_z - a.z) / (b.z - a.z);
+                    crossings.push(Point2::new(a.x + t * (b.x - a.x), a.y + t * (b.y - a.y)));
+                }
+            }
+            if crossings.len() == 2 {
+                segments.push((crossings[0], crossings[1]));
+            }
+        }
+        
+        info!("STL slice at z={}: {} outline segments", slice_z, segments.len());
+        anyhow::ensure!(!segments.is_empty(), "STL has no triangles crossing the plane z={}", slice_z);
+        
+        Ok(Self::from_outline(&segments, domain, boundaries))
+    }
+    
+    /// 2D geometry from a polygon file.
+    ///
+    /// Each non-empty line holds one `x y` vertex (comma or whitespace
+    /// separated), blank lines start a new polygon and `#` starts a comment.
+    /// Polygons are closed implicitly; nested polygons produce holes.
+    pub fn from_polygon_file(path: &str, domain: &DomainConfig, boundaries: &BoundaryConfig) -> anyhow::Result<Self> {
+        let segments = Self::polygon_segments(path)?;
+        Ok(Self::from_outline(&segments, domain, boundaries))
+    }
+    
+    /// Edges of the closed polygons of a polygon file
+    fn polygon_segments(path: &str) -> anyhow::Result<Vec<(Point2<f32>, Point2<f32>)>> {
+        let content = std::fs::read_to_string(path)?;
+        
+        let mut polygons: Vec<Vec<Point2<f32>>> = vec![Vec::new()];
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                if !polygons.last().is_some_and(|p| p.is_empty()) {
+                    polygons.push(Vec::new());
+                }
+                continue;
+            }
+            
+            let values: Vec<f32> = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<f32>())
+                .collect::<Result<_, _>>()
+                .map_err(|e| anyhow::anyhow!("{}:{}: {}", path, line_number + 1, e))?;
+            anyhow::ensure!(values.len() == 2, "{}:{}: expected 'x y'", path, line_number + 1);
+            
+            if let Some(polygon) = polygons.last_mut() {
+                polygon.push(Point2::new(values[0], values[1]));
+            }
+        }
+        
+        let mut segments = Vec::new();
+        for polygon in polygons.iter().filter(|p| p.len() >= 3) {
+            for (n, &a) in polygon.iter().enumerate() {
+                segments.push((a, polygon[(n + 1) % polygon.len()]));
+            }
+        }
+        
+        info!("Polygon file: {} polygons, {} edges", polygons.iter().filter(|p| p.len() >= 3).count(), segments.len());
+        anyhow::ensure!(!segments.is_empty(), "{} contains no polygon with at least 3 vertices", path);
+        Ok(segments)
+    }
+    
+    /// Bounding box (min and max corners) of the STL or polygon file that
+    /// `load` would read, without voxelizing it
+    pub fn surface_bounds(path: &str, config: &Config) -> anyhow::Result<[[f32; 3]; 2]> {
+        let is_stl = std::path::Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("stl"));
+        let points: Vec<[f32; 3]> = if is_stl || !config.is_2d() {
+            let mut file = std::fs::File::open(path)?;
+            read_stl(&mut file)?.vertices.iter().map(|v| [v[0], v[1], v[2]]).collect()
+        } else {
+            Self::polygon_segments(path)?.iter().map(|(a, _)| [a.x, a.y, 0.0]).collect()
+        };
+        anyhow::ensure!(!points.is_empty(), "{} contains no vertices", path);
+        Ok(Self::bounding_box(points))
+    }
+    
+    /// Bounding box (min and max corners) of the body surface
+    pub fn bounds(&self) -> [[f32; 3]; 2] {
+        match &self.surface {
+            Surface::Mesh(triangles) => Self::bounding_box(triangles.iter().flatten().map(|p| [p.x, p.y, p.z])),
+            Surface::Outline(segments) => Self::bounding_box(segments.iter().map(|(a, _)| [a.x, a.y, 0.0])),
+        }
+    }
+    
+    fn bounding_box(points: impl IntoIterator<Item = [f32; 3]>) -> [[f32; 3]; 2] {
+        let mut bounds = [[f32::INFINITY; 3], [f32::NEG_INFINITY; 3]];
+        for point in points {
+            for axis in 0..3 {
+                bounds[0][axis] = bounds[0][axis].min(point[axis]);
+                bounds[1][axis] = bounds[1][axis].max(point[axis]);
+            }
+        }
+        bounds
+    }
+    
+    /// Mark every node whose cell centre lies inside the closed outline
+    /// (even-odd rule) as solid on the single z=0 slice
+    fn from_outline(segments: &[(Point2<f32>, Point2<f32>)], domain: &DomainConfig, boundaries: &BoundaryConfig) -> Self {
+        let mut solid_nodes = HashSet::new();
+        let mut boundary_nodes = HashSet::new();
+        
+        for i in 0..domain.nx {
+            for j in 0..domain.ny {
+                let px = (i as f32 + 0.5) * domain.dx;
+                let py = (j as f32 + 0.5) * domain.dy;
                 
-                // Log inlet node assignment for debugging
-                if j == domain.ny / 2 && k == domain.nz / 2 {
-                    log::debug!("Assigned inlet node at center: ({}, {}, {})", 0, j, k);
+                let mut inside = false;
+                for (a, b) in segments {
+                    if (a.y > py) != (b.y > py) && px < a.x + (py - a.y) * (b.x - a.x) / (b.y - a.y) {
+                        inside = !inside;
+                    }
+                }
+                
+                if inside {
+                    solid_nodes.insert((i, j, 0));
                 }
             }
         }
         
-        // Outlet at x=nx-1 plane - force ALL these to be outlet nodes
-        for j in 0..domain.ny {
-            for k in 0..domain.nz {
-                let node = (domain.nx - 1, j, k);
-                // Remove from solid nodes if accidentally marked
-                solid_nodes.remove(&node);
-                // Ensure it's in fluid nodes
-                fluid_nodes.insert(node);
-                // Add to outlet
-                outlet_nodes.insert(node);
+        // Fluid neighbours of solid nodes are boundary candidates
+        for &(i, j, _) in &solid_nodes {
+            for di in -1i32..=1 {
+                for dj in -1i32..=1 {
+                    let ni = i as i32 + di;
+                    let nj = j as i32 + dj;
+                    if ni >= 0 && ni < domain.nx as i32 && nj >= 0 && nj < domain.ny as i32 &&
+                       !solid_nodes.contains(&(ni as usize, nj as usize, 0)) {
+                        boundary_nodes.insert((ni as usize, nj as usize, 0));
+                    }
+                }
             }
         }
         
-        // Log geometry statistics
-        info!("Geometry loaded: {} solid, {} fluid, {} inlet, {} outlet nodes", 
+        let mut fluid_nodes = HashSet::new();
+        for i in 0..domain.nx {
+            for j in 0..domain.ny {
+                if !solid_nodes.contains(&(i, j, 0)) {
+                    fluid_nodes.insert((i, j, 0));
+                }
+            }
+        }
+        
+        let (inlet_nodes, outlet_nodes, boundary_faces) =
+            Self::mark_faces(domain, boundaries, &mut solid_nodes, &mut fluid_nodes);
+        
+        info!("2D geometry: {} solid, {} fluid, {} inlet, {} outlet nodes", 
               solid_nodes.len(), fluid_nodes.len(), inlet_nodes.len(), outlet_nodes.len());
         
-        Ok(Geometry {
+        Geometry {
             solid_nodes,
             boundary_nodes,
             fluid_nodes,
             inlet_nodes,
             outlet_nodes,
-        })
+            boundary_faces,
+            surface: Surface::Outline(segments.to_vec()),
+        }
+    }
+    
+    /// Nodes on a domain face (`FACE_NAMES` index)
+    fn face_nodes(domain: &DomainConfig, face: u32) -> Vec<(usize, usize, usize)> {
+        let last = [domain.nx - 1, domain.ny - 1, domain.nz - 1];
+        let axis = (face / 2) as usize;
+        let plane = if face.is_multiple_of(2) { 0 } else { last[axis] };
+        
+        let mut nodes = Vec::new();
+        for i in 0..domain.nx {
+            for j in 0..domain.ny {
+                for k in 0..domain.nz {
+                    if [i, j, k][axis] == plane {
+                        nodes.push((i, j, k));
+                    }
+                }
+            }
+        }
+        nodes
+    }
+    
+    /// Apply the face boundaries: inlet and outlet faces force their nodes to
+    /// inlet or outlet nodes, wall faces make them solid. Walls take
+    /// precedence on shared edges, no-slip over moving over free-slip walls;
+    /// otherwise the first face in `FACE_NAMES` order wins.
+    fn mark_faces(
+        domain: &DomainConfig,
+        boundaries: &BoundaryConfig,
+        solid_nodes: &mut NodeSet,
+        fluid_nodes: &mut NodeSet,
+    ) -> (NodeSet, NodeSet, HashMap<(usize, usize, usize), u32>) {
+        let mut inlet_nodes = HashSet::new();
+        let mut outlet_nodes = HashSet::new();
+        let mut boundary_faces = HashMap::new();
+        
+        let faces = boundaries.faces();
+        let mut walls = HashMap::new();
+        for (face, boundary) in faces.iter().enumerate() {
+            let Some(boundary) = boundary.filter(|b| *b != FaceBoundary::Periodic) else { continue };
+            let face = face as u32;
+            
+            // A 2D domain has a single z slice, whose z faces are the whole domain
+            if face >= 4 && domain.nz == 1 {
+                warn!("Ignoring the {} boundary of a 2D domain", FACE_NAMES[face as usize]);
+                continue;
+            }
+            
+            let (node_type, _) = boundary.node_type();
+            for node in Self::face_nodes(domain, face) {
+                if node_type == 1 {
+                    // Stationary walls keep the edges they share with moving or free-slip walls
+                    let rank = match boundary {
+                        FaceBoundary::NoSlip => 0,
+                        FaceBoundary::MovingWall { .. } => 1,
+                        _ => 2,
+                    };
+                    if walls.get(&node).is_none_or(|&(other, _)| rank < other) {
+                        walls.insert(node, (rank, face));
+                    }
+                    continue;
+                }
+                if boundary_faces.contains_key(&node) {
+                    continue;
+                }
+                
+                // Remove from solid nodes if accidentally marked
+                solid_nodes.remove(&node);
+                fluid_nodes.insert(node);
+                boundary_faces.insert(node, face);
+                if node_type == 2 {
+                    inlet_nodes.insert(node);
+                } else {
+                    outlet_nodes.insert(node);
+                }
+            }
+        }
+        
+        for (node, (_, face)) in walls {
+            fluid_nodes.remove(&node);
+            inlet_nodes.remove(&node);
+            outlet_nodes.remove(&node);
+            boundary_faces.insert(node, face);
+            solid_nodes.insert(node);
+        }
+        
+        (inlet_nodes, outlet_nodes, boundary_faces)
     }
     
     fn voxelize_triangle(
@@ -238,11 +536,19 @@ impl Geometry {
     }
     
     /// Fill interior volumes using flood-fill from exterior boundaries
-    fn fill_interior_volumes(domain: &DomainConfig, solid_nodes: &mut HashSet<(usize, usize, usize)>) {
+    fn fill_interior_volumes(domain: &DomainConfig, periodic: [bool; 3], solid_nodes: &mut HashSet<(usize, usize, usize)>) {
         // Create a 3D grid to track which nodes are reachable from the boundary
-        let mut reachable = vec![vec![vec![false; domain.nz]; domain.ny]; domain.nx];
+        let mut reachable = vec![false; domain.nx * domain.ny * domain.nz];
+        let index = |i: usize, j: usize, k: usize| i + j * domain.nx + k * domain.nx * domain.ny;
         let mut queue = std::collections::VecDeque::new();
         
+        // Periodic faces are not exterior, and without any exterior face the
+        // inside of a body is undefined
+        if periodic.iter().all(|&p| p) {
+            info!("Fully periodic domain, skipping interior filling");
+            return;
+        }
+        
         // Start flood fill from corner nodes that are definitely outside
         let corners = [
             (0, 0, 0),
@@ -258,38 +564,38 @@ impl Geometry {
         for &(i, j, k) in &corners {
             if !solid_nodes.contains(&(i, j, k)) {
                 queue.push_back((i, j, k));
-                reachable[i][j][k] = true;
+                reachable[index(i, j, k)] = true;
             }
         }
         
-        // Also add boundary edges (but not inlet/outlet faces)
+        // Also add boundary edges (but not inlet/outlet or periodic faces)
         // Y and Z boundaries only
-        for i in 0..domain.nx {
+        for i in (0..domain.nx).filter(|_| !periodic[1]) {
             for k in 0..domain.nz {
                 // Bottom face (y=0)
-                if !solid_nodes.contains(&(i, 0, k)) && !reachable[i][0][k] {
+                if !solid_nodes.contains(&(i, 0, k)) && !reachable[index(i, 0, k)] {
                     queue.push_back((i, 0, k));
-                    reachable[i][0][k] = true;
+                    reachable[index(i, 0, k)] = true;
                 }
                 // Top face (y=ny-1)
-                if !solid_nodes.contains(&(i, domain.ny - 1, k)) && !reachable[i][domain.ny - 1][k] {
+                if !solid_nodes.contains(&(i, domain.ny - 1, k)) && !reachable[index(i, domain.ny - 1, k)] {
                     queue.push_back((i, domain.ny - 1, k));
-                    reachable[i][domain.ny - 1][k] = true;
+                    reachable[index(i, domain.ny - 1, k)] = true;
                 }
             }
         }
         
-        for i in 0..domain.nx {
+        for i in (0..domain.nx).filter(|_| !periodic[2]) {
             for j in 0..domain.ny {
                 // Front face (z=0)
-                if !solid_nodes.contains(&(i, j, 0)) && !reachable[i][j][0] {
+                if !solid_nodes.contains(&(i, j, 0)) && !reachable[index(i, j, 0)] {
                     queue.push_back((i, j, 0));
-                    reachable[i][j][0] = true;
+                    reachable[index(i, j, 0)] = true;
                 }
                 // Back face (z=nz-1)
-                if !solid_nodes.contains(&(i, j, domain.nz - 1)) && !reachable[i][j][domain.nz - 1] {
+                if !solid_nodes.contains(&(i, j, domain.nz - 1)) && !reachable[index(i, j, domain.nz - 1)] {
                     queue.push_back((i, j, domain.nz - 1));
-                    reachable[i][j][domain.nz - 1] = true;
+                    reachable[index(i, j, domain.nz - 1)] = true;
                 }
             }
         }
@@ -312,18 +618,28 @@ impl Geometry {
             ];
             
             for (ni, nj, nk) in neighbors {
-                // Check bounds
-                if ni >= domain.nx || nj >= domain.ny || nk >= domain.nz {
+                // Wrap across periodic axes, otherwise check bounds
+                let wrap = |n: usize, size: usize, periodic: bool| match n {
+                    n if n < size => Some(n),
+                    _ if !periodic => None,
+                    usize::MAX => Some(size - 1),
+                    _ => Some(0),
+                };
+                let (Some(ni), Some(nj), Some(nk)) = (
+                    wrap(ni, domain.nx, periodic[0]),
+                    wrap(nj, domain.ny, periodic[1]),
+                    wrap(nk, domain.nz, periodic[2]),
+                ) else {
                     continue;
-                }
+                };
                 
                 // Skip if already processed or is solid
-                if reachable[ni][nj][nk] || solid_nodes.contains(&(ni, nj, nk)) {
+                if reachable[index(ni, nj, nk)] || solid_nodes.contains(&(ni, nj, nk)) {
                     continue;
                 }
                 
                 // Mark as reachable and add to queue
-                reachable[ni][nj][nk] = true;
+                reachable[index(ni, nj, nk)] = true;
                 queue.push_back((ni, nj, nk));
             }
         }
@@ -335,7 +651,7 @@ impl Geometry {
         for i in 0..domain.nx {
             for j in 0..domain.ny {
                 for k in 0..domain.nz {
-                    if !reachable[i][j][k] && !solid_nodes.contains(&(i, j, k)) {
+                    if !reachable[index(i, j, k)] && !solid_nodes.contains(&(i, j, k)) {
                         solid_nodes.insert((i, j, k));
                         filled_count += 1;
                     }
@@ -346,6 +662,174 @@ impl Geometry {
         info!("Flood fill added {} interior solid nodes", filled_count);
     }
 
+    /// Turn the solid nodes whose cell centre lies outside the closed surface
+    /// `triangles` back into fluid, by the parity of the surface crossings
+    /// along x beyond the centre. Returns the number of nodes removed.
+    fn remove_nodes_outside(triangles: &[[Point3<f32>; 3]], domain: &DomainConfig, solid_nodes: &mut NodeSet) -> usize {
+        if triangles.is_empty() {
+            return 0;
+        }
+        let [min, max] = Self::bounding_box(triangles.iter().flatten().map(|v| [v.x, v.y, v.z]));
+        let (start, length) = (min[0] - domain.dx, max[0] - min[0] + 2.0 * domain.dx);
+        
+        // Mesh triangles by the rows of nodes along x whose line may cross them
+        let range = |values: [f32; 3], spacing: f32, size: usize| {
+            let min = values.iter().fold(f32::INFINITY, |a, &b| a.min(b)) / spacing - 0.5;
+            let max = values.iter().fold(f32::NEG_INFINITY, |a, &b| a.max(b)) / spacing - 0.5;
+            min.floor().max(0.0) as usize..(max.ceil() + 1.0).clamp(0.0, size as f32) as usize
+        };
+        let mut rows: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (n, t) in triangles.iter().enumerate() {
+            for j in range([t[0].y, t[1].y, t[2].y], domain.dy, domain.ny) {
+                for k in range([t[0].z, t[1].z, t[2].z], domain.dz, domain.nz) {
+                    rows.entry((j, k)).or_default().push(n);
+                }
+            }
+        }
+        
+        let before = solid_nodes.len();
+        let mut crossings: HashMap<(usize, usize), Vec<f32>> = HashMap::new();
+        solid_nodes.retain(|&(i, j, k)| {
+            let row = crossings.entry((j, k)).or_insert_with(|| {
+                // Slightly off the cell centres, so that the line misses mesh edges
+                // through them
+                let origin = Point3::new(start, (j as f32 + 0.5 + 1.3e-4) * domain.dy, (k as f32 + 0.5 + 2.9e-4) * domain.dz);
+                let line = Vector3::new(length, 0.0, 0.0);
+                rows.get(&(j, k))
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|&n| Self::triangle_crossing(&origin, &line, &triangles[n]))
+                    .map(|t| start + t * length)
+                    .collect()
+            });
+            let x = (i as f32 + 0.5) * domain.dx;
+            row.iter().filter(|&&crossing| crossing > x).count() % 2 == 1
+        });
+        before - solid_nodes.len()
+    }
+
+    /// Bounce-back links of `points`, a lattice built from this geometry
+    /// (see `solver::initial_lattice`): half-way links, moved to the STL
+    /// surface when `boundaries.interpolated_bounce_back` is set
+    pub fn wall_links(&self, config: &Config, points: &[LatticePoint]) -> anyhow::Result<Vec<WallLink>> {
+        let model = config.simulation.lattice.lattice();
+        let dims = [config.domain.nx, config.domain.ny, config.domain.nz];
+        let mut links = wall_links(model, points, dims, config.boundaries.periodic_axes()?);
+        if config.boundaries.interpolated_bounce_back {
+            self.interpolate_wall_links(&config.domain, model, &mut links);
+        }
+        Ok(links)
+    }
+    
+    /// Move the wall of the links into the STL body from half-way to where
+    /// they cross its surface, setting q to the crossing's fraction of the
+    /// link (for interpolated bounce-back). Nodes sit at their cell centres,
+    /// and with `interpolated_bounce_back` only nodes inside the surface are
+    /// solid (see `from_stl`), so every link into the body crosses it at
+    /// q in (0, 1]. Links into domain face walls and links that miss the
+    /// surface keep their q.
+    pub fn interpolate_wall_links(&self, domain: &DomainConfig, lattice: &dyn Lattice, links: &mut [WallLink]) {
+        let position = |idx: u32| {
+            let idx = idx as usize;
+            (idx % domain.nx, (idx / domain.nx) % domain.ny, idx / (domain.nx * domain.ny))
+        };
+        let spacing = Vector3::new(domain.dx, domain.dy, domain.dz);
+        let buckets = self.surface_buckets(domain);
+        
+        let mut interpolated = 0;
+        let mut missed = 0;
+        for link in links.iter_mut().filter(|link| !self.boundary_faces.contains_key(&position(link.solid))) {
+            let (i, j, k) = position(link.node);
+            let origin = Point3::from(Vector3::new(i as f32 + 0.5, j as f32 + 0.5, k as f32 + 0.5).component_mul(&spacing));
+            let c = lattice.velocities()[link.direction as usize];
+            let segment = Vector3::new(c[0] as f32, c[1] as f32, c[2] as f32).component_mul(&spacing);
+            let q = match &self.surface {
+                Surface::Mesh(triangles) => buckets
+                    .get(&(i, j, k))
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|&n| Self::triangle_crossing(&origin, &segment, &triangles[n]))
+                    .reduce(f32::min),
+                Surface::Outline(segments) => segments
+                    .iter()
+                    .filter_map(|edge| Self::segment_crossing(&origin.xy(), &segment.xy(), edge))
+                    .reduce(f32::min),
+            };
+            match q {
+                Some(q) => {
+                    link.q = q.min(1.0);
+                    interpolated += 1;
+                }
+                None => missed += 1,
+            }
+        }
+        
+        info!("{} interpolated wall links, {} body links miss the surface and keep half-way bounce-back",
+              interpolated, missed);
+    }
+    
+    /// Mesh triangles by the nodes whose links may cross them
+    fn surface_buckets(&self, domain: &DomainConfig) -> HashMap<(usize, usize, usize), Vec<usize>> {
+        let mut buckets: HashMap<_, Vec<usize>> = HashMap::new();
+        let Surface::Mesh(triangles) = &self.surface else { return buckets };
+        
+        // Node range of the triangle's bounding box, widened by one link
+        let range = |values: [f32; 3], spacing: f32, size: usize| {
+            let min = values.iter().fold(f32::INFINITY, |a, &b| a.min(b)) / spacing - 0.5;
+            let max = values.iter().fold(f32::NEG_INFINITY, |a, &b| a.max(b)) / spacing - 0.5;
+            let first = (min.floor() as i64 - 1).max(0) as usize;
+            let last = (max.ceil() as i64 + 1).min(size as i64 - 1);
+            first..(last + 1).max(0) as usize
+        };
+        for (n, t) in triangles.iter().enumerate() {
+            for i in range([t[0].x, t[1].x, t[2].x], domain.dx, domain.nx) {
+                for j in range([t[0].y, t[1].y, t[2].y], domain.dy, domain.ny) {
+                    for k in range([t[0].z, t[1].z, t[2].z], domain.dz, domain.nz) {
+                        buckets.entry((i, j, k)).or_default().push(n);
+                    }
+                }
+            }
+        }
+        buckets
+    }
+    
+    /// Fraction t in (0, 2] of the segment `origin + t link` at which it
+    /// crosses the triangle (Möller-Trumbore)
+    fn triangle_crossing(origin: &Point3<f32>, link: &Vector3<f32>, triangle: &[Point3<f32>; 3]) -> Option<f32> {
+        let e1 = triangle[1] - triangle[0];
+        let e2 = triangle[2] - triangle[0];
+        let p = link.cross(&e2);
+        let det = e1.dot(&p);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        
+        let s = origin - triangle[0];
+        let u = s.dot(&p) / det;
+        let q = s.cross(&e1);
+        let v = link.dot(&q) / det;
+        if u < 0.0 || v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = e2.dot(&q) / det;
+        (t > 0.0 && t <= 2.0).then_some(t)
+    }
+    
+    /// Fraction t in (0, 2] of the segment `origin + t link` at which it
+    /// crosses the outline segment
+    fn segment_crossing(origin: &Point2<f32>, link: &Vector2<f32>, (a, b): &(Point2<f32>, Point2<f32>)) -> Option<f32> {
+        let edge = b - a;
+        let denom = link.x * edge.y - link.y * edge.x;
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        
+        let s = a - origin;
+        let t = (s.x * edge.y - s.y * edge.x) / denom;
+        let u = (s.x * link.y - s.y * link.x) / denom;
+        (t > 0.0 && t <= 2.0 && (0.0..=1.0).contains(&u)).then_some(t)
+    }
+    
     pub fn is_solid(&self, i: usize, j: usize, k: usize) -> bool {
         self.solid_nodes.contains(&(i, j, k))
     }
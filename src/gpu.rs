@@ -1,25 +1,80 @@
 use wgpu::util::DeviceExt;
 use anyhow::Result;
-use log::info;
-use crate::{config::Config, lattice::LatticePoint};
+use log::{info, warn};
+use crate::{
+    boundary::WallLink,
+    collision::CollisionOperator,
+    turbulence::SubgridModel,
+    config::{Config, GpuConfig, PowerPreference},
+    lattice::{Lattice, LatticePoint},
+};
+
+/// Compute shaders used by the LBM step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderKind {
+    Collision,
+    Streaming,
+    Boundary,
+}
+
+/// Threads per workgroup of the wall links pass (`@workgroup_size` of `wall_links` in `boundary.wgsl`)
+const LINK_WORKGROUP_SIZE: u32 = 64;
+
+/// Workgroup grid `[x, y]` covering `invocations` threads in workgroups of
+/// `workgroup_size`, continued along y once x reaches `max_per_dimension`.
+/// Shaders rebuild the thread index as `x + y * groups.x * workgroup_size`.
+pub fn dispatch_grid(invocations: u32, workgroup_size: u32, max_per_dimension: u32) -> [u32; 2] {
+    let groups = invocations.div_ceil(workgroup_size);
+    let x = groups.min(max_per_dimension);
+    [x, if x == 0 { 0 } else { groups.div_ceil(x) }]
+}
+
+/// Full WGSL source of a shader: the generated lattice constants followed by
+/// the shader body
+pub fn shader_source(kind: ShaderKind, lattice: &dyn Lattice) -> String {
+    let body = match kind {
+        ShaderKind::Collision => include_str!("shaders/collision.wgsl"),
+        ShaderKind::Streaming => include_str!("shaders/streaming.wgsl"),
+        ShaderKind::Boundary => include_str!("shaders/boundary.wgsl"),
+    };
+    match kind {
+        ShaderKind::Collision => format!(
+            "{}\n{}\n{}",
+            lattice.wgsl_constants(),
+            lattice.moment_basis().wgsl_functions(),
+            body
+        ),
+        _ => format!("{}\n{}", lattice.wgsl_constants(), body),
+    }
+}
 
 pub struct GPUContext {
     device: wgpu::Device,
     queue: wgpu::Queue,
+    adapter_info: wgpu::AdapterInfo,
+    
+    // Lattice model, whose Q distributions are stored per node
+    model: &'static dyn Lattice,
     
     // Compute shaders
     collision_pipeline: wgpu::ComputePipeline,
     streaming_pipeline: wgpu::ComputePipeline,
     boundary_pipeline: wgpu::ComputePipeline,
+    links_pipeline: wgpu::ComputePipeline,
     
     // Buffers
     lattice_buffer: wgpu::Buffer,
     temp_buffer: wgpu::Buffer,
     config_buffer: wgpu::Buffer,
+    links_buffer: wgpu::Buffer,
+    link_count: u32,
+    profile_buffer: wgpu::Buffer,
     
     // Bind groups
+    collision_bind_group_layout: wgpu::BindGroupLayout,
     collision_bind_group: wgpu::BindGroup,
     streaming_bind_group: wgpu::BindGroup,
+    boundary_bind_group_layout: wgpu::BindGroupLayout,
     boundary_bind_group: wgpu::BindGroup,
     
     // Dimensions
@@ -31,23 +86,19 @@ pub struct GPUContext {
 impl GPUContext {
     pub async fn new(config: &Config) -> Result<Self> {
         // Initialize WGPU
+        let backends = Self::parse_backends(&config.gpu.backends)?;
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
+            backends,
             ..Default::default()
         });
         
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: None,
-            })
-            .await?;
+        let adapter = Self::select_adapter(&instance, backends, &config.gpu).await?;
         
         // Log GPU information for debugging
         let adapter_info = adapter.get_info();
-        info!("GPU Adapter Selected: {} ({:?}, {:?}, {:?})", 
-              adapter_info.name, adapter_info.vendor, adapter_info.device_type, adapter_info.backend);
+        info!("GPU Adapter Selected: {} ({:?}, {:?}, {:?}, driver: {} {})", 
+              adapter_info.name, adapter_info.vendor, adapter_info.device_type, adapter_info.backend,
+              adapter_info.driver, adapter_info.driver_info);
         
         // Get adapter limits and request high performance limits
         let adapter_limits = adapter.limits();
@@ -73,8 +124,14 @@ impl GPUContext {
         let ny = config.domain.ny as u32;
         let nz = config.domain.nz as u32;
         
-        // Create buffers
-        let lattice_size = (nx * ny * nz) as wgpu::BufferAddress * std::mem::size_of::<LatticePoint>() as wgpu::BufferAddress;
+        let lattice = config.simulation.lattice.lattice();
+        let q = lattice.q();
+        
+        // Create buffers (only the Q distributions in use are stored per node)
+        let point_size = ((q + LatticePoint::EXTRA_WORDS) * std::mem::size_of::<u32>()) as wgpu::BufferAddress;
+        let lattice_size = (nx * ny * nz) as wgpu::BufferAddress * point_size;
+        info!("Lattice buffer: {} x {} bytes per node ({:.1} MB)",
+              lattice.name(), point_size, lattice_size as f64 / (1024.0 * 1024.0));
         
         let lattice_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Lattice Buffer"),
@@ -91,17 +148,40 @@ impl GPUContext {
         });
         
         // Configuration buffer
+        let tau = config.calculate_tau();
+        let subgrid = SubgridModel::from_config(config);
+        let periodic = config.boundaries.periodic_axes()?;
+        let periodic_mask = periodic
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (axis, &periodic)| mask | (periodic as u32) << axis);
+        let (relaxation, relaxation_high) = match CollisionOperator::from_config(config)? {
+            CollisionOperator::Bgk { tau } => ([1.0 / tau, 0.0, 0.0, 0.0], [0.0; 4]),
+            CollisionOperator::Mrt(rates) => ([rates.shear, rates.bulk, rates.ghost, 0.0], [0.0; 4]),
+            CollisionOperator::Trt(rates) => ([rates.plus, 0.0, 0.0, rates.minus], [0.0; 4]),
+            CollisionOperator::Cumulant(rates) => (
+                [rates.shear, rates.bulk, rates.third, 0.0],
+                [rates.fourth, rates.fifth, rates.sixth, 0.0],
+            ),
+        };
         let config_data = GPUConfig {
-            domain_size: [nx, ny, nz, 0], // Fourth element is padding
-            tau: config.calculate_tau(),
+            domain_size: [nx, ny, nz, periodic_mask], // Fourth element: bit mask of the periodic axes
+            tau,
             density: config.physics.density,
-            padding1: [0.0, 0.0],
+            collision_model: config.simulation.collision_model as u32,
+            inlet_profile: 0,
             inlet_velocity: [
                 config.physics.inlet_velocity[0],
                 config.physics.inlet_velocity[1],
                 config.physics.inlet_velocity[2],
                 0.0, // padding
             ],
+            relaxation,
+            relaxation_high,
+            les_model: subgrid.map_or(0, |les| les.model as u32),
+            les_constant: subgrid.map_or(0.0, |les| les.constant),
+            padding2: [0.0; 2],
+            faces: config.boundaries.face_values(&config.physics),
         };
         
         let config_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -113,22 +193,22 @@ impl GPUContext {
         // Load shaders
         let collision_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Collision Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/collision.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(shader_source(ShaderKind::Collision, lattice).into()),
         });
         
         let streaming_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Streaming Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/streaming.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(shader_source(ShaderKind::Streaming, lattice).into()),
         });
         
         let boundary_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Boundary Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/boundary.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(shader_source(ShaderKind::Boundary, lattice).into()),
         });
         
         // Create bind group layouts for different shader types
         
-        // Layout for collision and boundary shaders (both buffers read-write)
+        // Layout for the collision shader (both buffers read-write, then the read-only inlet profile)
         let collision_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Collision Bind Group Layout"),
             entries: &[
@@ -162,6 +242,16 @@ impl GPUContext {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
         
@@ -202,6 +292,63 @@ impl GPUContext {
             ],
         });
         
+        // Layout for the boundary shader (lattice, temp and config, then the read-only wall links and inlet profile)
+        let boundary_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Boundary Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        
         // Create pipeline layouts
         let collision_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Collision Pipeline Layout"),
@@ -215,6 +362,12 @@ impl GPUContext {
             push_constant_ranges: &[],
         });
         
+        let boundary_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Boundary Pipeline Layout"),
+            bind_group_layouts: &[&boundary_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        
         // Create compute pipelines
         let collision_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
             label: Some("Collision Pipeline"),
@@ -236,33 +389,36 @@ impl GPUContext {
         
         let boundary_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
             label: Some("Boundary Pipeline"),
-            layout: Some(&collision_pipeline_layout),
+            layout: Some(&boundary_pipeline_layout),
             module: &boundary_shader,
             entry_point: Some("main"),
             compilation_options: Default::default(),
             cache: None,
         });
         
-        // Create bind groups
-        let collision_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Collision Bind Group"),
-            layout: &collision_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: lattice_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: temp_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: config_buffer.as_entire_binding(),
-                },
-            ],
+        let links_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Wall Links Pipeline"),
+            layout: Some(&boundary_pipeline_layout),
+            module: &boundary_shader,
+            entry_point: Some("wall_links"),
+            compilation_options: Default::default(),
+            cache: None,
         });
         
+        // Wall links buffer, holding a single unused link until links are uploaded
+        let links_buffer = Self::create_links_buffer(&device, &[WallLink { node: u32::MAX, direction: 0, q: 0.5, solid: 0 }]);
+        
+        // Inlet profile buffer, holding a single unused velocity until a profile is uploaded
+        let profile_buffer = Self::create_profile_buffer(&device, &[[0.0; 3]]);
+        
+        // Create bind groups
+        let collision_bind_group = Self::create_bind_group(
+            &device,
+            "Collision Bind Group",
+            &collision_bind_group_layout,
+            &[&lattice_buffer, &temp_buffer, &config_buffer, &profile_buffer],
+        );
+        
         let streaming_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Streaming Bind Group"),
             layout: &streaming_bind_group_layout,
@@ -282,36 +438,32 @@ impl GPUContext {
             ],
         });
         
-        let boundary_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Boundary Bind Group"),
-            layout: &collision_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: lattice_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: temp_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: config_buffer.as_entire_binding(),
-                },
-            ],
-        });
+        let boundary_bind_group = Self::create_bind_group(
+            &device,
+            "Boundary Bind Group",
+            &boundary_bind_group_layout,
+            &[&lattice_buffer, &temp_buffer, &config_buffer, &links_buffer, &profile_buffer],
+        );
         
         Ok(Self {
             device,
             queue,
+            adapter_info,
+            model: lattice,
             collision_pipeline,
             streaming_pipeline,
             boundary_pipeline,
+            links_pipeline,
             lattice_buffer,
             temp_buffer,
             config_buffer,
+            links_buffer,
+            link_count: 0,
+            profile_buffer,
+            collision_bind_group_layout,
             collision_bind_group,
             streaming_bind_group,
+            boundary_bind_group_layout,
             boundary_bind_group,
             nx,
             ny,
@@ -319,12 +471,189 @@ impl GPUContext {
         })
     }
     
-    pub fn upload_lattice_data(&self, data: &[LatticePoint]) {
-        self.queue.write_buffer(&self.lattice_buffer, 0, bytemuck::cast_slice(data));
+    /// Adapters available on the backends of the `gpu` config section, with their limits
+    pub fn list_adapters(gpu: &GpuConfig) -> Result<Vec<(wgpu::AdapterInfo, wgpu::Limits)>> {
+        let backends = Self::parse_backends(&gpu.backends)?;
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        Ok(instance.enumerate_adapters(backends)
+            .iter()
+            .map(|adapter| (adapter.get_info(), adapter.limits()))
+            .collect())
+    }
+    
+    /// Backends of a `gpu.backends` list: "all" or comma-separated wgpu
+    /// backend names such as "vulkan,metal". Unknown names are errors.
+    pub fn parse_backends(list: &str) -> Result<wgpu::Backends> {
+        if list.trim().eq_ignore_ascii_case("all") {
+            return Ok(wgpu::Backends::all());
+        }
+        let mut backends = wgpu::Backends::empty();
+        for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let backend = wgpu::Backends::from_comma_list(name);
+            anyhow::ensure!(!backend.is_empty(), "Unknown wgpu backend '{}' in '{}'", name, list);
+            backends |= backend;
+        }
+        anyhow::ensure!(!backends.is_empty(), "No valid wgpu backends in '{}'", list);
+        Ok(backends)
+    }
+    
+    /// Pick an adapter according to the `gpu` config section.
+    ///
+    /// An explicit adapter name takes precedence over the power preference.
+    /// Otherwise the preferred hardware adapter is requested first and, if
+    /// allowed, the request is retried on a fallback (software) adapter.
+    async fn select_adapter(
+        instance: &wgpu::Instance,
+        backends: wgpu::Backends,
+        gpu: &GpuConfig,
+    ) -> Result<wgpu::Adapter> {
+        if let Some(name) = &gpu.adapter_name {
+            let needle = name.to_lowercase();
+            let adapters = instance.enumerate_adapters(backends);
+            let available: Vec<String> = adapters.iter()
+                .map(|adapter| {
+                    let info = adapter.get_info();
+                    format!("{} ({:?})", info.name, info.backend)
+                })
+                .collect();
+            
+            return adapters.into_iter()
+                .find(|adapter| adapter.get_info().name.to_lowercase().contains(&needle))
+                .ok_or_else(|| anyhow::anyhow!(
+                    "No GPU adapter matching '{}' (available: {})",
+                    name,
+                    if available.is_empty() { "none".to_string() } else { available.join(", ") }
+                ));
+        }
+        
+        let power_preference = match gpu.power_preference {
+            PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+            PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+            PowerPreference::None => wgpu::PowerPreference::None,
+        };
+        
+        let hardware = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference,
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })
+            .await;
+        
+        match hardware {
+            Ok(adapter) => Ok(adapter),
+            Err(err) if gpu.allow_fallback => {
+                warn!("No hardware GPU adapter found ({}), retrying with a fallback adapter", err);
+                instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference,
+                        force_fallback_adapter: true,
+                        compatible_surface: None,
+                    })
+                    .await
+                    .map_err(|err| anyhow::anyhow!("No GPU adapter available, including fallback adapters: {}", err))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+    
+    /// Information about the adapter the context runs on
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+    
+    /// Upload the lattice state; the wall links are uploaded separately
+    pub fn upload_lattice_data(&mut self, data: &[LatticePoint]) {
+        let q = self.model.q();
+        let mut words = Vec::with_capacity(data.len() * (q + LatticePoint::EXTRA_WORDS));
+        for point in data {
+            point.write_gpu_words(q, &mut words);
+        }
+        self.queue.write_buffer(&self.lattice_buffer, 0, bytemuck::cast_slice(&words));
+    }
+    
+    /// Replace the links from fluid nodes into bounce-back and moving walls
+    pub fn upload_wall_links(&mut self, links: &[WallLink]) {
+        self.link_count = links.len() as u32;
+        if links.is_empty() {
+            return;
+        }
+        
+        self.links_buffer = Self::create_links_buffer(&self.device, links);
+        self.create_boundary_bind_group();
+    }
+    
+    /// Upload the full-strength velocity of every node for a spatial inlet
+    /// profile and switch the inlets over to it
+    pub fn upload_inlet_profile(&mut self, profile: &[[f32; 3]]) {
+        self.profile_buffer = Self::create_profile_buffer(&self.device, profile);
+        self.collision_bind_group = Self::create_bind_group(
+            &self.device,
+            "Collision Bind Group",
+            &self.collision_bind_group_layout,
+            &[&self.lattice_buffer, &self.temp_buffer, &self.config_buffer, &self.profile_buffer],
+        );
+        self.create_boundary_bind_group();
+        
+        let offset = std::mem::offset_of!(GPUConfig, inlet_profile) as wgpu::BufferAddress;
+        self.queue.write_buffer(&self.config_buffer, offset, bytemuck::bytes_of(&1u32));
+    }
+    
+    fn create_links_buffer(device: &wgpu::Device, links: &[WallLink]) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wall Links Buffer"),
+            contents: bytemuck::cast_slice(links),
+            usage: wgpu::BufferUsages::STORAGE,
+        })
+    }
+    
+    fn create_profile_buffer(device: &wgpu::Device, profile: &[[f32; 3]]) -> wgpu::Buffer {
+        let velocities: Vec<[f32; 4]> = profile.iter().map(|&[u, v, w]| [u, v, w, 0.0]).collect();
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Inlet Profile Buffer"),
+            contents: bytemuck::cast_slice(&velocities),
+            usage: wgpu::BufferUsages::STORAGE,
+        })
+    }
+    
+    /// Rebuild the boundary bind group after the wall links or inlet profile changed
+    fn create_boundary_bind_group(&mut self) {
+        self.boundary_bind_group = Self::create_bind_group(
+            &self.device,
+            "Boundary Bind Group",
+            &self.boundary_bind_group_layout,
+            &[&self.lattice_buffer, &self.temp_buffer, &self.config_buffer, &self.links_buffer, &self.profile_buffer],
+        );
+    }
+    
+    /// Bind group of `buffers` at consecutive bindings
+    fn create_bind_group(
+        device: &wgpu::Device,
+        label: &str,
+        layout: &wgpu::BindGroupLayout,
+        buffers: &[&wgpu::Buffer],
+    ) -> wgpu::BindGroup {
+        let entries: Vec<_> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &entries,
+        })
     }
     
     pub async fn read_lattice_data(&self) -> Result<Vec<LatticePoint>> {
-        let buffer_size = (self.nx * self.ny * self.nz) as usize * std::mem::size_of::<LatticePoint>();
+        let point_words = self.model.q() + LatticePoint::EXTRA_WORDS;
+        let buffer_size = (self.nx * self.ny * self.nz) as usize * point_words * std::mem::size_of::<u32>();
         
         let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Staging Buffer"),
@@ -351,7 +680,11 @@ impl GPUContext {
         receiver.await??;
         
         let data = buffer_slice.get_mapped_range();
-        let result: Vec<LatticePoint> = bytemuck::cast_slice(&data).to_vec();
+        let words: &[u32] = bytemuck::cast_slice(&data);
+        let result: Vec<LatticePoint> = words
+            .chunks_exact(point_words)
+            .map(|chunk| LatticePoint::from_gpu_words(self.model.q(), chunk))
+            .collect();
         
         drop(data);
         staging_buffer.unmap();
@@ -359,6 +692,12 @@ impl GPUContext {
         Ok(result)
     }
     
+    /// Overwrite the per-face parameters in the configuration uniform
+    pub fn update_face_values(&self, faces: &[[f32; 4]; 7]) {
+        let offset = std::mem::offset_of!(GPUConfig, faces) as wgpu::BufferAddress;
+        self.queue.write_buffer(&self.config_buffer, offset, bytemuck::cast_slice(faces));
+    }
+    
     pub fn step(&self) -> Result<()> {
         let start_time = std::time::Instant::now();
         
@@ -368,8 +707,8 @@ impl GPUContext {
         
         // Compute workgroup dispatch - note that z dimension uses workgroup_size=1 in shaders
         let workgroup_size_xy = 8;
-        let dispatch_x = (self.nx + workgroup_size_xy - 1) / workgroup_size_xy;
-        let dispatch_y = (self.ny + workgroup_size_xy - 1) / workgroup_size_xy;
+        let dispatch_x = self.nx.div_ceil(workgroup_size_xy);
+        let dispatch_y = self.ny.div_ceil(workgroup_size_xy);
         let dispatch_z = self.nz; // Each workgroup handles 1 z-slice
         
         // Collision step - read from lattice_buffer, write to temp_buffer
@@ -397,6 +736,19 @@ impl GPUContext {
             compute_pass.dispatch_workgroups(dispatch_x, dispatch_y, dispatch_z);
         }
         
+        // Interpolated bounce-back - rebuild the wall links from the post-collision temp_buffer
+        if self.link_count > 0 {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Wall Links Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.links_pipeline);
+            compute_pass.set_bind_group(0, &self.boundary_bind_group, &[]);
+            let max_groups = self.device.limits().max_compute_workgroups_per_dimension;
+            let [groups_x, groups_y] = dispatch_grid(self.link_count, LINK_WORKGROUP_SIZE, max_groups);
+            compute_pass.dispatch_workgroups(groups_x, groups_y, 1);
+        }
+        
         // Insert memory barrier before boundary conditions
         encoder.insert_debug_marker("Memory barrier after streaming");
         
@@ -428,9 +780,16 @@ impl GPUContext {
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 struct GPUConfig {
-    domain_size: [u32; 4],      // nx, ny, nz, padding - 16 bytes aligned
+    domain_size: [u32; 4],      // nx, ny, nz, periodic axes bit mask - 16 bytes aligned
     tau: f32,                   // 4 bytes
     density: f32,               // 4 bytes
-    padding1: [f32; 2],         // 8 bytes - total 16 bytes for this group
+    collision_model: u32,       // 4 bytes (`CollisionModel` discriminant)
+    inlet_profile: u32,         // 4 bytes - 1 if inlet velocities come from the profile buffer - total 16 bytes for this group
     inlet_velocity: [f32; 4],   // 16 bytes aligned
+    relaxation: [f32; 4],       // MRT shear (TRT symmetric), bulk, ghost (cumulant third order), TRT antisymmetric rates
+    relaxation_high: [f32; 4],  // Cumulant fourth, fifth and sixth order rates, padding
+    les_model: u32,             // 4 bytes (`LesModel` discriminant)
+    les_constant: f32,          // 4 bytes - model constant (Cs, Cw or c)
+    padding2: [f32; 2],         // 8 bytes - total 16 bytes for this group
+    faces: [[f32; 4]; 7],       // Per-face inlet or wall velocity (xyz) and outlet density, convection velocity or inlet waveform factor (w), then the body velocity
 }
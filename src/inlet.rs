@@ -0,0 +1,335 @@
+use anyhow::Result;
+use crate::{
+    config::{Config, CrossSection, FaceBoundary, InletProfile, InletWaveform},
+    lattice::LatticePoint,
+    Float,
+};
+
+/// Inlet velocity factor as a function of the time step (see
+/// `InletWaveform`), with tables loaded from their CSV files
+#[derive(Debug, Clone, PartialEq)]
+pub enum Waveform {
+    Constant,
+    Ramp { steps: Float },
+    Sinusoidal { period: Float, amplitude: Float, phase: Float },
+    /// `(time, factor)` samples in increasing time
+    Table { samples: Vec<(Float, Float)>, repeat: bool },
+}
+
+impl Waveform {
+    /// The configured inlet waveform
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(match &config.boundaries.inlet_waveform {
+            None => Waveform::Constant,
+            Some(InletWaveform::Ramp { duration }) => {
+                anyhow::ensure!(*duration > 0.0, "Inlet ramp duration must be positive, got {}", duration);
+                Waveform::Ramp { steps: *duration }
+            }
+            Some(InletWaveform::Sinusoidal { period, amplitude, phase }) => {
+                anyhow::ensure!(*period > 0.0, "Sinusoidal inlet period must be positive, got {}", period);
+                Waveform::Sinusoidal { period: *period, amplitude: *amplitude, phase: *phase }
+            }
+            Some(InletWaveform::Table { file, repeat, time_scale }) => {
+                let content = std::fs::read_to_string(file)
+                    .map_err(|e| anyhow::anyhow!("Failed to read inlet table {}: {}", file, e))?;
+                let scale = time_scale.unwrap_or(1.0);
+                anyhow::ensure!(scale > 0.0, "Inlet table time_scale must be positive, got {}", scale);
+                let samples = Self::parse_table(&content, file)?;
+                let samples = samples.into_iter().map(|(time, factor)| (time * scale, factor)).collect();
+                Waveform::Table { samples, repeat: *repeat }
+            }
+        })
+    }
+
+    /// `time, factor` rows (see `csv_rows`) in increasing time
+    pub fn parse_table(content: &str, path: &str) -> Result<Vec<(Float, Float)>> {
+        let mut samples: Vec<(Float, Float)> = Vec::new();
+        for (line_number, values) in csv_rows(content, path, &["time", "factor"])? {
+            if let Some(&(last, _)) = samples.last() {
+                anyhow::ensure!(values[0] > last, "{}:{}: times must increase", path, line_number);
+            }
+            samples.push((values[0], values[1]));
+        }
+        Ok(samples)
+    }
+
+    /// Whether the factor changes over time
+    pub fn is_constant(&self) -> bool {
+        *self == Waveform::Constant
+    }
+
+    /// Largest factor over time
+    pub fn peak_factor(&self) -> Float {
+        match self {
+            Waveform::Constant | Waveform::Ramp { .. } => 1.0,
+            Waveform::Sinusoidal { amplitude, .. } => 1.0 + amplitude.abs(),
+            Waveform::Table { samples, .. } => samples.iter().map(|&(_, factor)| factor.abs()).fold(0.0, Float::max),
+        }
+    }
+
+    /// Factor on the inlet velocity at time step `step`
+    pub fn factor(&self, step: usize) -> Float {
+        let t = step as Float;
+        match self {
+            Waveform::Constant => 1.0,
+            Waveform::Ramp { steps } => (t / steps).min(1.0),
+            Waveform::Sinusoidal { period, amplitude, phase } => {
+                1.0 + amplitude * (2.0 * std::f32::consts::PI * t / period + phase).sin()
+            }
+            Waveform::Table { samples, repeat } => {
+                let (first, last) = (samples[0], samples[samples.len() - 1]);
+                let t = if *repeat && last.0 > first.0 {
+                    first.0 + (t - first.0).rem_euclid(last.0 - first.0)
+                } else {
+                    t
+                };
+                match samples.iter().position(|&(time, _)| time > t) {
+                    Some(0) => first.1,
+                    Some(n) => {
+                        let ((t0, f0), (t1, f1)) = (samples[n - 1], samples[n]);
+                        f0 + (f1 - f0) * (t - t0) / (t1 - t0)
+                    }
+                    None => last.1,
+                }
+            }
+        }
+    }
+
+    /// Per-face parameters (see `BoundaryConfig::face_values`) at time step
+    /// `step`, with the velocity and the profile factor of the velocity
+    /// inlets scaled by the factor
+    pub fn face_values(&self, config: &Config, step: usize) -> [[Float; 4]; 7] {
+        let mut values = config.boundaries.face_values(&config.physics);
+        let factor = self.factor(step);
+        for (value, face) in values.iter_mut().zip(config.boundaries.faces()) {
+            if let Some(FaceBoundary::VelocityInlet { .. }) = face {
+                for component in value.iter_mut() {
+                    *component *= factor;
+                }
+            }
+        }
+        values
+    }
+}
+
+/// Velocity of every node of `lattice` at full strength (waveform factor 1)
+/// for the configured spatial inlet profile, zero away from the inlets, or
+/// `None` for uniform inlets. The inlet nodes are the lattice's inlet-type
+/// nodes on each face.
+pub fn inlet_profile(config: &Config, lattice: &[LatticePoint]) -> Result<Option<Vec<[Float; 3]>>> {
+    let Some(profile) = &config.boundaries.inlet_profile else {
+        return Ok(None);
+    };
+    let samples = match profile {
+        InletProfile::File { file } => Some(Samples::load(file)?),
+        _ => None,
+    };
+
+    let domain = &config.domain;
+    let size = [domain.nx, domain.ny, domain.nz];
+    let spacing = [domain.dx, domain.dy, domain.dz];
+    let position = |idx: usize| [idx % size[0], (idx / size[0]) % size[1], idx / (size[0] * size[1])];
+    let face_values = config.boundaries.face_values(&config.physics);
+
+    let mut velocities = vec![[0.0; 3]; lattice.len()];
+    for face in 0..6 {
+        let nodes: Vec<usize> = (0..lattice.len())
+            .filter(|&n| lattice[n].node_type == 2 && lattice[n].face == face)
+            .collect();
+        if nodes.is_empty() {
+            continue;
+        }
+        let axes = tangential_axes(face);
+        let [u, v, w, _] = face_values[face as usize];
+
+        // Centre and half-width of the inlet along the tangential axes that
+        // are more than one node thick, walls half-way beyond the end nodes
+        let extents: Vec<(usize, Float, Float)> = axes
+            .iter()
+            .filter(|&&axis| size[axis] > 1)
+            .map(|&axis| {
+                let (lo, hi) = nodes.iter().fold((usize::MAX, 0), |(lo, hi), &n| {
+                    let p = position(n)[axis];
+                    (lo.min(p), hi.max(p))
+                });
+                (axis, (lo + hi) as Float / 2.0, (hi - lo + 1) as Float / 2.0)
+            })
+            .collect();
+
+        for &n in &nodes {
+            let p = position(n);
+            let offsets: Vec<(Float, Float)> = extents.iter().map(|&(axis, centre, half)| (p[axis] as Float - centre, half)).collect();
+            velocities[n] = match profile {
+                InletProfile::Parabolic { cross_section } => {
+                    let s = shape_factor(&offsets, *cross_section, |r| 1.0 - r * r);
+                    [u * s, v * s, w * s]
+                }
+                InletProfile::PowerLaw { cross_section, exponent } => {
+                    let s = shape_factor(&offsets, *cross_section, |r| (1.0 - r).max(0.0).powf(1.0 / exponent));
+                    [u * s, v * s, w * s]
+                }
+                InletProfile::File { .. } => {
+                    // Node i covers the cell from i * dx to (i + 1) * dx
+                    let point = [0, 1, 2].map(|a| (p[a] as Float + 0.5) * spacing[a]);
+                    samples.as_ref().map_or([0.0; 3], |samples| samples.nearest(point, axes))
+                }
+            };
+        }
+    }
+    Ok(Some(velocities))
+}
+
+/// The two axes along a domain face, in x, y, z order
+fn tangential_axes(face: u32) -> [usize; 2] {
+    match face / 2 {
+        0 => [1, 2],
+        1 => [0, 2],
+        _ => [0, 1],
+    }
+}
+
+/// Profile `g(r)` at a node with the given `(offset from the centre,
+/// half-width)` along each bounded axis: a product over the axes of a
+/// rectangular inlet, or of the radius over the inscribed circle of a
+/// circular one
+fn shape_factor(offsets: &[(Float, Float)], cross_section: CrossSection, g: impl Fn(Float) -> Float) -> Float {
+    match cross_section {
+        CrossSection::Rectangular => offsets.iter().map(|&(offset, half)| g((offset / half).abs())).product(),
+        CrossSection::Circular => {
+            let radius = offsets.iter().map(|&(_, half)| half).fold(Float::INFINITY, Float::min);
+            let r2: Float = offsets.iter().map(|&(offset, _)| (offset / radius).powi(2)).sum();
+            if r2 >= 1.0 { 0.0 } else { g(r2.sqrt()) }
+        }
+    }
+}
+
+/// Velocity samples of an inlet profile file
+enum Samples {
+    /// In-plane coordinates of the inlet face (CSV)
+    Plane(Vec<([Float; 2], [Float; 3])>),
+    /// Points in space (VTK), projected onto the inlet face
+    Points(Vec<([Float; 3], [Float; 3])>),
+}
+
+impl Samples {
+    fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read inlet profile {}: {}", path, e))?;
+        let samples = if path.to_ascii_lowercase().ends_with(".vtk") {
+            Samples::Points(parse_vtk_velocity(&content, path)?)
+        } else {
+            Samples::Plane(parse_csv_velocity(&content, path)?)
+        };
+        log::info!("Inlet profile {}: {} samples", path, samples.len());
+        Ok(samples)
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Samples::Plane(samples) => samples.len(),
+            Samples::Points(samples) => samples.len(),
+        }
+    }
+
+    /// Velocity of the sample nearest to `point` in the plane of `axes`;
+    /// among points in space equally near in the plane, the one nearest to
+    /// the plane
+    fn nearest(&self, point: [Float; 3], axes: [usize; 2]) -> [Float; 3] {
+        let normal = 3 - axes[0] - axes[1];
+        let in_plane = |a: Float, b: Float| (a - point[axes[0]]).powi(2) + (b - point[axes[1]]).powi(2);
+        let key = |distance: (Float, Float)| move |other: &(Float, Float)| distance.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal);
+        match self {
+            Samples::Plane(samples) => samples
+                .iter()
+                .map(|&([a, b], velocity)| ((in_plane(a, b), 0.0), velocity))
+                .min_by(|x, y| key(x.0)(&y.0))
+                .map_or([0.0; 3], |(_, velocity)| velocity),
+            Samples::Points(samples) => samples
+                .iter()
+                .map(|&(p, velocity)| ((in_plane(p[axes[0]], p[axes[1]]), (p[normal] - point[normal]).abs()), velocity))
+                .min_by(|x, y| key(x.0)(&y.0))
+                .map_or([0.0; 3], |(_, velocity)| velocity),
+        }
+    }
+}
+
+/// `a, b, u, v, w` rows (see `csv_rows`)
+fn parse_csv_velocity(content: &str, path: &str) -> Result<Vec<([Float; 2], [Float; 3])>> {
+    Ok(csv_rows(content, path, &["a", "b", "u", "v", "w"])?
+        .into_iter()
+        .map(|(_, values)| ([values[0], values[1]], [values[2], values[3], values[4]]))
+        .collect())
+}
+
+/// Numeric rows of a CSV file with one value per name in `columns`, each
+/// with its line number. Values are comma or whitespace separated, `#`
+/// starts a comment and a non-numeric first row is skipped as a header.
+fn csv_rows(content: &str, path: &str, columns: &[&str]) -> Result<Vec<(usize, Vec<Float>)>> {
+    let mut rows = Vec::new();
+    let mut header = false;
+    for (line_number, line) in content.lines().enumerate() {
+        let line_number = line_number + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let values = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<Float>())
+            .collect::<Result<Vec<_>, _>>();
+        let values = match values {
+            Ok(values) => values,
+            Err(_) if rows.is_empty() && !header => {
+                header = true;
+                continue;
+            }
+            Err(e) => anyhow::bail!("{}:{}: {}", path, line_number, e),
+        };
+        anyhow::ensure!(values.len() == columns.len(), "{}:{}: expected '{}'", path, line_number, columns.join(", "));
+        rows.push((line_number, values));
+    }
+    anyhow::ensure!(!rows.is_empty(), "{} contains no samples", path);
+    Ok(rows)
+}
+
+/// Points and the `Velocity` (or else the first) vector field of a legacy
+/// ASCII VTK file
+fn parse_vtk_velocity(content: &str, path: &str) -> Result<Vec<([Float; 3], [Float; 3])>> {
+    let tokens: Vec<&str> = content.split_whitespace().collect();
+    let read = |start: usize, count: usize| -> Result<Vec<Float>> {
+        let values = tokens
+            .get(start..start + count)
+            .ok_or_else(|| anyhow::anyhow!("{}: unexpected end of file", path))?;
+        values
+            .iter()
+            .map(|v| v.parse::<Float>().map_err(|e| anyhow::anyhow!("{}: {}: '{}'", path, e, v)))
+            .collect()
+    };
+
+    let points_at = tokens
+        .iter()
+        .position(|&t| t == "POINTS")
+        .ok_or_else(|| anyhow::anyhow!("{}: no POINTS section", path))?;
+    let count: usize = tokens
+        .get(points_at + 1)
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("{}: invalid POINTS count", path))?;
+    let points = read(points_at + 3, 3 * count)?;
+
+    let vectors: Vec<usize> = (0..tokens.len()).filter(|&n| tokens[n] == "VECTORS").collect();
+    let vectors_at = vectors
+        .iter()
+        .copied()
+        .find(|&n| tokens.get(n + 1).is_some_and(|name| name.eq_ignore_ascii_case("velocity")))
+        .or(vectors.first().copied())
+        .ok_or_else(|| anyhow::anyhow!("{}: no VECTORS field", path))?;
+    let velocities = read(vectors_at + 3, 3 * count)?;
+
+    Ok(points
+        .chunks_exact(3)
+        .zip(velocities.chunks_exact(3))
+        .map(|(p, u)| ([p[0], p[1], p[2]], [u[0], u[1], u[2]]))
+        .collect())
+}
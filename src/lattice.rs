@@ -1,5 +1,200 @@
 use bytemuck::{Pod, Zeroable};
-use crate::Float;
+use std::fmt::Write;
+use std::sync::OnceLock;
+use crate::{
+    collision::{self, CumulantRates, MomentBasis, MrtRates, TrtRates},
+    Float,
+};
+
+/// Discrete velocity set of a lattice-Boltzmann model.
+///
+/// All models share the D3Q27 ordering (rest, faces, edges, corners) for the
+/// directions they contain, so the smaller sets are prefixes or subsets of it.
+/// Planar models (D2Q9) are embedded in 3D with a zero z component and run on
+/// a single z-slice.
+pub trait Lattice: Send + Sync {
+    /// Model name, e.g. "D3Q19"
+    fn name(&self) -> &'static str;
+
+    /// Discrete velocities
+    fn velocities(&self) -> &'static [[i32; 3]];
+
+    /// Weights for each direction
+    fn weights(&self) -> &'static [Float];
+
+    /// Opposite directions for bounce-back boundary conditions
+    fn opposite(&self) -> &'static [usize];
+
+    /// Orthogonal moment basis used by the MRT collision operator
+    fn moment_basis(&self) -> &'static MomentBasis;
+
+    /// Number of discrete velocities
+    fn q(&self) -> usize {
+        self.velocities().len()
+    }
+
+    /// Number of spatial dimensions (2 for planar models, which only have
+    /// velocities with a zero z component)
+    fn dimensions(&self) -> usize {
+        3
+    }
+
+    /// Speed of sound squared
+    fn cs2(&self) -> Float {
+        1.0 / 3.0
+    }
+
+    /// WGSL declarations of `Q`, `CS2`, `VELOCITIES`, `WEIGHTS` and `OPPOSITE`
+    /// prepended to every compute shader
+    fn wgsl_constants(&self) -> String {
+        let q = self.q();
+        let mut wgsl = String::new();
+        
+        writeln!(wgsl, "// {} constants (generated from lattice.rs)", self.name()).unwrap();
+        writeln!(wgsl, "const Q: u32 = {}u;", q).unwrap();
+        writeln!(wgsl, "const CS2: f32 = {:?};", self.cs2()).unwrap();
+        
+        writeln!(wgsl, "const VELOCITIES = array<array<i32, 3>, {}>(", q).unwrap();
+        for (i, c) in self.velocities().iter().enumerate() {
+            writeln!(wgsl, "    array<i32, 3>({}, {}, {}),  // {}", c[0], c[1], c[2], i).unwrap();
+        }
+        writeln!(wgsl, ");").unwrap();
+        
+        writeln!(wgsl, "const WEIGHTS = array<f32, {}>(", q).unwrap();
+        for (i, w) in self.weights().iter().enumerate() {
+            writeln!(wgsl, "    {:?},  // {}", w, i).unwrap();
+        }
+        writeln!(wgsl, ");").unwrap();
+        
+        writeln!(wgsl, "const OPPOSITE = array<u32, {}>(", q).unwrap();
+        for (i, o) in self.opposite().iter().enumerate() {
+            writeln!(wgsl, "    {}u,  // {}", o, i).unwrap();
+        }
+        writeln!(wgsl, ");").unwrap();
+        
+        wgsl
+    }
+}
+
+/// D2Q9 Lattice-Boltzmann model constants (velocities in the x-y plane)
+pub struct D2Q9;
+
+impl D2Q9 {
+    /// Number of discrete velocities
+    pub const Q: usize = 9;
+    
+    /// Discrete velocities (rest, faces and in-plane diagonals)
+    pub const VELOCITIES: [[i32; 3]; 9] = [
+        // Center
+        [0, 0, 0],
+        // Face neighbors (4)
+        [1, 0, 0], [-1, 0, 0], [0, 1, 0], [0, -1, 0],
+        // Diagonal neighbors (4)
+        [1, 1, 0], [1, -1, 0], [-1, 1, 0], [-1, -1, 0],
+    ];
+    
+    /// Weights for each direction
+    pub const WEIGHTS: [Float; 9] = [
+        // Center
+        4.0/9.0,
+        // Face neighbors (4)
+        1.0/9.0, 1.0/9.0, 1.0/9.0, 1.0/9.0,
+        // Diagonal neighbors (4)
+        1.0/36.0, 1.0/36.0, 1.0/36.0, 1.0/36.0,
+    ];
+    
+    /// Opposite directions for bounce-back boundary conditions
+    pub const OPPOSITE: [usize; 9] = [
+        0,  // Center stays the same
+        2, 1, 4, 3,  // Face opposites
+        8, 7, 6, 5,  // Diagonal opposites
+    ];
+    
+    /// Speed of sound squared
+    pub const CS2: Float = 1.0 / 3.0;
+}
+
+/// D3Q15 Lattice-Boltzmann model constants
+pub struct D3Q15;
+
+impl D3Q15 {
+    /// Number of discrete velocities
+    pub const Q: usize = 15;
+    
+    /// Discrete velocities (rest, faces and corners)
+    pub const VELOCITIES: [[i32; 3]; 15] = [
+        // Center
+        [0, 0, 0],
+        // Face neighbors (6)
+        [1, 0, 0], [-1, 0, 0], [0, 1, 0], [0, -1, 0], [0, 0, 1], [0, 0, -1],
+        // Corner neighbors (8)
+        [1, 1, 1], [1, 1, -1], [1, -1, 1], [1, -1, -1],
+        [-1, 1, 1], [-1, 1, -1], [-1, -1, 1], [-1, -1, -1],
+    ];
+    
+    /// Weights for each direction
+    pub const WEIGHTS: [Float; 15] = [
+        // Center
+        2.0/9.0,
+        // Face neighbors (6)
+        1.0/9.0, 1.0/9.0, 1.0/9.0, 1.0/9.0, 1.0/9.0, 1.0/9.0,
+        // Corner neighbors (8)
+        1.0/72.0, 1.0/72.0, 1.0/72.0, 1.0/72.0,
+        1.0/72.0, 1.0/72.0, 1.0/72.0, 1.0/72.0,
+    ];
+    
+    /// Opposite directions for bounce-back boundary conditions
+    pub const OPPOSITE: [usize; 15] = [
+        0,  // Center stays the same
+        2, 1, 4, 3, 6, 5,  // Face opposites
+        14, 13, 12, 11, 10, 9, 8, 7,  // Corner opposites
+    ];
+    
+    /// Speed of sound squared
+    pub const CS2: Float = 1.0 / 3.0;
+}
+
+/// D3Q19 Lattice-Boltzmann model constants
+pub struct D3Q19;
+
+impl D3Q19 {
+    /// Number of discrete velocities
+    pub const Q: usize = 19;
+    
+    /// Discrete velocities (rest, faces and edges)
+    pub const VELOCITIES: [[i32; 3]; 19] = [
+        // Center
+        [0, 0, 0],
+        // Face neighbors (6)
+        [1, 0, 0], [-1, 0, 0], [0, 1, 0], [0, -1, 0], [0, 0, 1], [0, 0, -1],
+        // Edge neighbors (12)
+        [1, 1, 0], [1, -1, 0], [-1, 1, 0], [-1, -1, 0],
+        [1, 0, 1], [1, 0, -1], [-1, 0, 1], [-1, 0, -1],
+        [0, 1, 1], [0, 1, -1], [0, -1, 1], [0, -1, -1],
+    ];
+    
+    /// Weights for each direction
+    pub const WEIGHTS: [Float; 19] = [
+        // Center
+        1.0/3.0,
+        // Face neighbors (6)
+        1.0/18.0, 1.0/18.0, 1.0/18.0, 1.0/18.0, 1.0/18.0, 1.0/18.0,
+        // Edge neighbors (12)
+        1.0/36.0, 1.0/36.0, 1.0/36.0, 1.0/36.0,
+        1.0/36.0, 1.0/36.0, 1.0/36.0, 1.0/36.0,
+        1.0/36.0, 1.0/36.0, 1.0/36.0, 1.0/36.0,
+    ];
+    
+    /// Opposite directions for bounce-back boundary conditions
+    pub const OPPOSITE: [usize; 19] = [
+        0,  // Center stays the same
+        2, 1, 4, 3, 6, 5,  // Face opposites
+        10, 9, 8, 7, 14, 13, 12, 11, 18, 17, 16, 15,  // Edge opposites
+    ];
+    
+    /// Speed of sound squared
+    pub const CS2: Float = 1.0 / 3.0;
+}
 
 /// D3Q27 Lattice-Boltzmann model constants and structures
 pub struct D3Q27;
@@ -42,7 +237,7 @@ impl D3Q27 {
     pub const OPPOSITE: [usize; 27] = [
         0,  // Center stays the same
         2, 1, 4, 3, 6, 5,  // Face opposites
-        9, 8, 7, 10, 13, 12, 11, 14, 17, 16, 15, 18,  // Edge opposites
+        10, 9, 8, 7, 14, 13, 12, 11, 18, 17, 16, 15,  // Edge opposites
         26, 25, 24, 23, 22, 21, 20, 19,  // Corner opposites
     ];
     
@@ -50,57 +245,115 @@ impl D3Q27 {
     pub const CS2: Float = 1.0 / 3.0;
 }
 
+
+macro_rules! impl_lattice {
+    ($model:ident, $name:literal, $dimensions:literal) => {
+        impl Lattice for $model {
+            fn name(&self) -> &'static str {
+                $name
+            }
+            
+            fn dimensions(&self) -> usize {
+                $dimensions
+            }
+            
+            fn velocities(&self) -> &'static [[i32; 3]] {
+                &Self::VELOCITIES
+            }
+            
+            fn weights(&self) -> &'static [Float] {
+                &Self::WEIGHTS
+            }
+            
+            fn opposite(&self) -> &'static [usize] {
+                &Self::OPPOSITE
+            }
+            
+            fn moment_basis(&self) -> &'static MomentBasis {
+                static BASIS: OnceLock<MomentBasis> = OnceLock::new();
+                BASIS.get_or_init(|| MomentBasis::new(self))
+            }
+        }
+    };
+}
+
+impl_lattice!(D2Q9, "D2Q9", 2);
+impl_lattice!(D3Q15, "D3Q15", 3);
+impl_lattice!(D3Q19, "D3Q19", 3);
+impl_lattice!(D3Q27, "D3Q27", 3);
+
+/// Largest number of discrete velocities of the supported lattices
+pub const MAX_Q: usize = D3Q27::Q;
+
 /// A single lattice point containing distribution functions
+///
+/// `f` is sized for the largest lattice (D3Q27); smaller velocity sets only
+/// use the first `Q` entries. On the GPU the unused entries are not stored.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 pub struct LatticePoint {
     /// Distribution functions (f_i)
-    pub f: [Float; 27],
+    pub f: [Float; MAX_Q],
     /// Macroscopic density
     pub density: Float,
     /// Macroscopic velocity
     pub velocity: [Float; 3],
     /// Node type (0: fluid, 1: solid, 2: inlet, 3: outlet)
     pub node_type: u32,
-    /// Padding for alignment
-    pub _padding: [u32; 3],
+    /// Subgrid eddy viscosity of the last collision (LES only, lattice units)
+    pub eddy_viscosity: Float,
+    /// Boundary scheme of inlet, outlet and wall face nodes (`InletScheme`,
+    /// `OutletScheme` or `WallScheme` discriminant)
+    pub boundary: u32,
+    /// Domain face of inlet, outlet and wall face nodes (0: -x, 1: +x, 2: -y, 3: +y, 4: -z, 5: +z),
+    /// or `BODY_FACE` for the solid nodes of the STL body
+    pub face: u32,
 }
 
 impl Default for LatticePoint {
     fn default() -> Self {
         Self {
-            f: [0.0; 27],
+            f: [0.0; MAX_Q],
             density: 1.0,
             velocity: [0.0; 3],
             node_type: 0,
-            _padding: [0; 3],
+            eddy_viscosity: 0.0,
+            boundary: 0,
+            face: 0,
         }
     }
 }
 
 impl LatticePoint {
+    /// Number of 32-bit words of the non-distribution fields
+    /// (density, velocity, node type, eddy viscosity, boundary scheme and face)
+    pub const EXTRA_WORDS: usize = 8;
+    
     /// Initialize with equilibrium distribution
-    pub fn new_equilibrium(density: Float, velocity: [Float; 3], node_type: u32) -> Self {
+    pub fn new_equilibrium(lattice: &dyn Lattice, density: Float, velocity: [Float; 3], node_type: u32) -> Self {
         let mut point = Self {
-            f: [0.0; 27],
+            f: [0.0; MAX_Q],
             density,
             velocity,
             node_type,
-            _padding: [0; 3],
+            eddy_viscosity: 0.0,
+            boundary: 0,
+            face: 0,
         };
         
         // Calculate equilibrium distribution
-        for i in 0..D3Q27::Q {
-            point.f[i] = Self::equilibrium_distribution(i, density, velocity);
+        for i in 0..lattice.q() {
+            point.f[i] = Self::equilibrium_distribution(lattice, i, density, velocity);
         }
         
         point
     }
     
     /// Calculate equilibrium distribution function
-    pub fn equilibrium_distribution(direction: usize, density: Float, velocity: [Float; 3]) -> Float {
-        let weight = D3Q27::WEIGHTS[direction];
-        let c = D3Q27::VELOCITIES[direction];
+    pub fn equilibrium_distribution(lattice: &dyn Lattice, direction: usize, density: Float, velocity: [Float; 3]) -> Float {
+        let weight = lattice.weights()[direction];
+        let c = lattice.velocities()[direction];
+        let cs2 = lattice.cs2();
         
         // Dot product of velocity and lattice velocity
         let cu = c[0] as Float * velocity[0] + c[1] as Float * velocity[1] + c[2] as Float * velocity[2];
@@ -109,23 +362,24 @@ impl LatticePoint {
         let u2 = velocity[0] * velocity[0] + velocity[1] * velocity[1] + velocity[2] * velocity[2];
         
         // Equilibrium distribution function
-        weight * density * (1.0 + cu / D3Q27::CS2 + 
-                           cu * cu / (2.0 * D3Q27::CS2 * D3Q27::CS2) - 
-                           u2 / (2.0 * D3Q27::CS2))
+        weight * density * (1.0 + cu / cs2 + 
+                           cu * cu / (2.0 * cs2 * cs2) - 
+                           u2 / (2.0 * cs2))
     }
     
     /// Calculate macroscopic quantities from distribution functions
-    pub fn calculate_macroscopic(&mut self) {
+    pub fn calculate_macroscopic(&mut self, lattice: &dyn Lattice) {
+        let q = lattice.q();
+        
         // Density
-        self.density = self.f.iter().sum();
+        self.density = self.f[..q].iter().sum();
         
         // Velocity
         self.velocity = [0.0; 3];
-        for i in 0..D3Q27::Q {
-            let c = D3Q27::VELOCITIES[i];
-            self.velocity[0] += self.f[i] * c[0] as Float;
-            self.velocity[1] += self.f[i] * c[1] as Float;
-            self.velocity[2] += self.f[i] * c[2] as Float;
+        for (f, c) in self.f[..q].iter().zip(lattice.velocities()) {
+            self.velocity[0] += f * c[0] as Float;
+            self.velocity[1] += f * c[1] as Float;
+            self.velocity[2] += f * c[2] as Float;
         }
         
         if self.density > 1e-10 {
@@ -136,12 +390,79 @@ impl LatticePoint {
     }
     
     /// Apply BGK collision step
-    pub fn collide(&mut self, tau: Float) {
+    pub fn collide(&mut self, lattice: &dyn Lattice, tau: Float) {
         let omega = 1.0 / tau;
         
-        for i in 0..D3Q27::Q {
-            let f_eq = Self::equilibrium_distribution(i, self.density, self.velocity);
+        for i in 0..lattice.q() {
+            let f_eq = Self::equilibrium_distribution(lattice, i, self.density, self.velocity);
             self.f[i] += omega * (f_eq - self.f[i]);
         }
     }
+    
+    /// Apply MRT collision step: the non-equilibrium part of each moment is
+    /// relaxed with the rate of its moment group
+    pub fn collide_mrt(&mut self, lattice: &dyn Lattice, rates: &MrtRates) {
+        let q = lattice.q();
+        let basis = lattice.moment_basis();
+        
+        let mut f_neq = [0.0; MAX_Q];
+        for (i, f_neq) in f_neq[..q].iter_mut().enumerate() {
+            *f_neq = self.f[i] - Self::equilibrium_distribution(lattice, i, self.density, self.velocity);
+        }
+        
+        let mut m_neq = basis.to_moments(&f_neq[..q]);
+        for (m, group) in m_neq[..q].iter_mut().zip(basis.groups()) {
+            *m *= rates.for_group(*group);
+        }
+        
+        let df = basis.to_populations(&m_neq[..q]);
+        for (f, df) in self.f[..q].iter_mut().zip(&df[..q]) {
+            *f -= df;
+        }
+    }
+    
+    /// Apply TRT collision step: the symmetric and antisymmetric parts of
+    /// each opposite pair f_i, f_-i are relaxed with separate rates
+    pub fn collide_trt(&mut self, lattice: &dyn Lattice, rates: &TrtRates) {
+        let q = lattice.q();
+        let opposite = lattice.opposite();
+        
+        let mut f_eq = [0.0; MAX_Q];
+        for (i, f_eq) in f_eq[..q].iter_mut().enumerate() {
+            *f_eq = Self::equilibrium_distribution(lattice, i, self.density, self.velocity);
+        }
+        
+        let f = self.f;
+        for i in 0..q {
+            let j = opposite[i];
+            let neq_plus = 0.5 * ((f[i] + f[j]) - (f_eq[i] + f_eq[j]));
+            let neq_minus = 0.5 * ((f[i] - f[j]) - (f_eq[i] - f_eq[j]));
+            self.f[i] = f[i] - rates.plus * neq_plus - rates.minus * neq_minus;
+        }
+    }
+    
+    /// Apply cumulant collision step (D3Q27 only): the populations are
+    /// transformed to central moments and cumulants, which are relaxed order
+    /// by order and transformed back
+    pub fn collide_cumulant(&mut self, lattice: &dyn Lattice, rates: &CumulantRates) {
+        let q = lattice.q();
+        collision::cumulant_relax(lattice, &mut self.f[..q], self.density, self.velocity, rates);
+    }
+    
+    /// Append the GPU representation of this point (`q` distributions
+    /// followed by the remaining fields) to `words`
+    pub fn write_gpu_words(&self, q: usize, words: &mut Vec<u32>) {
+        let raw: &[u32] = bytemuck::cast_slice(std::slice::from_ref(self));
+        words.extend_from_slice(&raw[..q]);
+        words.extend_from_slice(&raw[MAX_Q..]);
+    }
+    
+    /// Inverse of `write_gpu_words`
+    pub fn from_gpu_words(q: usize, words: &[u32]) -> Self {
+        let mut point = Self::zeroed();
+        let raw: &mut [u32] = bytemuck::cast_slice_mut(std::slice::from_mut(&mut point));
+        raw[..q].copy_from_slice(&words[..q]);
+        raw[MAX_Q..].copy_from_slice(&words[q..q + Self::EXTRA_WORDS]);
+        point
+    }
 }
@@ -1,15 +1,27 @@
 pub mod config;
 pub mod geometry;
 pub mod lattice;
+pub mod collision;
+pub mod turbulence;
+pub mod boundary;
+pub mod inlet;
+pub mod units;
+pub mod validation;
 pub mod solver;
 pub mod gpu;
+pub mod cpu;
+pub mod backend;
 pub mod output;
+pub mod checkpoint;
 
 pub use config::Config;
 pub use geometry::Geometry;
-pub use lattice::{D3Q27, LatticePoint};
+pub use lattice::{Lattice, D2Q9, D3Q15, D3Q19, D3Q27, LatticePoint};
+pub use collision::{CollisionOperator, MomentBasis};
 pub use solver::LBMSolver;
 pub use gpu::GPUContext;
+pub use cpu::CPUContext;
+pub use backend::ComputeBackend;
 pub use output::VTKWriter;
 
 pub type Float = f32;
@@ -1,51 +1,252 @@
-use lattice_boltzmann_rs::{Config, LBMSolver, VTKWriter};
+use lattice_boltzmann_rs::{
+    backend::memory_estimate,
+    config::{BackendType, PowerPreference},
+    lattice::MAX_Q,
+    units::UnitConverter,
+    Config, GPUContext, Geometry, LBMSolver, VTKWriter,
+};
 use anyhow::Result;
-use env_logger;
-use log::info;
-use std::env;
+use clap::{ArgAction, Args, Parser, Subcommand};
+use log::{info, warn};
+
+/// Lattice Boltzmann flow solver for STL geometries on the GPU or CPU
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    /// Log level: off, error, warn, info, debug or trace (default: RUST_LOG, else error)
+    #[arg(long, global = true, value_name = "LEVEL")]
+    log_level: Option<log::LevelFilter>,
+
+    /// Raise the log level (-v info, -vv debug, -vvv trace)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Arguments of `run`, for the short form `lattice-boltzmann-rs config.json geometry.stl`
+    #[command(flatten)]
+    run: Option<RunArgs>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a simulation
+    Run(RunArgs),
+    /// Check the config (against the geometry's bounds if given) without running it
+    Validate {
+        #[command(flatten)]
+        setup: Setup,
+        /// STL or polygon file of the geometry
+        geometry: Option<String>,
+    },
+    /// Voxelize the geometry and write the node types as VTK
+    Voxelize {
+        #[command(flatten)]
+        setup: Setup,
+        /// STL or polygon file of the geometry
+        geometry: String,
+        /// VTK file to write (default: geometry.vtk in the output directory)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Estimate the memory of a run and list the GPU adapters it could use
+    Info {
+        #[command(flatten)]
+        setup: Setup,
+    },
+    /// Continue a run from a checkpoint (see output.checkpoint_frequency)
+    Resume {
+        #[command(flatten)]
+        run: RunArgs,
+        /// Checkpoint file (default: checkpoint.bin in the output directory)
+        #[arg(long)]
+        checkpoint: Option<String>,
+    },
+    /// Write the config with the overrides applied, in the format of the output file
+    Convert {
+        #[command(flatten)]
+        setup: Setup,
+        /// Config file to write (.json, .toml, .yaml or .yml)
+        #[arg(short, long)]
+        output: String,
+        /// Convert a config in physical units to lattice units
+        #[arg(long)]
+        lattice_units: bool,
+        /// Geometry for a characteristic length taken from its extent
+        #[arg(long)]
+        geometry: Option<String>,
+    },
+    /// Print the JSON Schema of config files, for editor completion
+    Schema {
+        /// Write the schema to this file instead of standard output
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Args)]
+struct RunArgs {
+    #[command(flatten)]
+    setup: Setup,
+    /// STL file containing the geometry (2D/D2Q9 runs: STL sliced at domain.slice_z, or a polygon file)
+    geometry: String,
+}
+
+/// The config file and the command line options that override it
+#[derive(Args)]
+struct Setup {
+    /// Config file with the simulation parameters (JSON, or TOML/YAML by extension)
+    config: String,
+
+    /// Override a config field, e.g. `--set domain.nx=200` (repeatable)
+    #[arg(long = "set", value_name = "PATH=VALUE")]
+    overrides: Vec<String>,
+
+    /// Write output to this directory instead of output.output_directory
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<String>,
+
+    /// Compute backend: gpu or cpu
+    #[arg(long)]
+    backend: Option<BackendType>,
+
+    /// wgpu backends, e.g. vulkan,gl (default: all)
+    #[arg(long, value_name = "LIST")]
+    gpu_backends: Option<String>,
+
+    /// GPU power preference: high, low or none
+    #[arg(long, value_name = "PREF")]
+    power_preference: Option<PowerPreference>,
+
+    /// Use the adapter whose name contains NAME
+    #[arg(long, value_name = "NAME")]
+    adapter: Option<String>,
+
+    /// Do not retry on a software adapter
+    #[arg(long)]
+    no_fallback: bool,
+}
+
+impl Setup {
+    /// Load the config file and apply the command line overrides
+    fn load(&self) -> Result<Config> {
+        info!("Loading configuration from: {}", self.config);
+        let mut config = Config::from_file(&self.config)?;
+        for assignment in &self.overrides {
+            config.apply_override(assignment)?;
+        }
+        if let Some(directory) = &self.output_dir {
+            config.output.output_directory = directory.clone();
+        }
+        if let Some(backend) = self.backend {
+            config.simulation.backend = backend;
+        }
+        if let Some(backends) = &self.gpu_backends {
+            config.gpu.backends = backends.clone();
+        }
+        if let Some(preference) = self.power_preference {
+            config.gpu.power_preference = preference;
+        }
+        if self.adapter.is_some() {
+            config.gpu.adapter_name = self.adapter.clone();
+        }
+        if self.no_fallback {
+            config.gpu.allow_fallback = false;
+        }
+        Ok(config)
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    env_logger::init();
-    
-    // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        eprintln!("Usage: {} <config.json> <geometry.stl>", args[0]);
-        eprintln!("  config.json - JSON file containing simulation parameters");
-        eprintln!("  geometry.stl - STL file containing the geometry");
-        std::process::exit(1);
-    }
-    
-    let config_path = &args[1];
-    let stl_path = &args[2];
-    
-    info!("Loading configuration from: {}", config_path);
-    let config = Config::from_file(config_path)?;
-    
+    let cli = Cli::parse();
+
+    // Initialize logging - RUST_LOG unless a level is given on the command line
+    let mut logger = env_logger::Builder::from_default_env();
+    if let Some(level) = cli.log_level {
+        logger.filter_level(level);
+    } else if cli.verbose > 0 {
+        let level = match cli.verbose {
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        };
+        logger.filter_level(level);
+    }
+    logger.init();
+
+    match (cli.command, cli.run) {
+        (Some(Command::Run(args)), _) | (None, Some(args)) => run(&args, None).await,
+        (Some(Command::Resume { run: args, checkpoint }), _) => {
+            let config = args.setup.load()?;
+            let checkpoint = checkpoint
+                .unwrap_or_else(|| format!("{}/checkpoint.bin", config.output.output_directory));
+            run(&args, Some(checkpoint)).await
+        }
+        (Some(Command::Validate { setup, geometry }), _) => validate(&setup, geometry.as_deref()),
+        (Some(Command::Voxelize { setup, geometry, output }), _) => voxelize(&setup, &geometry, output),
+        (Some(Command::Info { setup }), _) => show_info(&setup),
+        (Some(Command::Convert { setup, output, lattice_units, geometry }), _) => {
+            convert(&setup, &output, lattice_units, geometry.as_deref())
+        }
+        (Some(Command::Schema { output }), _) => {
+            let schema = serde_json::to_string_pretty(&Config::json_schema())? + "\n";
+            match output {
+                Some(path) => std::fs::write(path, schema)?,
+                None => print!("{}", schema),
+            }
+            Ok(())
+        }
+        (None, None) => unreachable!("clap requires a subcommand or the run arguments"),
+    }
+}
+
+/// Run a simulation, optionally continuing from a checkpoint
+async fn run(args: &RunArgs, checkpoint: Option<String>) -> Result<()> {
+    let mut config = args.setup.load()?;
+    let stl_path = &args.geometry;
+
+    // Pre-flight checks - errors stop the run before the geometry is
+    // voxelized, the solver repeats them and logs the warnings
+    let body = match Geometry::surface_bounds(stl_path, &config) {
+        Ok(bounds) => Some(bounds),
+        Err(e) => {
+            warn!("Could not read the geometry bounds of {}: {}", stl_path, e);
+            None
+        }
+    };
+    config.validate(body).into_result()?;
+    config.resolve_characteristic_length(body)?;
+
+    let units = UnitConverter::from_config(&config)?;
     info!("Simulation parameters:");
     info!("  Domain: {}x{}x{}", config.domain.nx, config.domain.ny, config.domain.nz);
-    info!("  Reynolds number: {}", config.physics.reynolds_number);
+    info!("  Reynolds number: {:.1} (characteristic length {})", units.reynolds_number, config.characteristic_length());
     info!("  Inlet velocity: {:?}", config.physics.inlet_velocity);
     info!("  Max iterations: {}", config.simulation.max_iterations);
     info!("  Output frequency: {}", config.output.output_frequency);
-    info!("  Tau (relaxation time): {}", config.calculate_tau());
-    
+    info!("  Tau (relaxation time): {}", units.tau);
+    info!("  Units: {}", units.describe());
+    info!("  Backend: {:?}", config.simulation.backend);
+
     // Create and run solver
     info!("Initializing LBM solver...");
     let mut solver = LBMSolver::new(config, stl_path).await?;
-    
+    if let Some(path) = &checkpoint {
+        solver.resume(path)?;
+    }
+
     // Write geometry file for visualization
-    let vtk_writer = VTKWriter::new(solver.get_config());
+    let vtk_writer = VTKWriter::new(solver.get_config(), solver.get_units());
     let geometry_filename = format!("{}/geometry.vtk", solver.get_config().output.output_directory);
     vtk_writer.write_geometry(&geometry_filename, solver.get_geometry())?;
     info!("Wrote geometry file: {}", geometry_filename);
-    
+
     // Run simulation
     info!("Starting simulation...");
     solver.run().await?;
-    
+
     info!("Simulation completed successfully!");
     info!("Output files written to: {}", solver.get_config().output.output_directory);
     info!("To visualize:");
@@ -55,6 +256,99 @@ async fn main() -> Result<()> {
     info!("  4. Use the 'Velocity' vector field for streamlines");
     info!("  5. Use 'VelocityMagnitude' or 'Pressure' for contour plots");
     info!("  6. Use 'Vorticity' to visualize flow structures");
-    
+
     Ok(())
 }
+
+/// Print every pre-flight issue and fail if any of them is an error
+fn validate(setup: &Setup, geometry: Option<&str>) -> Result<()> {
+    let config = setup.load()?;
+    let body = geometry
+        .map(|path| Geometry::surface_bounds(path, &config))
+        .transpose()?;
+    let validation = config.validate(body);
+    for issue in &validation.issues {
+        println!("{}", issue);
+    }
+
+    let errors = validation.errors().count();
+    anyhow::ensure!(errors == 0, "{} has {} error(s)", setup.config, errors);
+    println!("{}: OK ({} warning(s))", setup.config, validation.warnings().count());
+    Ok(())
+}
+
+/// Voxelize the geometry onto the grid and write it for inspection
+fn voxelize(setup: &Setup, geometry_path: &str, output: Option<String>) -> Result<()> {
+    let mut config = setup.load()?;
+    let geometry = Geometry::load(geometry_path, &config)?;
+    config.resolve_characteristic_length(Some(geometry.bounds()))?;
+
+    let filename = match output {
+        Some(filename) => filename,
+        None => {
+            std::fs::create_dir_all(&config.output.output_directory)?;
+            format!("{}/geometry.vtk", config.output.output_directory)
+        }
+    };
+    let units = UnitConverter::from_config(&config)?;
+    VTKWriter::new(&config, &units).write_geometry(&filename, &geometry)?;
+
+    let [low, high] = geometry.bounds();
+    println!("Grid: {} x {} x {}", config.domain.nx, config.domain.ny, config.domain.nz);
+    println!("Geometry bounds: {:?} to {:?}", low, high);
+    println!("Solid nodes: {}", geometry.solid_nodes.len());
+    println!("Fluid nodes: {}", geometry.fluid_nodes.len());
+    println!("Wrote {}", filename);
+    Ok(())
+}
+
+/// Report the memory a run needs and the adapters available to it
+fn show_info(setup: &Setup) -> Result<()> {
+    let config = setup.load()?;
+    let estimate = memory_estimate(&config);
+    let model = config.simulation.lattice.lattice();
+
+    println!("Domain: {} x {} x {} ({} nodes), {}",
+             config.domain.nx, config.domain.ny, config.domain.nz, estimate.nodes, model.name());
+    println!("Backend: {:?}", config.simulation.backend);
+    println!("Lattice state: {} per copy", megabytes(estimate.lattice_bytes));
+    println!("Backend memory: {} (excluding wall links)", megabytes(estimate.backend_bytes));
+    println!("Host memory: {} (also the size of a checkpoint)", megabytes(estimate.host_bytes));
+    if model.q() < MAX_Q {
+        println!("Host and CPU nodes hold {} distributions for any lattice, of which {} uses {}",
+                 MAX_Q, model.name(), model.q());
+    }
+
+    if config.simulation.backend == BackendType::Gpu {
+        let adapters = GPUContext::list_adapters(&config.gpu)?;
+        if adapters.is_empty() {
+            println!("No GPU adapters found on backends '{}'", config.gpu.backends);
+        }
+        for (adapter, limits) in adapters {
+            let largest = u64::from(limits.max_storage_buffer_binding_size).min(limits.max_buffer_size);
+            println!("Adapter: {} ({:?}, {:?}), max storage buffer {} - {}",
+                     adapter.name, adapter.backend, adapter.device_type, megabytes(largest),
+                     if estimate.lattice_bytes <= largest { "fits" } else { "lattice too large" });
+        }
+    }
+    Ok(())
+}
+
+/// Write the config after the overrides, in lattice units if requested
+fn convert(setup: &Setup, output: &str, lattice_units: bool, geometry: Option<&str>) -> Result<()> {
+    let mut config = setup.load()?;
+    if lattice_units {
+        let body = geometry
+            .map(|path| Geometry::surface_bounds(path, &config))
+            .transpose()?;
+        config.resolve_characteristic_length(body)?;
+        config = UnitConverter::from_config(&config)?.lattice_config(&config);
+    }
+    config.to_file(output)?;
+    println!("Wrote {}", output);
+    Ok(())
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
@@ -1,17 +1,21 @@
 use anyhow::Result;
 use std::fs::File;
 use std::io::Write;
-use crate::{config::Config, lattice::LatticePoint, Float};
+use crate::{config::Config, lattice::LatticePoint, units::UnitConverter, Float};
 
+/// Writes lattice snapshots converted to the units of the config (see
+/// `UnitConverter`), with the conversion factors in each file header
 pub struct VTKWriter {
     config: Config,
+    units: UnitConverter,
     collection_entries: Vec<(usize, f64, String)>, // (iteration, time, filename)
 }
 
 impl VTKWriter {
-    pub fn new(config: &Config) -> Self {
+    pub fn new(config: &Config, units: &UnitConverter) -> Self {
         Self {
             config: config.clone(),
+            units: *units,
             collection_entries: Vec::new(),
         }
     }
@@ -23,27 +27,28 @@ impl VTKWriter {
         
         let mut file = File::create(filename)?;
         
-        // Calculate physical time (assuming unit time step for now)
-        let time = iteration as f64;
+        // Physical time (the iteration in lattice units)
+        let time = self.units.time(iteration);
+        let velocity_scale = self.units.velocity();
         
         // Track this file for the collection
         self.collection_entries.push((iteration, time, filename.to_string()));
         
         // Write VTK header for structured grid
         writeln!(file, "# vtk DataFile Version 3.0")?;
-        writeln!(file, "LBM Solution - Iteration {} Time {:.3}", iteration, time)?;
+        writeln!(file, "LBM Solution - Iteration {} Time {:.6e} - {}", iteration, time, self.units.describe())?;
         writeln!(file, "ASCII")?;
         writeln!(file, "DATASET STRUCTURED_GRID")?;
         writeln!(file, "DIMENSIONS {} {} {}", nx, ny, nz)?;
         
-        // Write points
+        // Write points at the cell centres, where the geometry places the nodes
         writeln!(file, "POINTS {} float", nx * ny * nz)?;
         for k in 0..nz {
             for j in 0..ny {
                 for i in 0..nx {
-                    let x = i as Float * self.config.domain.dx;
-                    let y = j as Float * self.config.domain.dy;
-                    let z = k as Float * self.config.domain.dz;
+                    let x = (i as Float + 0.5) * self.config.domain.dx;
+                    let y = (j as Float + 0.5) * self.config.domain.dy;
+                    let z = (k as Float + 0.5) * self.config.domain.dz;
                     writeln!(file, "{} {} {}", x, y, z)?;
                 }
             }
@@ -56,13 +61,14 @@ impl VTKWriter {
         writeln!(file, "SCALARS Density float")?;
         writeln!(file, "LOOKUP_TABLE default")?;
         for point in lattice {
-            writeln!(file, "{:.6}", point.density)?;
+            writeln!(file, "{:.6e}", point.density * self.units.density)?;
         }
         
         // Velocity
         writeln!(file, "VECTORS Velocity float")?;
         for point in lattice {
-            writeln!(file, "{:.6} {:.6} {:.6}", point.velocity[0], point.velocity[1], point.velocity[2])?;
+            let [u, v, w] = point.velocity.map(|u| u * velocity_scale);
+            writeln!(file, "{:.6e} {:.6e} {:.6e}", u, v, w)?;
         }
         
         // Velocity magnitude
@@ -72,7 +78,7 @@ impl VTKWriter {
             let vel_mag = (point.velocity[0].powi(2) + 
                           point.velocity[1].powi(2) + 
                           point.velocity[2].powi(2)).sqrt();
-            writeln!(file, "{:.6}", vel_mag)?;
+            writeln!(file, "{:.6e}", vel_mag * velocity_scale)?;
         }
         
         // Node type
@@ -101,20 +107,29 @@ impl VTKWriter {
         writeln!(file, "LOOKUP_TABLE default")?;
         for point in lattice {
             let pressure = (point.density - self.config.physics.density) / 3.0; // cs^2 = 1/3
-            writeln!(file, "{:.6}", pressure)?;
+            writeln!(file, "{:.6e}", pressure * self.units.pressure())?;
         }
         
-        // Vorticity
+        // Subgrid eddy viscosity (LES)
+        if self.config.output.eddy_viscosity {
+            writeln!(file, "SCALARS EddyViscosity float")?;
+            writeln!(file, "LOOKUP_TABLE default")?;
+            for point in lattice {
+                writeln!(file, "{:.6e}", point.eddy_viscosity * self.units.viscosity())?;
+            }
+        }
+        
+        // Vorticity, from the lattice velocities over the grid spacing
         let vorticity = self.calculate_vorticity(lattice);
         writeln!(file, "VECTORS Vorticity float")?;
         for k in 0..nz {
             for j in 0..ny {
                 for i in 0..nx {
                     let idx = i + j * nx + k * nx * ny;
-                    writeln!(file, "{:.6} {:.6} {:.6}", 
-                            vorticity[idx * 3], 
-                            vorticity[idx * 3 + 1], 
-                            vorticity[idx * 3 + 2])?;
+                    writeln!(file, "{:.6e} {:.6e} {:.6e}", 
+                            vorticity[idx * 3] * velocity_scale, 
+                            vorticity[idx * 3 + 1] * velocity_scale, 
+                            vorticity[idx * 3 + 2] * velocity_scale)?;
                 }
             }
         }
@@ -132,7 +147,11 @@ impl VTKWriter {
         
         let mut vorticity = vec![0.0; lattice.len() * 3];
         
-        for k in 1..nz-1 {
+        // Planar (nz = 1) runs have no z-neighbours: only the x-y derivatives contribute
+        let planar = nz < 3;
+        let k_range = if planar { 0..nz } else { 1..nz - 1 };
+        
+        for k in k_range {
             for j in 1..ny-1 {
                 for i in 1..nx-1 {
                     let idx = i + j * nx + k * nx * ny;
@@ -142,18 +161,23 @@ impl VTKWriter {
                     let idx_xm = (i - 1) + j * nx + k * nx * ny;
                     let idx_yp = i + (j + 1) * nx + k * nx * ny;
                     let idx_ym = i + (j - 1) * nx + k * nx * ny;
-                    let idx_zp = i + j * nx + (k + 1) * nx * ny;
-                    let idx_zm = i + j * nx + (k - 1) * nx * ny;
                     
                     // Only calculate for fluid nodes
                     if lattice[idx].node_type == 0 {
+                        let (dudz, dvdz) = if planar {
+                            (0.0, 0.0)
+                        } else {
+                            let idx_zp = i + j * nx + (k + 1) * nx * ny;
+                            let idx_zm = i + j * nx + (k - 1) * nx * ny;
+                            ((lattice[idx_zp].velocity[0] - lattice[idx_zm].velocity[0]) / (2.0 * dz),
+                             (lattice[idx_zp].velocity[1] - lattice[idx_zm].velocity[1]) / (2.0 * dz))
+                        };
+                        
                         // dw/dy - dv/dz (x-component of vorticity)
                         let dwdy = (lattice[idx_yp].velocity[2] - lattice[idx_ym].velocity[2]) / (2.0 * dy);
-                        let dvdz = (lattice[idx_zp].velocity[1] - lattice[idx_zm].velocity[1]) / (2.0 * dz);
                         vorticity[idx * 3] = dwdy - dvdz;
                         
                         // du/dz - dw/dx (y-component of vorticity)
-                        let dudz = (lattice[idx_zp].velocity[0] - lattice[idx_zm].velocity[0]) / (2.0 * dz);
                         let dwdx = (lattice[idx_xp].velocity[2] - lattice[idx_xm].velocity[2]) / (2.0 * dx);
                         vorticity[idx * 3 + 1] = dudz - dwdx;
                         
@@ -183,14 +207,14 @@ impl VTKWriter {
         writeln!(file, "DATASET STRUCTURED_GRID")?;
         writeln!(file, "DIMENSIONS {} {} {}", nx, ny, nz)?;
         
-        // Write points
+        // Write points at the cell centres, where the geometry places the nodes
         writeln!(file, "POINTS {} float", nx * ny * nz)?;
         for k in 0..nz {
             for j in 0..ny {
                 for i in 0..nx {
-                    let x = i as Float * self.config.domain.dx;
-                    let y = j as Float * self.config.domain.dy;
-                    let z = k as Float * self.config.domain.dz;
+                    let x = (i as Float + 0.5) * self.config.domain.dx;
+                    let y = (j as Float + 0.5) * self.config.domain.dy;
+                    let z = (k as Float + 0.5) * self.config.domain.dz;
                     writeln!(file, "{} {} {}", x, y, z)?;
                 }
             }
@@ -222,6 +246,32 @@ impl VTKWriter {
         Ok(())
     }
     
+    /// Path of the snapshot written at `iteration`
+    pub fn snapshot_path(&self, iteration: usize) -> String {
+        format!("{}/output_{:06}.{}",
+                self.config.output.output_directory,
+                iteration,
+                self.config.output.output_format)
+    }
+    
+    /// Whether the collection already holds a snapshot of `iteration`
+    pub fn has_snapshot(&self, iteration: usize) -> bool {
+        self.collection_entries.iter().any(|(step, _, _)| *step == iteration)
+    }
+    
+    /// Re-register the snapshots an earlier run left in the output directory
+    /// up to `iteration`, so the collection of a resumed run covers the whole
+    /// time series
+    pub fn restore_collection(&mut self, iteration: usize) {
+        self.collection_entries.clear();
+        for step in (0..=iteration).step_by(self.config.output.output_frequency) {
+            let filename = self.snapshot_path(step);
+            if std::path::Path::new(&filename).exists() {
+                self.collection_entries.push((step, self.units.time(step), filename));
+            }
+        }
+    }
+    
     /// Write a ParaView collection file that groups all VTK files with time information
     pub fn write_collection(&self, collection_filename: &str) -> Result<()> {
         let mut file = File::create(collection_filename)?;
@@ -236,7 +286,7 @@ impl VTKWriter {
                 .file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or(filename);
-            writeln!(file, "    <DataSet timestep=\"{:.6}\" part=\"0\" file=\"{}\"/>", 
+            writeln!(file, "    <DataSet timestep=\"{:.6e}\" part=\"0\" file=\"{}\"/>", 
                      time, basename)?;
         }
         
@@ -1,93 +1,285 @@
-// D3Q27 Lattice-Boltzmann boundary conditions shader
+// Lattice-Boltzmann boundary conditions shader
 
 struct LatticePoint {
-    f: array<f32, 27>,           // Distribution functions
+    f: array<f32, Q>,            // Distribution functions
     density: f32,                // Macroscopic density
     velocity: array<f32, 3>,     // Macroscopic velocity
     node_type: u32,              // Node type (0: fluid, 1: solid, 2: inlet, 3: outlet)
-    padding: array<u32, 3>,      // Padding for alignment
+    eddy_viscosity: f32,         // Subgrid eddy viscosity (LES only)
+    boundary: u32,               // Boundary scheme of inlet/outlet/wall face nodes
+    face: u32,                   // Domain face of inlet/outlet/wall face nodes (0: -x, 1: +x, 2: -y, 3: +y, 4: -z, 5: +z, 6: STL body)
 }
 
 struct Config {
-    domain_size: vec4<u32>,         // nx, ny, nz, padding - 16 bytes aligned
+    domain_size: vec4<u32>,         // nx, ny, nz, periodic axes bit mask - 16 bytes aligned
     tau: f32,                       // 4 bytes
     density: f32,                   // 4 bytes  
-    padding1: vec2<f32>,            // 8 bytes - total 16 bytes for this group
+    collision_model: u32,           // 4 bytes (0: BGK, 1: MRT, 2: TRT, 3: cumulant)
+    inlet_profile: u32,             // 4 bytes - 1 if inlet velocities come from the profile buffer - total 16 bytes for this group
     inlet_velocity: vec4<f32>,      // 16 bytes aligned
+    relaxation: vec4<f32>,          // MRT shear (TRT symmetric), bulk, ghost (cumulant third order), TRT antisymmetric rates
+    relaxation_high: vec4<f32>,     // Cumulant fourth, fifth and sixth order rates, padding
+    les_model: u32,                 // 4 bytes (0: none, 1: Smagorinsky, 2: WALE, 3: Vreman)
+    les_constant: f32,              // 4 bytes - model constant (Cs, Cw or c)
+    padding2: vec2<f32>,            // 8 bytes - total 16 bytes for this group
+    faces: array<vec4<f32>, 7>,     // Per-face inlet or wall velocity (xyz) and outlet density, convection velocity or inlet waveform factor (w), then the body velocity
 }
 
+// Link from a fluid node into a bounce-back or moving-wall solid node (see `WallLink` in `boundary.rs`)
+struct WallLink {
+    node: u32,                   // Fluid node
+    direction: u32,              // Direction from the fluid node into the wall
+    q: f32,                      // Distance to the wall as a fraction of the link, in (0, 1] (1/2: half-way)
+    solid: u32,                  // Solid node at the other end of the link
+}
+
+// Q, CS2, VELOCITIES, WEIGHTS and OPPOSITE are generated by `Lattice::wgsl_constants`
+
+const INLET_EQUILIBRIUM: u32 = 0u;
+const INLET_ZOU_HE: u32 = 1u;
+const OUTLET_ZERO_GRADIENT: u32 = 0u;
+const OUTLET_PRESSURE: u32 = 1u;
+const OUTLET_CONVECTIVE: u32 = 2u;
+const WALL_BOUNCE_BACK: u32 = 0u;
+const WALL_FREE_SLIP: u32 = 1u;
+const WALL_MOVING: u32 = 2u;
+
 @group(0) @binding(0) var<storage, read_write> lattice: array<LatticePoint>;
 @group(0) @binding(1) var<storage, read_write> temp: array<LatticePoint>;
 @group(0) @binding(2) var<uniform> config: Config;
+@group(0) @binding(3) var<storage, read> links: array<WallLink>;
+@group(0) @binding(4) var<storage, read> inlet_profile: array<vec4<f32>>;
+
+// Prescribed velocity of an inlet node - its profile velocity scaled by the
+// waveform factor, or the uniform face velocity
+fn inlet_velocity(idx: u32) -> vec3<f32> {
+    let face = config.faces[lattice[idx].face];
+    if (config.inlet_profile != 0u) {
+        return inlet_profile[idx].xyz * face.w;
+    }
+    return face.xyz;
+}
+
+// Inward unit normal of a domain face (0: -x, 1: +x, 2: -y, 3: +y, 4: -z, 5: +z)
+fn face_normal(face: u32) -> vec3<i32> {
+    var normal = vec3<i32>(0, 0, 0);
+    normal[face / 2u] = select(-1, 1, face % 2u == 0u);
+    return normal;
+}
+
+// Sums of the populations parallel to (x) and leaving through (y) the node's face,
+// which are known after streaming
+fn known_populations(idx: u32) -> vec2<f32> {
+    let normal = face_normal(lattice[idx].face);
+    var sums = vec2<f32>(0.0, 0.0);
+    for (var i = 0u; i < Q; i++) {
+        let c = VELOCITIES[i];
+        let cn = dot(vec3<i32>(c[0], c[1], c[2]), normal);
+        if (cn == 0) {
+            sums.x += lattice[idx].f[i];
+        } else if (cn < 0) {
+            sums.y += lattice[idx].f[i];
+        }
+    }
+    return sums;
+}
+
+// Zou-He velocity boundary (non-equilibrium bounce-back, see `boundary.rs`): rebuild the
+// populations entering from outside the domain so that the node carries exactly `velocity`
+fn zou_he_velocity(idx: u32, velocity: vec3<f32>) {
+    let un = dot(velocity, vec3<f32>(face_normal(lattice[idx].face)));
+    let known = known_populations(idx);
+    zou_he_reconstruct(idx, (known.x + 2.0 * known.y) / (1.0 - un), velocity);
+}
+
+// Zou-He pressure boundary: impose `density` with the normal velocity that follows from the
+// known populations and zero tangential velocity
+fn zou_he_pressure(idx: u32, density: f32) {
+    let normal = face_normal(lattice[idx].face);
+    let known = known_populations(idx);
+    let un = 1.0 - (known.x + 2.0 * known.y) / density;
+    zou_he_reconstruct(idx, density, un * vec3<f32>(normal));
+}
 
-// D3Q27 constants
-const Q: u32 = 27u;
-const CS2: f32 = 1.0 / 3.0;
-
-// D3Q27 velocities
-const VELOCITIES = array<array<i32, 3>, 27>(
-    array<i32, 3>(0, 0, 0),     // 0
-    array<i32, 3>(1, 0, 0),     // 1
-    array<i32, 3>(-1, 0, 0),    // 2
-    array<i32, 3>(0, 1, 0),     // 3
-    array<i32, 3>(0, -1, 0),    // 4
-    array<i32, 3>(0, 0, 1),     // 5
-    array<i32, 3>(0, 0, -1),    // 6
-    array<i32, 3>(1, 1, 0),     // 7
-    array<i32, 3>(1, -1, 0),    // 8
-    array<i32, 3>(-1, 1, 0),    // 9
-    array<i32, 3>(-1, -1, 0),   // 10
-    array<i32, 3>(1, 0, 1),     // 11
-    array<i32, 3>(1, 0, -1),    // 12
-    array<i32, 3>(-1, 0, 1),    // 13
-    array<i32, 3>(-1, 0, -1),   // 14
-    array<i32, 3>(0, 1, 1),     // 15
-    array<i32, 3>(0, 1, -1),    // 16
-    array<i32, 3>(0, -1, 1),    // 17
-    array<i32, 3>(0, -1, -1),   // 18
-    array<i32, 3>(1, 1, 1),     // 19
-    array<i32, 3>(1, 1, -1),    // 20
-    array<i32, 3>(1, -1, 1),    // 21
-    array<i32, 3>(1, -1, -1),   // 22
-    array<i32, 3>(-1, 1, 1),    // 23
-    array<i32, 3>(-1, 1, -1),   // 24
-    array<i32, 3>(-1, -1, 1),   // 25
-    array<i32, 3>(-1, -1, -1),  // 26
-);
-
-// D3Q27 weights
-const WEIGHTS = array<f32, 27>(
-    8.0/27.0,                    // 0
-    2.0/27.0, 2.0/27.0, 2.0/27.0, 2.0/27.0, 2.0/27.0, 2.0/27.0,  // 1-6
-    1.0/54.0, 1.0/54.0, 1.0/54.0, 1.0/54.0,  // 7-10
-    1.0/54.0, 1.0/54.0, 1.0/54.0, 1.0/54.0,  // 11-14
-    1.0/54.0, 1.0/54.0, 1.0/54.0, 1.0/54.0,  // 15-18
-    1.0/216.0, 1.0/216.0, 1.0/216.0, 1.0/216.0,  // 19-22
-    1.0/216.0, 1.0/216.0, 1.0/216.0, 1.0/216.0,  // 23-26
-);
-
-// Opposite directions for bounce-back
-const OPPOSITE = array<u32, 27>(
-    0u,  // Center stays the same
-    2u, 1u, 4u, 3u, 6u, 5u,  // Face opposites
-    9u, 8u, 7u, 10u, 13u, 12u, 11u, 14u, 17u, 16u, 15u, 18u,  // Edge opposites
-    26u, 25u, 24u, 23u, 22u, 21u, 20u, 19u,  // Corner opposites
-);
-
-fn equilibrium_distribution(direction: u32, density: f32, velocity: array<f32, 3>) -> f32 {
-    let weight = WEIGHTS[direction];
-    let c = VELOCITIES[direction];
+// Set the unknown populations (c.n > 0) for the given density and velocity
+fn zou_he_reconstruct(idx: u32, density: f32, velocity: vec3<f32>) {
+    let normal = face_normal(lattice[idx].face);
     
-    // Dot product of velocity and lattice velocity
-    let cu = f32(c[0]) * velocity[0] + f32(c[1]) * velocity[1] + f32(c[2]) * velocity[2];
+    // Bounce back the non-equilibrium part of the opposite population
+    for (var i = 0u; i < Q; i++) {
+        let c = VELOCITIES[i];
+        let ci = vec3<f32>(f32(c[0]), f32(c[1]), f32(c[2]));
+        if (dot(vec3<i32>(c[0], c[1], c[2]), normal) > 0) {
+            lattice[idx].f[i] = lattice[idx].f[OPPOSITE[i]] + 2.0 * WEIGHTS[i] * density * dot(ci, velocity) / CS2;
+        }
+    }
     
-    // Velocity magnitude squared
-    let u2 = velocity[0] * velocity[0] + velocity[1] * velocity[1] + velocity[2] * velocity[2];
+    // Correct the tangential momentum with the unknown populations
+    var momentum = vec3<f32>(0.0, 0.0, 0.0);
+    var spread = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i = 0u; i < Q; i++) {
+        let c = VELOCITIES[i];
+        let ci = vec3<f32>(f32(c[0]), f32(c[1]), f32(c[2]));
+        momentum += lattice[idx].f[i] * ci;
+        if (dot(vec3<i32>(c[0], c[1], c[2]), normal) > 0) {
+            spread += ci * ci;
+        }
+    }
+    let tangential = vec3<f32>(1.0, 1.0, 1.0) - abs(vec3<f32>(normal));
+    let correction = select(vec3<f32>(0.0), (density * velocity - momentum) / max(spread, vec3<f32>(1.0)), spread > vec3<f32>(0.0)) * tangential;
+    for (var i = 0u; i < Q; i++) {
+        let c = VELOCITIES[i];
+        if (dot(vec3<i32>(c[0], c[1], c[2]), normal) > 0) {
+            lattice[idx].f[i] += dot(vec3<f32>(f32(c[0]), f32(c[1]), f32(c[2])), correction);
+        }
+    }
     
-    // Equilibrium distribution function
-    return weight * density * (1.0 + cu / CS2 + 
-                              cu * cu / (2.0 * CS2 * CS2) - 
-                              u2 / (2.0 * CS2));
+    lattice[idx].density = density;
+    lattice[idx].velocity = array<f32, 3>(velocity.x, velocity.y, velocity.z);
+}
+
+// Index of the node one step inwards from boundary node `idx` on its face, or -1 outside the domain
+fn interior_neighbor(idx: u32) -> i32 {
+    let size = config.domain_size.xyz;
+    let position = vec3<i32>(vec3<u32>(idx % size.x, (idx / size.x) % size.y, idx / (size.x * size.y)));
+    let interior = position + face_normal(lattice[idx].face);
+    if (any(interior < vec3<i32>(0, 0, 0)) || any(interior >= vec3<i32>(size))) {
+        return -1;
+    }
+    return interior.x + interior.y * i32(size.x) + interior.z * i32(size.x * size.y);
+}
+
+// Convective outflow df/dt + U df/dn = 0, discretized implicitly with upwind differences as
+// f(t+1) = (f(t) + U f_interior(t+1)) / (1 + U). The previous-step populations f(t) are
+// still in `temp`, where the collision pass copied the outlet node unchanged
+fn convective_outflow(idx: u32) {
+    let normal = face_normal(lattice[idx].face);
+    let interior = interior_neighbor(idx);
+    
+    // Keep the previous populations without a non-solid interior neighbour
+    var u = 0.0;
+    var n = idx;
+    if (interior >= 0 && lattice[u32(interior)].node_type != 1u) {
+        n = u32(interior);
+        u = config.faces[lattice[idx].face].w;
+        if (u <= 0.0) {
+            let v = lattice[n].velocity;
+            u = clamp(-dot(vec3<f32>(v[0], v[1], v[2]), vec3<f32>(normal)), 0.0, 1.0);
+        }
+    }
+    
+    var density = 0.0;
+    var momentum = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i = 0u; i < Q; i++) {
+        let c = VELOCITIES[i];
+        let f = (temp[idx].f[i] + u * lattice[n].f[i]) / (1.0 + u);
+        lattice[idx].f[i] = f;
+        density += f;
+        momentum += f * vec3<f32>(f32(c[0]), f32(c[1]), f32(c[2]));
+    }
+    lattice[idx].density = density;
+    let velocity = momentum / max(density, 1e-10);
+    lattice[idx].velocity = array<f32, 3>(velocity.x, velocity.y, velocity.z);
+}
+
+// Density and velocity of the node's current populations
+fn update_moments(idx: u32) {
+    var density = 0.0;
+    var momentum = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i = 0u; i < Q; i++) {
+        let c = VELOCITIES[i];
+        let f = lattice[idx].f[i];
+        density += f;
+        momentum += f * vec3<f32>(f32(c[0]), f32(c[1]), f32(c[2]));
+    }
+    lattice[idx].density = density;
+    let velocity = momentum / max(density, 1e-10);
+    lattice[idx].velocity = array<f32, 3>(velocity.x, velocity.y, velocity.z);
+}
+
+// Free-slip wall (see `boundary.rs`): reflect the populations streamed into the wall node
+// specularly, reversing only their wall-normal component
+fn free_slip(idx: u32) {
+    let normal = face_normal(lattice[idx].face);
+    let streamed = lattice[idx].f;
+    var density = 0.0;
+    var momentum = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i = 0u; i < Q; i++) {
+        let c = VELOCITIES[i];
+        let ci = vec3<i32>(c[0], c[1], c[2]);
+        let reflected = ci - 2 * dot(ci, normal) * normal;
+        var f = streamed[i];
+        for (var j = 0u; j < Q; j++) {
+            let cj = VELOCITIES[j];
+            if (all(vec3<i32>(cj[0], cj[1], cj[2]) == reflected)) {
+                f = streamed[j];
+            }
+        }
+        lattice[idx].f[i] = f;
+        density += f;
+        momentum += f * vec3<f32>(ci);
+    }
+    lattice[idx].density = density;
+    let velocity = momentum / max(density, 1e-10) * (vec3<f32>(1.0, 1.0, 1.0) - abs(vec3<f32>(normal)));
+    lattice[idx].velocity = array<f32, 3>(velocity.x, velocity.y, velocity.z);
+}
+
+// Index of the node one step along `c` from `idx`, wrapped on periodic axes, or -1 outside
+// the domain
+fn neighbor(idx: u32, c: vec3<i32>) -> i32 {
+    let size = vec3<i32>(config.domain_size.xyz);
+    let position = vec3<i32>(vec3<u32>(idx % config.domain_size.x, (idx / config.domain_size.x) % config.domain_size.y, idx / (config.domain_size.x * config.domain_size.y)));
+    var next = position + c;
+    for (var a = 0u; a < 3u; a++) {
+        if (next[a] < 0 || next[a] >= size[a]) {
+            if ((config.domain_size.w & (1u << a)) == 0u) {
+                return -1;
+            }
+            next[a] = (next[a] + size[a]) % size[a];
+        }
+    }
+    return next.x + next.y * size.x + next.z * size.x * size.y;
+}
+
+// Bounce-back of the wall links (Bouzidi, see `interpolated_bounce_back` in `boundary.rs`): rebuild
+// the population entering each link's fluid node from the post-collision populations in `temp`.
+// Half-way links (q = 1/2), and q < 1/2 links without a fluid upwind node, reflect the population
+// that left the fluid node towards the wall
+// Large link counts are dispatched as a 2D grid of workgroups (see `dispatch_grid` in `gpu.rs`)
+@compute @workgroup_size(64, 1, 1)
+fn wall_links(@builtin(global_invocation_id) global_id: vec3<u32>,
+              @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = global_id.x + global_id.y * groups.x * 64u;
+    if (index >= arrayLength(&links)) {
+        return;
+    }
+    let link = links[index];
+    if (link.node == 0xffffffffu) {
+        return;
+    }
+    
+    let i = link.direction;
+    let back = OPPOSITE[i];
+    let c = VELOCITIES[back];
+    
+    // Momentum of a moving wall
+    var momentum = 0.0;
+    if (lattice[link.solid].boundary == WALL_MOVING) {
+        let cu = dot(vec3<f32>(f32(c[0]), f32(c[1]), f32(c[2])), config.faces[lattice[link.solid].face].xyz);
+        momentum = 2.0 * WEIGHTS[back] * config.density * cu / CS2;
+    }
+    
+    let q = link.q;
+    var f = temp[link.node].f[i] + momentum;
+    if (q >= 0.5) {
+        f = (temp[link.node].f[i] + (2.0 * q - 1.0) * temp[link.node].f[back] + momentum) / (2.0 * q);
+    } else {
+        let upwind = neighbor(link.node, vec3<i32>(c[0], c[1], c[2]));
+        if (upwind >= 0 && temp[u32(upwind)].node_type != 1u) {
+            f = 2.0 * q * temp[link.node].f[i] + (1.0 - 2.0 * q) * temp[u32(upwind)].f[i] + momentum;
+        }
+    }
+    lattice[link.node].f[back] = f;
 }
 
 @compute @workgroup_size(8, 8, 1)
@@ -105,36 +297,47 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     
     // Handle boundary conditions based on node type
     switch (node_type) {
-        case 1u: { // Solid node - bounce-back
-            // Full bounce-back boundary condition
-            for (var i = 0u; i < Q; i++) {
-                let opposite = OPPOSITE[i];
-                lattice[idx].f[i] = lattice[idx].f[opposite];
+        case 1u: { // Solid node - free-slip, or bounce-back and moving walls reflected by `wall_links`
+            if (lattice[idx].boundary == WALL_FREE_SLIP) {
+                free_slip(idx);
+                return;
             }
-            lattice[idx].velocity = array<f32, 3>(0.0, 0.0, 0.0);
-            lattice[idx].density = 1.0;
+            
+            var velocity = vec3<f32>(0.0, 0.0, 0.0);
+            if (lattice[idx].boundary == WALL_MOVING) {
+                velocity = config.faces[lattice[idx].face].xyz;
+            }
+            lattice[idx].velocity = array<f32, 3>(velocity.x, velocity.y, velocity.z);
+            lattice[idx].density = config.density;
         }
-        case 3u: { // Outlet - zero gradient (Neumann BC)
-            // Copy from neighboring fluid node in the flow direction
-            if (x > 0u) {
-                let neighbor_idx = (x - 1u) + y * config.domain_size.x + z * config.domain_size.x * config.domain_size.y;
-                // Only copy from fluid nodes
-                if (lattice[neighbor_idx].node_type == 0u) {
-                    for (var i = 0u; i < Q; i++) {
-                        lattice[idx].f[i] = lattice[neighbor_idx].f[i];
-                    }
-                    lattice[idx].density = lattice[neighbor_idx].density;
-                    lattice[idx].velocity = lattice[neighbor_idx].velocity;
-                } else {
-                    // If no valid neighbor, use outflow conditions
-                    for (var i = 0u; i < Q; i++) {
-                        lattice[idx].f[i] = equilibrium_distribution(i, 1.0, array<f32, 3>(0.1, 0.0, 0.0));
-                    }
-                    lattice[idx].density = 1.0;
-                    lattice[idx].velocity = array<f32, 3>(0.1, 0.0, 0.0);
+        case 2u: { // Inlet - Zou-He velocity (equilibrium inlets are set in the collision pass)
+            if (lattice[idx].boundary == INLET_ZOU_HE) {
+                zou_he_velocity(idx, inlet_velocity(idx));
+            }
+        }
+        case 3u: { // Outlet - pressure (Zou-He), convective or zero gradient (Neumann BC)
+            if (lattice[idx].boundary == OUTLET_PRESSURE) {
+                zou_he_pressure(idx, config.faces[lattice[idx].face].w);
+                return;
+            }
+            if (lattice[idx].boundary == OUTLET_CONVECTIVE) {
+                convective_outflow(idx);
+                return;
+            }
+            
+            // Copy from a fluid interior neighbour, otherwise keep the streamed populations
+            let interior = interior_neighbor(idx);
+            if (interior >= 0 && lattice[u32(interior)].node_type == 0u) {
+                let neighbor_idx = u32(interior);
+                for (var i = 0u; i < Q; i++) {
+                    lattice[idx].f[i] = lattice[neighbor_idx].f[i];
                 }
+                lattice[idx].density = lattice[neighbor_idx].density;
+                lattice[idx].velocity = lattice[neighbor_idx].velocity;
+            } else {
+                update_moments(idx);
             }
         }
-        default: {} // Fluid and inlet nodes - no modification needed (handled in collision)
+        default: {} // Fluid nodes - no modification needed
     }
 }
@@ -1,70 +1,63 @@
-// D3Q27 Lattice-Boltzmann collision shader
+// Lattice-Boltzmann collision shader
 
 struct LatticePoint {
-    f: array<f32, 27>,           // Distribution functions
+    f: array<f32, Q>,            // Distribution functions
     density: f32,                // Macroscopic density
     velocity: array<f32, 3>,     // Macroscopic velocity
     node_type: u32,              // Node type (0: fluid, 1: solid, 2: inlet, 3: outlet)
-    padding: array<u32, 3>,      // Padding for alignment
+    eddy_viscosity: f32,         // Subgrid eddy viscosity (LES only)
+    boundary: u32,               // Boundary scheme of inlet/outlet/wall face nodes
+    face: u32,                   // Domain face of inlet/outlet/wall face nodes (0: -x, 1: +x, 2: -y, 3: +y, 4: -z, 5: +z, 6: STL body)
 }
 
 struct Config {
-    domain_size: vec4<u32>,         // nx, ny, nz, padding - 16 bytes aligned
+    domain_size: vec4<u32>,         // nx, ny, nz, periodic axes bit mask - 16 bytes aligned
     tau: f32,                       // 4 bytes
     density: f32,                   // 4 bytes  
-    padding1: vec2<f32>,            // 8 bytes - total 16 bytes for this group
+    collision_model: u32,           // 4 bytes (0: BGK, 1: MRT, 2: TRT, 3: cumulant)
+    inlet_profile: u32,             // 4 bytes - 1 if inlet velocities come from the profile buffer - total 16 bytes for this group
     inlet_velocity: vec4<f32>,      // 16 bytes aligned
+    relaxation: vec4<f32>,          // MRT shear (TRT symmetric), bulk, ghost (cumulant third order), TRT antisymmetric rates
+    relaxation_high: vec4<f32>,     // Cumulant fourth, fifth and sixth order rates, padding
+    les_model: u32,                 // 4 bytes (0: none, 1: Smagorinsky, 2: WALE, 3: Vreman)
+    les_constant: f32,              // 4 bytes - model constant (Cs, Cw or c)
+    padding2: vec2<f32>,            // 8 bytes - total 16 bytes for this group
+    faces: array<vec4<f32>, 7>,     // Per-face inlet or wall velocity (xyz) and outlet density, convection velocity or inlet waveform factor (w), then the body velocity
 }
 
+// Q, CS2, VELOCITIES, WEIGHTS and OPPOSITE are generated by `Lattice::wgsl_constants`,
+// MRT_GROUP, mrt_moments and mrt_populations by `MomentBasis::wgsl_functions`
+
+const COLLISION_BGK: u32 = 0u;
+const COLLISION_MRT: u32 = 1u;
+const COLLISION_TRT: u32 = 2u;
+const COLLISION_CUMULANT: u32 = 3u;
+
+const INLET_EQUILIBRIUM: u32 = 0u;
+const INLET_ZOU_HE: u32 = 1u;
+const OUTLET_ZERO_GRADIENT: u32 = 0u;
+const OUTLET_PRESSURE: u32 = 1u;
+const OUTLET_CONVECTIVE: u32 = 2u;
+
+const LES_NONE: u32 = 0u;
+const LES_SMAGORINSKY: u32 = 1u;
+const LES_WALE: u32 = 2u;
+const LES_VREMAN: u32 = 3u;
+
 @group(0) @binding(0) var<storage, read_write> lattice: array<LatticePoint>;
 @group(0) @binding(1) var<storage, read_write> temp: array<LatticePoint>;
 @group(0) @binding(2) var<uniform> config: Config;
+@group(0) @binding(3) var<storage, read> inlet_profile: array<vec4<f32>>;
 
-// D3Q27 constants
-const Q: u32 = 27u;
-const CS2: f32 = 1.0 / 3.0;
-
-// D3Q27 velocities
-const VELOCITIES = array<array<i32, 3>, 27>(
-    array<i32, 3>(0, 0, 0),     // 0
-    array<i32, 3>(1, 0, 0),     // 1
-    array<i32, 3>(-1, 0, 0),    // 2
-    array<i32, 3>(0, 1, 0),     // 3
-    array<i32, 3>(0, -1, 0),    // 4
-    array<i32, 3>(0, 0, 1),     // 5
-    array<i32, 3>(0, 0, -1),    // 6
-    array<i32, 3>(1, 1, 0),     // 7
-    array<i32, 3>(1, -1, 0),    // 8
-    array<i32, 3>(-1, 1, 0),    // 9
-    array<i32, 3>(-1, -1, 0),   // 10
-    array<i32, 3>(1, 0, 1),     // 11
-    array<i32, 3>(1, 0, -1),    // 12
-    array<i32, 3>(-1, 0, 1),    // 13
-    array<i32, 3>(-1, 0, -1),   // 14
-    array<i32, 3>(0, 1, 1),     // 15
-    array<i32, 3>(0, 1, -1),    // 16
-    array<i32, 3>(0, -1, 1),    // 17
-    array<i32, 3>(0, -1, -1),   // 18
-    array<i32, 3>(1, 1, 1),     // 19
-    array<i32, 3>(1, 1, -1),    // 20
-    array<i32, 3>(1, -1, 1),    // 21
-    array<i32, 3>(1, -1, -1),   // 22
-    array<i32, 3>(-1, 1, 1),    // 23
-    array<i32, 3>(-1, 1, -1),   // 24
-    array<i32, 3>(-1, -1, 1),   // 25
-    array<i32, 3>(-1, -1, -1),  // 26
-);
-
-// D3Q27 weights
-const WEIGHTS = array<f32, 27>(
-    8.0/27.0,                    // 0
-    2.0/27.0, 2.0/27.0, 2.0/27.0, 2.0/27.0, 2.0/27.0, 2.0/27.0,  // 1-6
-    1.0/54.0, 1.0/54.0, 1.0/54.0, 1.0/54.0,  // 7-10
-    1.0/54.0, 1.0/54.0, 1.0/54.0, 1.0/54.0,  // 11-14
-    1.0/54.0, 1.0/54.0, 1.0/54.0, 1.0/54.0,  // 15-18
-    1.0/216.0, 1.0/216.0, 1.0/216.0, 1.0/216.0,  // 19-22
-    1.0/216.0, 1.0/216.0, 1.0/216.0, 1.0/216.0,  // 23-26
-);
+// Prescribed velocity of an inlet node - its profile velocity scaled by the
+// waveform factor, or the uniform face velocity
+fn inlet_velocity(idx: u32) -> vec3<f32> {
+    let face = config.faces[lattice[idx].face];
+    if (config.inlet_profile != 0u) {
+        return inlet_profile[idx].xyz * face.w;
+    }
+    return face.xyz;
+}
 
 fn equilibrium_distribution(direction: u32, density: f32, velocity: array<f32, 3>) -> f32 {
     let weight = WEIGHTS[direction];
@@ -88,7 +81,7 @@ fn equilibrium_distribution(direction: u32, density: f32, velocity: array<f32, 3
     
     // Ensure result is valid
     if (eq < 0.0 || eq != eq) { // Check for NaN
-        return weight * density / 27.0; // Return uniform distribution
+        return weight * density / f32(Q); // Return uniform distribution
     }
     
     return eq;
@@ -119,9 +112,9 @@ fn calculate_macroscopic(idx: u32) {
         velocity[1] /= density;
         velocity[2] /= density;
         
-        // Clamp velocity to reasonable range
+        // Clamp velocity to reasonable range (not needed by the cumulant operator)
         let vel_mag = sqrt(velocity[0] * velocity[0] + velocity[1] * velocity[1] + velocity[2] * velocity[2]);
-        if (vel_mag > 0.3) { // Max Mach number around 0.3
+        if (vel_mag > 0.3 && config.collision_model != COLLISION_CUMULANT) { // Max Mach number around 0.3
             let scale = 0.3 / vel_mag;
             velocity[0] *= scale;
             velocity[1] *= scale;
@@ -133,6 +126,380 @@ fn calculate_macroscopic(idx: u32) {
     lattice[idx].velocity = velocity;
 }
 
+// Norm sqrt(Pi_ab Pi_ab) of the non-equilibrium momentum flux Pi_ab = sum_i c_ia c_ib (f_i - f_i^eq)
+fn non_equilibrium_flux_norm(idx: u32) -> f32 {
+    var pi = array<f32, 6>(0.0, 0.0, 0.0, 0.0, 0.0, 0.0); // xx, yy, zz, xy, xz, yz
+    for (var i = 0u; i < Q; i++) {
+        let c = VELOCITIES[i];
+        let f_neq = lattice[idx].f[i] - equilibrium_distribution(i, lattice[idx].density, lattice[idx].velocity);
+        pi[0] += f32(c[0] * c[0]) * f_neq;
+        pi[1] += f32(c[1] * c[1]) * f_neq;
+        pi[2] += f32(c[2] * c[2]) * f_neq;
+        pi[3] += f32(c[0] * c[1]) * f_neq;
+        pi[4] += f32(c[0] * c[2]) * f_neq;
+        pi[5] += f32(c[1] * c[2]) * f_neq;
+    }
+    return sqrt(pi[0] * pi[0] + pi[1] * pi[1] + pi[2] * pi[2] +
+                2.0 * (pi[3] * pi[3] + pi[4] * pi[4] + pi[5] * pi[5]));
+}
+
+// Velocity of a neighbouring node from the moments of its populations (its stored
+// velocity may already have been overwritten by another invocation of this pass)
+fn node_velocity(n: u32) -> vec3<f32> {
+    var density = 0.0;
+    var momentum = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i = 0u; i < Q; i++) {
+        let c = VELOCITIES[i];
+        let f = lattice[n].f[i];
+        density += f;
+        momentum += f * vec3<f32>(f32(c[0]), f32(c[1]), f32(c[2]));
+    }
+    if (density <= 1e-10) {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+    return momentum / density;
+}
+
+// Velocity gradient g[b][a] = du_a/dx_b by central differences, one-sided next to
+// solid nodes and non-periodic domain edges so that no difference is taken into a solid
+fn velocity_gradient(idx: u32) -> mat3x3<f32> {
+    let size = config.domain_size.xyz;
+    let position = vec3<u32>(idx % size.x, (idx / size.x) % size.y, idx / (size.x * size.y));
+    let stride = vec3<u32>(1u, size.x, size.x * size.y);
+    let center = vec3<f32>(lattice[idx].velocity[0], lattice[idx].velocity[1], lattice[idx].velocity[2]);
+
+    var g = mat3x3<f32>(vec3<f32>(0.0), vec3<f32>(0.0), vec3<f32>(0.0));
+    for (var b = 0u; b < 3u; b++) {
+        // Neighbours along axis b, wrapped on periodic axes
+        let periodic = (config.domain_size.w & (1u << b)) != 0u;
+        var plus = idx + stride[b];
+        var minus = idx - stride[b];
+        if (position[b] + 1u == size[b]) {
+            plus = select(idx, idx + stride[b] - size[b] * stride[b], periodic);
+        }
+        if (position[b] == 0u) {
+            minus = select(idx, idx + (size[b] - 1u) * stride[b], periodic);
+        }
+        
+        let has_plus = plus != idx && lattice[plus].node_type != 1u;
+        let has_minus = minus != idx && lattice[minus].node_type != 1u;
+        if (has_plus && has_minus) {
+            g[b] = 0.5 * (node_velocity(plus) - node_velocity(minus));
+        } else if (has_plus) {
+            g[b] = node_velocity(plus) - center;
+        } else if (has_minus) {
+            g[b] = center - node_velocity(minus);
+        }
+    }
+    return g;
+}
+
+// Sum of the squared entries
+fn frobenius2(m: mat3x3<f32>) -> f32 {
+    return dot(m[0], m[0]) + dot(m[1], m[1]) + dot(m[2], m[2]);
+}
+
+// WALE eddy viscosity (Cw dx)^2 (Sd:Sd)^(3/2) / ((S:S)^(5/2) + (Sd:Sd)^(5/4))
+fn wale_viscosity(g: mat3x3<f32>) -> f32 {
+    let cw = config.les_constant;
+    let s = 0.5 * (g + transpose(g));
+    let g2 = g * g;
+    let trace = (g2[0][0] + g2[1][1] + g2[2][2]) / 3.0;
+    let identity = mat3x3<f32>(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(0.0, 0.0, 1.0));
+    let sd = 0.5 * (g2 + transpose(g2)) - trace * identity;
+
+    let ss = frobenius2(s);
+    let sdsd = frobenius2(sd);
+    let denominator = pow(ss, 2.5) + pow(sdsd, 1.25);
+    if (denominator <= 1e-30) {
+        return 0.0;
+    }
+    return cw * cw * pow(sdsd, 1.5) / denominator;
+}
+
+// Vreman eddy viscosity c sqrt(B_beta / (alpha_ij alpha_ij)) with alpha_ij = du_j/dx_i
+fn vreman_viscosity(g: mat3x3<f32>) -> f32 {
+    let alpha_norm = frobenius2(g);
+    if (alpha_norm <= 1e-30) {
+        return 0.0;
+    }
+
+    // beta_ij = sum_m g_im g_jm, symmetric so the storage order does not matter
+    let beta = g * transpose(g);
+    let b = beta[0][0] * beta[1][1] - beta[0][1] * beta[0][1]
+          + beta[0][0] * beta[2][2] - beta[0][2] * beta[0][2]
+          + beta[1][1] * beta[2][2] - beta[1][2] * beta[1][2];
+    if (b <= 0.0) {
+        return 0.0;
+    }
+    return config.les_constant * sqrt(b / alpha_norm);
+}
+
+// Shear relaxation time including the subgrid eddy viscosity (see `turbulence.rs`)
+fn effective_tau(idx: u32, tau0: f32) -> f32 {
+    switch (config.les_model) {
+        case LES_SMAGORINSKY: { // tau = (tau0 + sqrt(tau0^2 + 18 sqrt(2) Cs^2 |Pi| / rho)) / 2
+            let cs = config.les_constant;
+            let pi_norm = non_equilibrium_flux_norm(idx);
+            return 0.5 * (tau0 + sqrt(tau0 * tau0 + 18.0 * sqrt(2.0) * cs * cs * pi_norm / lattice[idx].density));
+        }
+        case LES_WALE: {
+            return tau0 + wale_viscosity(velocity_gradient(idx)) / CS2;
+        }
+        case LES_VREMAN: {
+            return tau0 + vreman_viscosity(velocity_gradient(idx)) / CS2;
+        }
+        default: {
+            return tau0;
+        }
+    }
+}
+
+// Relaxation rate of an MRT moment group (conserved moments use the shear rate)
+fn mrt_rate(group: u32, omega_shear: f32) -> f32 {
+    switch (group) {
+        case 1u: { return config.relaxation.y; } // Bulk
+        case 3u: { return config.relaxation.z; } // Ghost
+        default: { return omega_shear; } // Conserved and shear
+    }
+}
+
+// MRT collision: relax the non-equilibrium part of each moment with the rate of its group
+fn collide_mrt(idx: u32, omega_shear: f32) {
+    var f_neq: array<f32, Q>;
+    for (var i = 0u; i < Q; i++) {
+        f_neq[i] = lattice[idx].f[i] - equilibrium_distribution(i, lattice[idx].density, lattice[idx].velocity);
+    }
+    
+    var m_neq = mrt_moments(f_neq);
+    for (var k = 0u; k < Q; k++) {
+        m_neq[k] *= mrt_rate(MRT_GROUP[k], omega_shear);
+    }
+    
+    let df = mrt_populations(m_neq);
+    for (var i = 0u; i < Q; i++) {
+        temp[idx].f[i] = lattice[idx].f[i] - df[i];
+    }
+}
+
+// TRT collision: relax the symmetric and antisymmetric parts of each opposite pair separately
+fn collide_trt(idx: u32, omega_plus: f32) {
+    // Keep the magic parameter of the configured rates when LES changes omega_plus
+    let magic = (1.0 / config.relaxation.x - 0.5) * (1.0 / config.relaxation.w - 0.5);
+    let omega_minus = 1.0 / (magic / (1.0 / omega_plus - 0.5) + 0.5);
+    
+    var f_eq: array<f32, Q>;
+    for (var i = 0u; i < Q; i++) {
+        f_eq[i] = equilibrium_distribution(i, lattice[idx].density, lattice[idx].velocity);
+    }
+    
+    for (var i = 0u; i < Q; i++) {
+        let j = OPPOSITE[i];
+        let f_i = lattice[idx].f[i];
+        let f_j = lattice[idx].f[j];
+        let neq_plus = 0.5 * ((f_i + f_j) - (f_eq[i] + f_eq[j]));
+        let neq_minus = 0.5 * ((f_i - f_j) - (f_eq[i] - f_eq[j]));
+        temp[idx].f[i] = f_i - omega_plus * neq_plus - omega_minus * neq_minus;
+    }
+}
+
+// Central moments of the cumulant collision, k[a][b][c] of order (a, b, c)
+var<private> k: array<array<array<f32, 3>, 3>, 3>;
+
+// Chimera transform of populations (c = -1, 0, 1) along one axis to central moments about u
+fn chimera_forward(f: vec3<f32>, u: f32) -> vec3<f32> {
+    let sum = f.x + f.y + f.z;
+    let diff = f.z - f.x;
+    return vec3<f32>(sum, diff - u * sum, (f.z + f.x) - 2.0 * u * diff + u * u * sum);
+}
+
+// Inverse of chimera_forward
+fn chimera_backward(m: vec3<f32>, u: f32) -> vec3<f32> {
+    let m1 = m.y + u * m.x;
+    let m2 = m.z + 2.0 * u * m.y + u * u * m.x;
+    return vec3<f32>(0.5 * (m2 - m1), m.x - m2, 0.5 * (m2 + m1));
+}
+
+// Apply the chimera transform to k along one axis
+fn chimera_axis(axis: u32, u: f32, to_moments: bool) {
+    for (var i = 0u; i < 3u; i++) {
+        for (var j = 0u; j < 3u; j++) {
+            var v: vec3<f32>;
+            switch (axis) {
+                case 0u: { v = vec3<f32>(k[0][i][j], k[1][i][j], k[2][i][j]); }
+                case 1u: { v = vec3<f32>(k[i][0][j], k[i][1][j], k[i][2][j]); }
+                default: { v = vec3<f32>(k[i][j][0], k[i][j][1], k[i][j][2]); }
+            }
+            
+            if (to_moments) {
+                v = chimera_forward(v, u);
+            } else {
+                v = chimera_backward(v, u);
+            }
+            
+            switch (axis) {
+                case 0u: { k[0][i][j] = v.x; k[1][i][j] = v.y; k[2][i][j] = v.z; }
+                case 1u: { k[i][0][j] = v.x; k[i][1][j] = v.y; k[i][2][j] = v.z; }
+                default: { k[i][j][0] = v.x; k[i][j][1] = v.y; k[i][j][2] = v.z; }
+            }
+        }
+    }
+}
+
+// Lower-order part of the sixth-order central moment, k222 - C222
+fn sixth_order_correction(inv_rho: f32) -> f32 {
+    return (4.0 * k[1][1][1] * k[1][1][1]
+        + k[2][0][0] * k[0][2][2] + k[0][2][0] * k[2][0][2] + k[0][0][2] * k[2][2][0]
+        + 4.0 * (k[0][1][1] * k[2][1][1] + k[1][0][1] * k[1][2][1] + k[1][1][0] * k[1][1][2])
+        + 2.0 * (k[1][2][0] * k[1][0][2] + k[2][1][0] * k[0][1][2] + k[2][0][1] * k[0][2][1])) * inv_rho
+        - (16.0 * k[1][1][0] * k[1][0][1] * k[0][1][1]
+            + 4.0 * (k[1][0][1] * k[1][0][1] * k[0][2][0]
+                + k[0][1][1] * k[0][1][1] * k[2][0][0]
+                + k[1][1][0] * k[1][1][0] * k[0][0][2])
+            + 2.0 * k[2][0][0] * k[0][2][0] * k[0][0][2]) * inv_rho * inv_rho;
+}
+
+// Cumulant collision (D3Q27 only, see `collision::cumulant_relax`)
+fn collide_cumulant(idx: u32, omega_shear: f32) {
+    let rho = lattice[idx].density;
+    let velocity = lattice[idx].velocity;
+    let inv_rho = 1.0 / rho;
+    
+    let omega_bulk = config.relaxation.y;
+    let omega_3 = config.relaxation.z;
+    let omega_4 = config.relaxation_high.x;
+    let omega_5 = config.relaxation_high.y;
+    let omega_6 = config.relaxation_high.z;
+    
+    for (var d = 0u; d < Q; d++) {
+        let c = VELOCITIES[d];
+        k[u32(c[0] + 1)][u32(c[1] + 1)][u32(c[2] + 1)] = lattice[idx].f[d];
+    }
+    chimera_axis(2u, velocity[2], true);
+    chimera_axis(1u, velocity[1], true);
+    chimera_axis(0u, velocity[0], true);
+    
+    // Fourth- to sixth-order cumulants (orders two and three equal the central moments)
+    let c211 = k[2][1][1] - (k[2][0][0] * k[0][1][1] + 2.0 * k[1][1][0] * k[1][0][1]) * inv_rho;
+    let c121 = k[1][2][1] - (k[0][2][0] * k[1][0][1] + 2.0 * k[1][1][0] * k[0][1][1]) * inv_rho;
+    let c112 = k[1][1][2] - (k[0][0][2] * k[1][1][0] + 2.0 * k[1][0][1] * k[0][1][1]) * inv_rho;
+    let c220 = k[2][2][0] - (k[2][0][0] * k[0][2][0] + 2.0 * k[1][1][0] * k[1][1][0]) * inv_rho;
+    let c202 = k[2][0][2] - (k[2][0][0] * k[0][0][2] + 2.0 * k[1][0][1] * k[1][0][1]) * inv_rho;
+    let c022 = k[0][2][2] - (k[0][2][0] * k[0][0][2] + 2.0 * k[0][1][1] * k[0][1][1]) * inv_rho;
+    
+    let c122 = k[1][2][2] - (k[0][0][2] * k[1][2][0] + k[0][2][0] * k[1][0][2]
+        + 4.0 * k[0][1][1] * k[1][1][1]
+        + 2.0 * (k[1][0][1] * k[0][2][1] + k[1][1][0] * k[0][1][2])) * inv_rho;
+    let c212 = k[2][1][2] - (k[0][0][2] * k[2][1][0] + k[2][0][0] * k[0][1][2]
+        + 4.0 * k[1][0][1] * k[1][1][1]
+        + 2.0 * (k[0][1][1] * k[2][0][1] + k[1][1][0] * k[1][0][2])) * inv_rho;
+    let c221 = k[2][2][1] - (k[2][0][0] * k[0][2][1] + k[0][2][0] * k[2][0][1]
+        + 4.0 * k[1][1][0] * k[1][1][1]
+        + 2.0 * (k[1][0][1] * k[1][2][0] + k[0][1][1] * k[2][1][0])) * inv_rho;
+    
+    let c222 = k[2][2][2] - sixth_order_correction(inv_rho);
+    
+    // Second order: trace relaxes with the bulk rate, the deviatoric part with the shear rate
+    var trace = k[2][0][0] + k[0][2][0] + k[0][0][2];
+    var xx_yy = k[2][0][0] - k[0][2][0];
+    var xx_zz = k[2][0][0] - k[0][0][2];
+    trace += omega_bulk * (rho - trace);
+    xx_yy *= 1.0 - omega_shear;
+    xx_zz *= 1.0 - omega_shear;
+    k[2][0][0] = (xx_yy + xx_zz + trace) / 3.0;
+    k[0][2][0] = (trace - 2.0 * xx_yy + xx_zz) / 3.0;
+    k[0][0][2] = (trace + xx_yy - 2.0 * xx_zz) / 3.0;
+    k[1][1][0] *= 1.0 - omega_shear;
+    k[1][0][1] *= 1.0 - omega_shear;
+    k[0][1][1] *= 1.0 - omega_shear;
+    
+    // Third order
+    k[2][1][0] *= 1.0 - omega_3;
+    k[1][2][0] *= 1.0 - omega_3;
+    k[2][0][1] *= 1.0 - omega_3;
+    k[1][0][2] *= 1.0 - omega_3;
+    k[0][2][1] *= 1.0 - omega_3;
+    k[0][1][2] *= 1.0 - omega_3;
+    k[1][1][1] *= 1.0 - omega_3;
+    
+    // Fourth order, back to central moments with the post-collision lower orders
+    let relax4 = 1.0 - omega_4;
+    k[2][1][1] = relax4 * c211 + (k[2][0][0] * k[0][1][1] + 2.0 * k[1][1][0] * k[1][0][1]) * inv_rho;
+    k[1][2][1] = relax4 * c121 + (k[0][2][0] * k[1][0][1] + 2.0 * k[1][1][0] * k[0][1][1]) * inv_rho;
+    k[1][1][2] = relax4 * c112 + (k[0][0][2] * k[1][1][0] + 2.0 * k[1][0][1] * k[0][1][1]) * inv_rho;
+    k[2][2][0] = relax4 * c220 + (k[2][0][0] * k[0][2][0] + 2.0 * k[1][1][0] * k[1][1][0]) * inv_rho;
+    k[2][0][2] = relax4 * c202 + (k[2][0][0] * k[0][0][2] + 2.0 * k[1][0][1] * k[1][0][1]) * inv_rho;
+    k[0][2][2] = relax4 * c022 + (k[0][2][0] * k[0][0][2] + 2.0 * k[0][1][1] * k[0][1][1]) * inv_rho;
+    
+    // Fifth order
+    let relax5 = 1.0 - omega_5;
+    k[1][2][2] = relax5 * c122 + (k[0][0][2] * k[1][2][0] + k[0][2][0] * k[1][0][2]
+        + 4.0 * k[0][1][1] * k[1][1][1]
+        + 2.0 * (k[1][0][1] * k[0][2][1] + k[1][1][0] * k[0][1][2])) * inv_rho;
+    k[2][1][2] = relax5 * c212 + (k[0][0][2] * k[2][1][0] + k[2][0][0] * k[0][1][2]
+        + 4.0 * k[1][0][1] * k[1][1][1]
+        + 2.0 * (k[0][1][1] * k[2][0][1] + k[1][1][0] * k[1][0][2])) * inv_rho;
+    k[2][2][1] = relax5 * c221 + (k[2][0][0] * k[0][2][1] + k[0][2][0] * k[2][0][1]
+        + 4.0 * k[1][1][0] * k[1][1][1]
+        + 2.0 * (k[1][0][1] * k[1][2][0] + k[0][1][1] * k[2][1][0])) * inv_rho;
+    
+    // Sixth order
+    k[2][2][2] = (1.0 - omega_6) * c222 + sixth_order_correction(inv_rho);
+    
+    // Conserved moments
+    k[0][0][0] = rho;
+    k[1][0][0] = 0.0;
+    k[0][1][0] = 0.0;
+    k[0][0][1] = 0.0;
+    
+    chimera_axis(0u, velocity[0], false);
+    chimera_axis(1u, velocity[1], false);
+    chimera_axis(2u, velocity[2], false);
+    for (var d = 0u; d < Q; d++) {
+        let c = VELOCITIES[d];
+        temp[idx].f[d] = k[u32(c[0] + 1)][u32(c[1] + 1)][u32(c[2] + 1)];
+    }
+}
+
+// Copy a node unchanged to the streaming buffer
+fn copy_node(idx: u32) {
+    for (var i = 0u; i < Q; i++) {
+        temp[idx].f[i] = lattice[idx].f[i];
+    }
+    temp[idx].density = lattice[idx].density;
+    temp[idx].velocity = lattice[idx].velocity;
+    temp[idx].eddy_viscosity = lattice[idx].eddy_viscosity;
+}
+
+// Fluid collision - BGK, MRT, TRT or cumulant, with the subgrid model applied to the shear rate
+fn collide_fluid(idx: u32) {
+    // Calculate macroscopic quantities
+    calculate_macroscopic(idx);
+    
+    // Shear relaxation, adjusted by the subgrid model
+    let tau0 = 1.0 / config.relaxation.x;
+    let tau = effective_tau(idx, tau0);
+    let omega = 1.0 / tau;
+    temp[idx].eddy_viscosity = CS2 * (tau - tau0);
+    
+    if (config.collision_model == COLLISION_MRT) {
+        collide_mrt(idx, omega);
+    } else if (config.collision_model == COLLISION_TRT) {
+        collide_trt(idx, omega);
+    } else if (config.collision_model == COLLISION_CUMULANT) {
+        collide_cumulant(idx, omega);
+    } else {
+        // BGK collision
+        for (var i = 0u; i < Q; i++) {
+            let f_eq = equilibrium_distribution(i, lattice[idx].density, lattice[idx].velocity);
+            temp[idx].f[i] = lattice[idx].f[i] + omega * (f_eq - lattice[idx].f[i]);
+        }
+    }
+    
+    temp[idx].density = lattice[idx].density;
+    temp[idx].velocity = lattice[idx].velocity;
+}
+
 @compute @workgroup_size(8, 8, 1)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let x = global_id.x;
@@ -147,60 +514,59 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     
     // Handle different node types
     switch (lattice[idx].node_type) {
-        case 0u: { // Fluid nodes - BGK collision
-            // Calculate macroscopic quantities
-            calculate_macroscopic(idx);
-            
-            // BGK collision
-            let omega = 1.0 / config.tau;
-            
-            for (var i = 0u; i < Q; i++) {
-                let f_eq = equilibrium_distribution(i, lattice[idx].density, lattice[idx].velocity);
-                temp[idx].f[i] = lattice[idx].f[i] + omega * (f_eq - lattice[idx].f[i]);
-            }
-            
-            temp[idx].density = lattice[idx].density;
-            temp[idx].velocity = lattice[idx].velocity;
+        case 0u: { // Fluid nodes - BGK, MRT, TRT or cumulant collision
+            collide_fluid(idx);
         }
-        case 2u: { // Inlet nodes - prescribed velocity with relaxation
-            let inlet_vel = array<f32, 3>(
-                config.inlet_velocity.x,
-                config.inlet_velocity.y,
-                config.inlet_velocity.z
-            );
+        case 2u: { // Inlet nodes
+            if (lattice[idx].boundary == INLET_ZOU_HE) {
+                // Collide like fluid, the velocity is imposed in the boundary pass
+                collide_fluid(idx);
+            } else {
+                // Prescribed velocity with relaxation
+                let face_velocity = inlet_velocity(idx);
+                let inlet_vel = array<f32, 3>(face_velocity.x, face_velocity.y, face_velocity.z);
             
-            // Calculate current macroscopic quantities first
-            calculate_macroscopic(idx);
+                // Calculate current macroscopic quantities first
+                calculate_macroscopic(idx);
             
-            // Apply relaxation for smoother transition
-            let relax_factor = 0.9; // Strong enforcement but not abrupt
-            let relaxed_vel = array<f32, 3>(
-                relax_factor * inlet_vel[0] + (1.0 - relax_factor) * lattice[idx].velocity[0],
-                relax_factor * inlet_vel[1] + (1.0 - relax_factor) * lattice[idx].velocity[1],
-                relax_factor * inlet_vel[2] + (1.0 - relax_factor) * lattice[idx].velocity[2]
-            );
+                // Apply relaxation for smoother transition
+                let relax_factor = 0.9; // Strong enforcement but not abrupt
+                let relaxed_vel = array<f32, 3>(
+                    relax_factor * inlet_vel[0] + (1.0 - relax_factor) * lattice[idx].velocity[0],
+                    relax_factor * inlet_vel[1] + (1.0 - relax_factor) * lattice[idx].velocity[1],
+                    relax_factor * inlet_vel[2] + (1.0 - relax_factor) * lattice[idx].velocity[2]
+                );
             
-            // Use relaxed density too for stability
-            let relaxed_density = relax_factor * config.density + (1.0 - relax_factor) * lattice[idx].density;
+                // Use relaxed density too for stability
+                let relaxed_density = relax_factor * config.density + (1.0 - relax_factor) * lattice[idx].density;
             
-            // Set equilibrium distribution at inlet with relaxed values
-            for (var i = 0u; i < Q; i++) {
-                temp[idx].f[i] = equilibrium_distribution(i, relaxed_density, relaxed_vel);
-            }
+                // Set equilibrium distribution at inlet with relaxed values
+                for (var i = 0u; i < Q; i++) {
+                    temp[idx].f[i] = equilibrium_distribution(i, relaxed_density, relaxed_vel);
+                }
             
-            temp[idx].density = relaxed_density;
-            temp[idx].velocity = relaxed_vel;
+                temp[idx].density = relaxed_density;
+                temp[idx].velocity = relaxed_vel;
+                temp[idx].eddy_viscosity = 0.0;
+            }
         }
-        default: { // Solid, outlet, and other nodes - copy unchanged
-            for (var i = 0u; i < Q; i++) {
-                temp[idx].f[i] = lattice[idx].f[i];
+        case 3u: { // Outlet nodes
+            if (lattice[idx].boundary == OUTLET_PRESSURE) {
+                // Collide like fluid, the density is imposed in the boundary pass
+                collide_fluid(idx);
+            } else {
+                // Zero-gradient and convective outlets are set in the boundary pass, the
+                // convective one from the previous-step populations left here in `temp`
+                copy_node(idx);
             }
-            temp[idx].density = lattice[idx].density;
-            temp[idx].velocity = lattice[idx].velocity;
+        }
+        default: { // Solid and other nodes - copy unchanged
+            copy_node(idx);
         }
     }
     
-    // Copy node type and padding
+    // Copy node type and boundary data
     temp[idx].node_type = lattice[idx].node_type;
-    temp[idx].padding = lattice[idx].padding;
+    temp[idx].boundary = lattice[idx].boundary;
+    temp[idx].face = lattice[idx].face;
 }
@@ -1,102 +1,126 @@
 use anyhow::Result;
-use log::info;
+use log::{info, warn};
 use indicatif::{ProgressBar, ProgressStyle};
 use crate::{
-    config::Config,
+    config::{Config, InletProfile, BODY_FACE},
     geometry::Geometry,
+    inlet::{inlet_profile, Waveform},
     lattice::LatticePoint,
-    gpu::GPUContext,
+    backend::{self, ComputeBackend},
+    checkpoint::Checkpoint,
     output::VTKWriter,
+    units::UnitConverter,
 };
 
 pub struct LBMSolver {
     config: Config,
     geometry: Geometry,
-    gpu_context: GPUContext,
+    backend: Box<dyn ComputeBackend>,
     lattice: Vec<LatticePoint>,
     iteration: usize,
     vtk_writer: VTKWriter,
+    /// Conversion between the lattice units of `config` and the units of the input config
+    units: UnitConverter,
+    /// Time dependence of the inlet velocity
+    waveform: Waveform,
 }
 
 impl LBMSolver {
-    pub async fn new(config: Config, stl_path: &str) -> Result<Self> {
+    pub async fn new(mut config: Config, stl_path: &str) -> Result<Self> {
         info!("Initializing LBM solver with domain size: {}x{}x{}", 
               config.domain.nx, config.domain.ny, config.domain.nz);
         
-        // Load geometry from STL file
-        let geometry = Geometry::from_stl(stl_path, &config.domain)?;
+        // Load geometry (STL surface in 3D, outline in 2D)
+        let geometry = Geometry::load(stl_path, &config)?;
         info!("Loaded geometry with {} solid nodes, {} fluid nodes", 
               geometry.solid_nodes.len(), geometry.fluid_nodes.len());
+        let validation = config.validate(Some(geometry.bounds())).into_result()?;
+        for issue in validation.warnings() {
+            warn!("{}", issue);
+        }
+        config.resolve_characteristic_length(Some(geometry.bounds()))?;
+        
+        // Everything below runs in lattice units
+        let units = UnitConverter::from_config(&config)?;
+        let config = units.lattice_config(&config);
+        if units.is_physical() {
+            info!("Units: {}", units.describe());
+            info!("Lattice inlet velocity {:?}", config.physics.inlet_velocity);
+        }
+        info!("Relaxation time {:.4}, effective Reynolds number {:.1} (characteristic length {})",
+              units.tau, units.reynolds_number, config.characteristic_length());
         
-        // Initialize GPU context
-        let gpu_context = GPUContext::new(&config).await?;
+        // Initialize compute backend (GPU or CPU)
+        let mut backend = backend::create_backend(&config).await?;
         
         // Initialize lattice
-        let total_nodes = config.domain.nx * config.domain.ny * config.domain.nz;
-        let mut lattice = Vec::with_capacity(total_nodes);
-        
-        for k in 0..config.domain.nz {
-            for j in 0..config.domain.ny {
-                for i in 0..config.domain.nx {
-                    let node_type = if geometry.is_solid(i, j, k) {
-                        1 // Solid
-                    } else if geometry.is_inlet(i, j, k) {
-                        2 // Inlet
-                    } else if geometry.is_outlet(i, j, k) {
-                        3 // Outlet
-                    } else {
-                        0 // Fluid
-                    };
-                    
-                    // Initialize velocity based on node type
-                    let velocity = if node_type == 2 {
-                        // Inlet velocity
-                        config.physics.inlet_velocity
-                    } else if node_type == 0 {
-                        // Fluid nodes start with small initial velocity to seed the flow
-                        [config.physics.inlet_velocity[0] * 0.1, 0.0, 0.0]
-                    } else {
-                        // Solid and outlet nodes
-                        [0.0; 3]
-                    };
-                    
-                    let point = LatticePoint::new_equilibrium(
-                        config.physics.density,
-                        velocity,
-                        node_type,
-                    );
-                    
-                    lattice.push(point);
-                    
-                    // Debug output for some key nodes (use debug level to avoid interfering with progress bar)
-                    if (i == 0 && j == config.domain.ny / 2 && k == config.domain.nz / 2) ||
-                       (i == config.domain.nx / 2 && j == config.domain.ny / 2 && k == config.domain.nz / 2) {
-                        log::debug!("Node ({}, {}, {}): type={}, vel=[{:.4}, {:.4}, {:.4}]", 
-                                i, j, k, node_type, velocity[0], velocity[1], velocity[2]);
-                    }
+        let model = config.simulation.lattice.lattice();
+        info!("Lattice model: {}", model.name());
+        let waveform = Waveform::from_config(&config)?;
+        let mut lattice = initial_lattice(&config, &geometry)?;
+        
+        // Spatial inlet profile - inlet nodes start from their profile velocity;
+        // profile files hold velocities in the units of the input config
+        let mut profile = inlet_profile(&config, &lattice)?;
+        if let (Some(profile), Some(InletProfile::File { .. })) = (&mut profile, &config.boundaries.inlet_profile) {
+            for velocity in profile.iter_mut() {
+                *velocity = units.to_lattice_velocity(*velocity);
+            }
+        }
+        if let Some(profile) = &profile {
+            for (point, velocity) in lattice.iter_mut().zip(profile) {
+                if point.node_type == 2 {
+                    let velocity = velocity.map(|c| c * waveform.factor(0));
+                    let (boundary, face) = (point.boundary, point.face);
+                    *point = LatticePoint::new_equilibrium(model, config.physics.density, velocity, 2);
+                    (point.boundary, point.face) = (boundary, face);
                 }
             }
         }
         
-        // Upload initial data to GPU
-        gpu_context.upload_lattice_data(&lattice);
+        // Upload initial data to the backend
+        Self::upload_lattice(backend.as_mut(), &config, &geometry, &lattice)?;
+        if let Some(profile) = &profile {
+            backend.upload_inlet_profile(profile)?;
+        }
         
         // Write geometry file for visualization debugging
+        std::fs::create_dir_all(&config.output.output_directory)?;
         Self::write_geometry_file(&geometry, &config)?;
         
         // Initialize VTK writer
-        let vtk_writer = VTKWriter::new(&config);
+        let vtk_writer = VTKWriter::new(&config, &units);
         
         Ok(Self {
             config,
             geometry,
-            gpu_context,
+            backend,
             lattice,
             iteration: 0,
             vtk_writer,
+            units,
+            waveform,
         })
     }
     
+    /// Continue from a checkpoint written by an earlier run of the same
+    /// config and geometry
+    pub fn resume(&mut self, path: &str) -> Result<()> {
+        let checkpoint = Checkpoint::read(path, &self.config)?;
+        Self::upload_lattice(self.backend.as_mut(), &self.config, &self.geometry, &checkpoint.lattice)?;
+        self.lattice = checkpoint.lattice;
+        self.iteration = checkpoint.iteration;
+        self.vtk_writer.restore_collection(self.iteration);
+        info!("Resumed from {} at iteration {}", path, self.iteration);
+        Ok(())
+    }
+    
+    /// Replace the backend's lattice state and its wall links
+    fn upload_lattice(backend: &mut dyn ComputeBackend, config: &Config, geometry: &Geometry, lattice: &[LatticePoint]) -> Result<()> {
+        backend.upload_lattice_data(lattice)?;
+        backend.upload_wall_links(&geometry.wall_links(config, lattice)?)
+    }
+    
     pub async fn run(&mut self) -> Result<()> {
         info!("Starting LBM simulation for {} iterations", self.config.simulation.max_iterations);
         
@@ -111,22 +135,35 @@ impl LBMSolver {
             .progress_chars("#>-"));
         pb.set_message("LBM Simulation");
         
-        // Write initial state
-        if self.iteration % self.config.output.output_frequency == 0 {
+        // A resumed run continues the bar from its checkpoint
+        if self.iteration > 0 {
+            pb.set_position(self.iteration as u64);
+            pb.reset_eta();
+        }
+        
+        // Write initial state, unless an earlier run already wrote it
+        if self.iteration.is_multiple_of(self.config.output.output_frequency)
+            && !self.vtk_writer.has_snapshot(self.iteration)
+        {
             self.write_output().await?;
         }
         
         let mut converged = false;
         
         while self.iteration < self.config.simulation.max_iterations && !converged {
-            // Perform one LBM step on GPU
-            self.gpu_context.step()?;
+            // Inlet velocity of the new time level
+            if !self.waveform.is_constant() {
+                self.backend.update_face_values(&self.waveform.face_values(&self.config, self.iteration + 1))?;
+            }
+            
+            // Perform one LBM step on the backend
+            self.backend.step()?;
             
             self.iteration += 1;
             pb.set_position(self.iteration as u64);
             
             // Output results at specified frequency
-            if self.iteration % self.config.output.output_frequency == 0 {
+            if self.iteration.is_multiple_of(self.config.output.output_frequency) {
                 pb.set_message("Writing...");
                 self.write_output().await?;
                 
@@ -139,6 +176,12 @@ impl LBMSolver {
                     pb.set_message("LBM Simulation");
                 }
             }
+            
+            if let Some(frequency) = self.config.output.checkpoint_frequency
+                && self.iteration.is_multiple_of(frequency)
+            {
+                self.write_checkpoint()?;
+            }
         }
         
         pb.finish_with_message(format!("LBM Simulation completed - {} iterations", self.iteration));
@@ -159,8 +202,8 @@ impl LBMSolver {
     }
     
     async fn write_output(&mut self) -> Result<()> {
-        // Read data back from GPU
-        self.lattice = self.gpu_context.read_lattice_data().await?;
+        // Read data back from the backend
+        self.lattice = self.backend.read_lattice_data()?;
         
         // Calculate flow statistics for diagnostics
         let mut max_velocity = 0.0;
@@ -197,10 +240,7 @@ impl LBMSolver {
               self.iteration, max_velocity, avg_velocity, inlet_velocity_check, inlet_count);
         
         // Write VTK file
-        let filename = format!("{}/output_{:06}.{}", 
-                              self.config.output.output_directory,
-                              self.iteration,
-                              self.config.output.output_format);
+        let filename = self.vtk_writer.snapshot_path(self.iteration);
         
         self.vtk_writer.write(&filename, &self.lattice, self.iteration)?;
         
@@ -209,6 +249,19 @@ impl LBMSolver {
         Ok(())
     }
     
+    /// Save the lattice state to `checkpoint.bin` in the output directory,
+    /// replacing the previous checkpoint only once the new one is complete
+    fn write_checkpoint(&mut self) -> Result<()> {
+        let checkpoint = Checkpoint::new(&self.config, self.iteration, self.backend.read_lattice_data()?);
+        let path = format!("{}/checkpoint.bin", self.config.output.output_directory);
+        let partial = format!("{}.partial", path);
+        checkpoint.write(&partial)?;
+        std::fs::rename(&partial, &path)?;
+        self.lattice = checkpoint.lattice;
+        log::debug!("Wrote checkpoint {} at iteration {}", path, self.iteration);
+        Ok(())
+    }
+    
     async fn check_convergence(&self) -> Result<bool> {
         // Simple convergence check based on maximum velocity change
         // In a real implementation, you would compare with previous iteration
@@ -216,10 +269,9 @@ impl LBMSolver {
         let max_velocity = self.lattice.iter()
             .filter(|point| point.node_type == 0) // Only fluid nodes
             .map(|point| {
-                let v_mag = (point.velocity[0].powi(2) + 
-                           point.velocity[1].powi(2) + 
-                           point.velocity[2].powi(2)).sqrt();
-                v_mag
+                (point.velocity[0].powi(2) + 
+                 point.velocity[1].powi(2) + 
+                 point.velocity[2].powi(2)).sqrt()
             })
             .fold(0.0, f32::max);
         
@@ -235,6 +287,10 @@ impl LBMSolver {
         &self.config
     }
     
+    pub fn get_units(&self) -> &UnitConverter {
+        &self.units
+    }
+    
     pub fn get_geometry(&self) -> &Geometry {
         &self.geometry
     }
@@ -297,3 +353,76 @@ impl LBMSolver {
         Ok(())
     }
 }
+
+/// Lattice of `config` at the start of a run: every node of `geometry` at
+/// equilibrium with its node type, face and boundary scheme. Inlet nodes start
+/// at their face velocity, fluid nodes with a tenth of the inlet velocity to
+/// seed the flow; spatial inlet profiles are applied by the solver.
+pub fn initial_lattice(config: &Config, geometry: &Geometry) -> Result<Vec<LatticePoint>> {
+    let model = config.simulation.lattice.lattice();
+    let total_nodes = config.domain.nx * config.domain.ny * config.domain.nz;
+    let mut lattice = Vec::with_capacity(total_nodes);
+    let faces = config.boundaries.faces();
+    let waveform = Waveform::from_config(config)?;
+    let face_values = waveform.face_values(config, 0);
+    
+    for k in 0..config.domain.nz {
+        for j in 0..config.domain.ny {
+            for i in 0..config.domain.nx {
+                let node_type = if geometry.is_solid(i, j, k) {
+                    1 // Solid
+                } else if geometry.is_inlet(i, j, k) {
+                    2 // Inlet
+                } else if geometry.is_outlet(i, j, k) {
+                    3 // Outlet
+                } else {
+                    0 // Fluid
+                };
+                
+                // Initialize velocity based on node type
+                let velocity = if node_type == 2 {
+                    // Inlet velocity of the node's face
+                    let face = geometry.boundary_faces.get(&(i, j, k)).copied().unwrap_or(0);
+                    let [u, v, w, _] = face_values[face as usize];
+                    [u, v, w]
+                } else if node_type == 0 {
+                    // Fluid nodes start with small initial velocity to seed the flow
+                    [config.physics.inlet_velocity[0] * 0.1 * waveform.factor(0), 0.0, 0.0]
+                } else {
+                    // Solid and outlet nodes
+                    [0.0; 3]
+                };
+                
+                let mut point = LatticePoint::new_equilibrium(
+                    model,
+                    config.physics.density,
+                    velocity,
+                    node_type,
+                );
+                
+                // Inlet, outlet and wall face nodes carry their face and its boundary scheme,
+                // the remaining solid nodes belong to the STL body
+                if let Some(&face) = geometry.boundary_faces.get(&(i, j, k)) {
+                    point.face = face;
+                    if let Some(boundary) = faces[face as usize] {
+                        point.boundary = boundary.node_type().1;
+                    }
+                } else if node_type == 1 {
+                    point.face = BODY_FACE;
+                    point.boundary = config.boundaries.body_scheme() as u32;
+                }
+                
+                lattice.push(point);
+                
+                // Debug output for some key nodes (use debug level to avoid interfering with progress bar)
+                if j == config.domain.ny / 2 && k == config.domain.nz / 2 &&
+                   (i == 0 || i == config.domain.nx / 2) {
+                    log::debug!("Node ({}, {}, {}): type={}, vel=[{:.4}, {:.4}, {:.4}]", 
+                            i, j, k, node_type, velocity[0], velocity[1], velocity[2]);
+                }
+            }
+        }
+    }
+    
+    Ok(lattice)
+}
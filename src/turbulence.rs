@@ -0,0 +1,187 @@
+use crate::{
+    config::{Config, LesModel},
+    lattice::{Lattice, LatticePoint},
+    Float,
+};
+
+/// Velocity gradient g[a][b] = du_a/dx_b in lattice units
+pub type VelocityGradient = [[Float; 3]; 3];
+
+/// Subgrid-scale eddy-viscosity model for large-eddy simulation.
+///
+/// The model raises the local shear relaxation time to tau0 + nu_t / cs^2,
+/// where nu_t is evaluated from the node's current state inside the collision
+/// pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubgridModel {
+    pub model: LesModel,
+    /// Model constant (Cs for Smagorinsky, Cw for WALE, c for Vreman)
+    pub constant: Float,
+}
+
+impl SubgridModel {
+    /// The configured subgrid model, or `None` for laminar runs
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let les = &config.simulation.les;
+        let constant = match les.model {
+            LesModel::None => return None,
+            LesModel::Smagorinsky => les.smagorinsky_constant,
+            LesModel::Wale => les.wale_constant,
+            LesModel::Vreman => les.vreman_constant,
+        };
+        Some(Self { model: les.model, constant })
+    }
+
+    /// Whether the model needs the finite-difference velocity gradient
+    pub fn uses_velocity_gradient(&self) -> bool {
+        matches!(self.model, LesModel::Wale | LesModel::Vreman)
+    }
+
+    /// Shear relaxation time of `point` (with up-to-date macroscopic fields)
+    /// including the eddy viscosity. `gradient` is only used by the
+    /// gradient-based models.
+    pub fn effective_tau(
+        &self,
+        lattice: &dyn Lattice,
+        point: &LatticePoint,
+        gradient: &VelocityGradient,
+        tau0: Float,
+    ) -> Float {
+        match self.model {
+            LesModel::None => tau0,
+            LesModel::Smagorinsky => {
+                // nu_t = (Cs dx)^2 |S| with the strain rate taken from the
+                // non-equilibrium momentum flux, solved for tau in closed form
+                let cs = self.constant;
+                let pi_norm = non_equilibrium_flux_norm(lattice, point);
+                0.5 * (tau0 + (tau0 * tau0 + 18.0 * Float::sqrt(2.0) * cs * cs * pi_norm / point.density).sqrt())
+            }
+            LesModel::Wale => tau0 + wale_viscosity(gradient, self.constant) / lattice.cs2(),
+            LesModel::Vreman => tau0 + vreman_viscosity(gradient, self.constant) / lattice.cs2(),
+        }
+    }
+}
+
+/// Norm sqrt(Pi_ab Pi_ab) of the non-equilibrium momentum flux
+/// Pi_ab = sum_i c_ia c_ib (f_i - f_i^eq)
+pub fn non_equilibrium_flux_norm(lattice: &dyn Lattice, point: &LatticePoint) -> Float {
+    let mut pi = [0.0; 6]; // xx, yy, zz, xy, xz, yz
+    for (i, c) in lattice.velocities().iter().enumerate() {
+        let f_neq = point.f[i] - LatticePoint::equilibrium_distribution(lattice, i, point.density, point.velocity);
+        let c = [c[0] as Float, c[1] as Float, c[2] as Float];
+        pi[0] += c[0] * c[0] * f_neq;
+        pi[1] += c[1] * c[1] * f_neq;
+        pi[2] += c[2] * c[2] * f_neq;
+        pi[3] += c[0] * c[1] * f_neq;
+        pi[4] += c[0] * c[2] * f_neq;
+        pi[5] += c[1] * c[2] * f_neq;
+    }
+    (pi[0] * pi[0] + pi[1] * pi[1] + pi[2] * pi[2] +
+     2.0 * (pi[3] * pi[3] + pi[4] * pi[4] + pi[5] * pi[5])).sqrt()
+}
+
+/// Velocity of a node from the moments of its populations
+fn node_velocity(lattice: &dyn Lattice, point: &LatticePoint) -> [Float; 3] {
+    let mut point = *point;
+    point.calculate_macroscopic(lattice);
+    if point.density <= 1e-10 { [0.0; 3] } else { point.velocity }
+}
+
+/// Velocity gradient at `idx` by central differences, falling back to
+/// one-sided differences next to solid nodes and non-periodic domain edges so that
+/// no difference is taken into a solid (see `velocity_gradient` in
+/// `collision.wgsl`). `center` is the node's own, already updated velocity;
+/// neighbour velocities are taken from the moments of their populations.
+pub fn velocity_gradient(
+    lattice: &dyn Lattice,
+    nodes: &[LatticePoint],
+    idx: usize,
+    center: [Float; 3],
+    [nx, ny, nz]: [usize; 3],
+    periodic: [bool; 3],
+) -> VelocityGradient {
+    let position = [idx % nx, (idx / nx) % ny, idx / (nx * ny)];
+    let size = [nx, ny, nz];
+    let stride = [1, nx, nx * ny];
+
+    let neighbor = |axis: usize, forward: bool| -> Option<[Float; 3]> {
+        let n = if forward {
+            if position[axis] + 1 < size[axis] {
+                idx + stride[axis]
+            } else {
+                (periodic[axis] && size[axis] > 1).then(|| idx - position[axis] * stride[axis])?
+            }
+        } else if position[axis] > 0 {
+            idx - stride[axis]
+        } else {
+            (periodic[axis] && size[axis] > 1).then(|| idx + (size[axis] - 1) * stride[axis])?
+        };
+        (nodes[n].node_type != 1).then(|| node_velocity(lattice, &nodes[n]))
+    };
+
+    // derivatives[b][a] = du_a/dx_b
+    let derivatives = [0, 1, 2].map(|b| match (neighbor(b, true), neighbor(b, false)) {
+        (Some(plus), Some(minus)) => [0, 1, 2].map(|a| 0.5 * (plus[a] - minus[a])),
+        (Some(plus), None) => [0, 1, 2].map(|a| plus[a] - center[a]),
+        (None, Some(minus)) => [0, 1, 2].map(|a| center[a] - minus[a]),
+        (None, None) => [0.0; 3],
+    });
+    transpose(&derivatives)
+}
+
+fn transpose(m: &VelocityGradient) -> VelocityGradient {
+    [0, 1, 2].map(|i| [0, 1, 2].map(|j| m[j][i]))
+}
+
+fn multiply(a: &VelocityGradient, b: &VelocityGradient) -> VelocityGradient {
+    [0, 1, 2].map(|i| [0, 1, 2].map(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+/// Sum of the squared entries
+fn frobenius2(m: &VelocityGradient) -> Float {
+    m.iter().flatten().map(|v| v * v).sum()
+}
+
+/// WALE eddy viscosity nu_t = (Cw dx)^2 (Sd:Sd)^(3/2) / ((S:S)^(5/2) + (Sd:Sd)^(5/4)),
+/// which vanishes for pure shear and therefore at walls
+pub fn wale_viscosity(g: &VelocityGradient, cw: Float) -> Float {
+    let gt = transpose(g);
+    let g2 = multiply(g, g);
+    let g2t = transpose(&g2);
+    let trace = (g2[0][0] + g2[1][1] + g2[2][2]) / 3.0;
+
+    let mut s = [[0.0; 3]; 3];
+    let mut sd = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            s[i][j] = 0.5 * (g[i][j] + gt[i][j]);
+            sd[i][j] = 0.5 * (g2[i][j] + g2t[i][j]) - if i == j { trace } else { 0.0 };
+        }
+    }
+
+    let ss = frobenius2(&s);
+    let sdsd = frobenius2(&sd);
+    let denominator = ss.powf(2.5) + sdsd.powf(1.25);
+    if denominator <= 1e-30 {
+        return 0.0;
+    }
+    cw * cw * sdsd.powf(1.5) / denominator
+}
+
+/// Vreman eddy viscosity nu_t = c sqrt(B_beta / (alpha_ij alpha_ij)) with
+/// alpha_ij = du_j/dx_i and beta = alpha^T alpha (dx = 1)
+pub fn vreman_viscosity(g: &VelocityGradient, c: Float) -> Float {
+    let alpha_norm = frobenius2(g);
+    if alpha_norm <= 1e-30 {
+        return 0.0;
+    }
+
+    let beta = multiply(g, &transpose(g));
+    let b = beta[0][0] * beta[1][1] - beta[0][1] * beta[0][1]
+        + beta[0][0] * beta[2][2] - beta[0][2] * beta[0][2]
+        + beta[1][1] * beta[2][2] - beta[1][2] * beta[1][2];
+    if b <= 0.0 {
+        return 0.0;
+    }
+    c * (b / alpha_norm).sqrt()
+}
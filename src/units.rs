@@ -0,0 +1,205 @@
+use anyhow::Result;
+use crate::{
+    config::{CharacteristicLength, Config, FaceBoundary, InletWaveform},
+    Float,
+};
+
+/// Lattice Mach number used when neither `units.mach` nor `simulation.tau` is set
+pub const DEFAULT_MACH: Float = 0.1;
+
+/// Conversion factors between lattice and physical units.
+///
+/// Lattice quantities are scaled by the lattice spacing `dx`, the time step
+/// `dt` and the reference density, so a lattice velocity u is u dx/dt in m/s
+/// and a lattice viscosity ν is ν dx²/dt in m²/s. Configs without a `units`
+/// section are already in lattice units and convert with unit factors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitConverter {
+    /// Lattice spacing [m]
+    pub dx: Float,
+    /// Time step [s]
+    pub dt: Float,
+    /// Physical density of the lattice density 1 [kg/m³]
+    pub density: Float,
+    /// Relaxation time of the lattice viscosity
+    pub tau: Float,
+    /// Reynolds number of the characteristic velocity and length
+    pub reynolds_number: Float,
+    physical: bool,
+}
+
+impl UnitConverter {
+    /// Conversion of `config` without checking the resulting scales
+    pub fn new(config: &Config) -> Self {
+        let Some(units) = &config.units else {
+            return Self {
+                dx: 1.0,
+                dt: 1.0,
+                density: 1.0,
+                tau: config.calculate_tau(),
+                reynolds_number: config.effective_reynolds_number(),
+                physical: false,
+            };
+        };
+
+        let dx = config.domain.dx;
+        let cs2 = config.simulation.lattice.lattice().cs2();
+        let velocity = units.velocity.unwrap_or_else(|| config.inlet_speed());
+        let length = config.characteristic_length();
+        let viscosity = config
+            .physics
+            .viscosity
+            .unwrap_or(velocity * length / config.physics.reynolds_number);
+
+        // ν_lb = ν dt / dx² = cs² (τ - 1/2) and u_lb = U dt / dx = Ma cs
+        let dt = match (units.mach, config.simulation.tau) {
+            (None, Some(tau)) => cs2 * (tau - 0.5) * dx * dx / viscosity,
+            (mach, _) => mach.unwrap_or(DEFAULT_MACH) * cs2.sqrt() * dx / velocity,
+        };
+        Self {
+            dx,
+            dt,
+            density: config.physics.density,
+            tau: viscosity * dt / (dx * dx) / cs2 + 0.5,
+            reynolds_number: velocity * length / viscosity,
+            physical: true,
+        }
+    }
+
+    /// Conversion of `config`, failing on non-physical inputs or scales
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let converter = Self::new(config);
+        let Some(units) = &config.units else {
+            return Ok(converter);
+        };
+
+        anyhow::ensure!(
+            units.mach.is_none() || config.simulation.tau.is_none(),
+            "Set either units.mach or simulation.tau, the other follows from the time step"
+        );
+        let positive = [
+            ("units.length", units.length),
+            ("units.velocity", units.velocity),
+            ("units.mach", units.mach),
+            ("physics.density", Some(config.physics.density)),
+            ("physics.viscosity", config.physics.viscosity),
+            ("domain.dx", Some(config.domain.dx)),
+        ];
+        for (name, value) in positive {
+            if let Some(value) = value {
+                anyhow::ensure!(value > 0.0, "{} must be positive in physical units, got {}", name, value);
+            }
+        }
+        anyhow::ensure!(
+            converter.dt.is_finite() && converter.dt > 0.0,
+            "No lattice time step for a characteristic velocity of {} m/s",
+            units.velocity.unwrap_or_else(|| config.inlet_speed())
+        );
+        anyhow::ensure!(converter.tau > 0.5, "Relaxation time {} must exceed 0.5", converter.tau);
+        Ok(converter)
+    }
+
+    /// Whether the config is in physical units
+    pub fn is_physical(&self) -> bool {
+        self.physical
+    }
+
+    /// Velocity of one lattice unit [m/s]
+    pub fn velocity(&self) -> Float {
+        self.dx / self.dt
+    }
+
+    /// Pressure of one lattice unit [Pa]
+    pub fn pressure(&self) -> Float {
+        self.density * self.velocity() * self.velocity()
+    }
+
+    /// Kinematic viscosity of one lattice unit [m²/s]
+    pub fn viscosity(&self) -> Float {
+        self.dx * self.dx / self.dt
+    }
+
+    /// Time of `step` [s]
+    pub fn time(&self, step: usize) -> f64 {
+        step as f64 * self.dt as f64
+    }
+
+    /// Lattice velocity of a physical one
+    pub fn to_lattice_velocity(&self, velocity: [Float; 3]) -> [Float; 3] {
+        velocity.map(|u| u / self.velocity())
+    }
+
+    /// `config` in lattice units, as run by the backends: velocities and
+    /// densities are scaled, the viscosity and relaxation time fixed and
+    /// `reynolds_number` replaced by the simulated one. Configs already in
+    /// lattice units are returned unchanged.
+    pub fn lattice_config(&self, config: &Config) -> Config {
+        let mut lattice = config.clone();
+        if !self.physical {
+            return lattice;
+        }
+
+        let velocity = |u: Float| u / self.velocity();
+        let density = |rho: Float| rho / self.density;
+        lattice.units = None;
+        lattice.simulation.tau = Some(self.tau);
+        lattice.physics.reynolds_number = self.reynolds_number;
+        lattice.physics.characteristic_length = Some(CharacteristicLength::Length(config.characteristic_length()));
+        lattice.physics.inlet_velocity = self.to_lattice_velocity(config.physics.inlet_velocity);
+        lattice.physics.density = density(config.physics.density);
+        lattice.physics.viscosity = config.physics.viscosity.map(|nu| nu / self.viscosity());
+
+        let boundaries = &mut lattice.boundaries;
+        boundaries.outlet_density = boundaries.outlet_density.map(density);
+        boundaries.convection_velocity = boundaries.convection_velocity.map(velocity);
+        boundaries.body_velocity = boundaries.body_velocity.map(|u| self.to_lattice_velocity(u));
+        boundaries.inlet_waveform = boundaries.inlet_waveform.take().map(|waveform| match waveform {
+            InletWaveform::Ramp { duration } => InletWaveform::Ramp { duration: duration / self.dt },
+            InletWaveform::Sinusoidal { period, amplitude, phase } => {
+                InletWaveform::Sinusoidal { period: period / self.dt, amplitude, phase }
+            }
+            InletWaveform::Table { file, repeat, time_scale } => {
+                InletWaveform::Table { file, repeat, time_scale: Some(time_scale.unwrap_or(1.0) / self.dt) }
+            }
+        });
+        for face in [
+            &mut boundaries.x_min,
+            &mut boundaries.x_max,
+            &mut boundaries.y_min,
+            &mut boundaries.y_max,
+            &mut boundaries.z_min,
+            &mut boundaries.z_max,
+        ] {
+            *face = face.map(|face| match face {
+                FaceBoundary::VelocityInlet { velocity, scheme } => FaceBoundary::VelocityInlet {
+                    velocity: velocity.map(|u| self.to_lattice_velocity(u)),
+                    scheme,
+                },
+                FaceBoundary::MovingWall { velocity } => FaceBoundary::MovingWall {
+                    velocity: self.to_lattice_velocity(velocity),
+                },
+                FaceBoundary::PressureOutlet { density: rho } => FaceBoundary::PressureOutlet { density: rho.map(density) },
+                FaceBoundary::ConvectiveOutflow { convection_velocity } => FaceBoundary::ConvectiveOutflow {
+                    convection_velocity: convection_velocity.map(velocity),
+                },
+                face => face,
+            });
+        }
+        lattice
+    }
+
+    /// One-line summary of the conversion factors, as written into output headers
+    pub fn describe(&self) -> String {
+        if !self.physical {
+            return "lattice units".to_string();
+        }
+        format!(
+            "SI units: dx {:e} m, dt {:e} s, velocity {:e} m/s, density {:e} kg/m^3, pressure {:e} Pa per lattice unit",
+            self.dx,
+            self.dt,
+            self.velocity(),
+            self.density,
+            self.pressure()
+        )
+    }
+}
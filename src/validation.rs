@@ -0,0 +1,371 @@
+use std::fmt;
+use crate::{
+    collision::CollisionOperator,
+    config::{Config, FaceBoundary, InletProfile, LesModel},
+    inlet::Waveform,
+    units::UnitConverter,
+    Float,
+};
+
+/// Relaxation times below this are accepted but close to the BGK stability limit of 1/2
+const MIN_SAFE_TAU: Float = 0.51;
+/// Relaxation times above this are stable but increasingly inaccurate
+const MAX_ACCURATE_TAU: Float = 2.0;
+/// Lattice Mach number above which compressibility errors (~Ma²) dominate
+const MAX_MACH: Float = 0.3;
+/// Lattice Mach number above which compressibility errors become noticeable
+const WARN_MACH: Float = 0.2;
+/// Grid Reynolds number u dx / ν above which under-resolved runs without a
+/// subgrid model tend to become unstable
+const MAX_GRID_REYNOLDS: Float = 20.0;
+/// Smallest number of nodes across the body for a resolved boundary layer
+const MIN_BODY_NODES: Float = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The run would fail or produce meaningless results
+    Error,
+    /// The run is possible but likely inaccurate or unstable
+    Warning,
+}
+
+/// One finding of `Config::validate`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Config path of the offending setting, e.g. `simulation.tau`
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.field, self.message)
+    }
+}
+
+/// Pre-flight report of a config: stability bounds, Mach and grid Reynolds
+/// numbers, domain and geometry fit, and output settings
+#[derive(Debug, Clone, Default)]
+pub struct Validation {
+    pub issues: Vec<Issue>,
+}
+
+impl Validation {
+    fn push(&mut self, severity: Severity, field: &str, message: String) {
+        self.issues.push(Issue { severity, field: field.to_string(), message });
+    }
+
+    fn error(&mut self, field: &str, message: String) {
+        self.push(Severity::Error, field, message);
+    }
+
+    fn warning(&mut self, field: &str, message: String) {
+        self.push(Severity::Warning, field, message);
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// Whether an issue was reported for `field`
+    pub fn mentions(&self, field: &str) -> bool {
+        self.issues.iter().any(|issue| issue.field == field)
+    }
+
+    /// The report itself, or an error listing every error found
+    pub fn into_result(self) -> anyhow::Result<Self> {
+        if !self.has_errors() {
+            return Ok(self);
+        }
+        let errors: Vec<String> = self.errors().map(|issue| format!("  {}", issue)).collect();
+        anyhow::bail!("Invalid configuration:\n{}", errors.join("\n"))
+    }
+}
+
+/// Checks of `config`, with `body` the bounding box (min, max corners) of
+/// the geometry when known. Lattice-unit checks use the converted config
+/// (see `UnitConverter`).
+pub fn validate(config: &Config, body: Option<[[Float; 3]; 2]>) -> Validation {
+    let mut report = Validation::default();
+    check_domain(config, body, &mut report);
+    check_output(config, &mut report);
+    check_legacy_tau(config, &mut report);
+
+    match (config.units.as_ref().and_then(|units| units.length), &config.physics.characteristic_length) {
+        (Some(_), Some(_)) => report.error(
+            "units.length",
+            "set either units.length or physics.characteristic_length, not both".to_string(),
+        ),
+        (Some(_), None) => report.warning(
+            "units.length",
+            "deprecated, use physics.characteristic_length instead".to_string(),
+        ),
+        _ => {}
+    }
+
+    let mut config = config.clone();
+    let length = config.resolve_characteristic_length(body).map(|_| config.characteristic_length());
+    match length {
+        Err(e) => report.error("physics.characteristic_length", e.to_string()),
+        Ok(length) if length.is_nan() || length <= 0.0 => {
+            report.error("physics.characteristic_length", format!("must be positive, got {}", length));
+        }
+        Ok(_) => {}
+    }
+    let config = &config;
+
+    let units = match UnitConverter::from_config(config) {
+        Ok(units) => units,
+        Err(e) => {
+            report.error("units", e.to_string());
+            return report;
+        }
+    };
+    let lattice = units.lattice_config(config);
+    check_stability(&lattice, &mut report);
+    check_boundaries(&lattice, &mut report);
+    check_reynolds_number(config, &units, &mut report);
+    report
+}
+
+fn check_domain(config: &Config, body: Option<[[Float; 3]; 2]>, report: &mut Validation) {
+    let domain = &config.domain;
+    let size = [domain.nx, domain.ny, domain.nz];
+    let spacing = [domain.dx, domain.dy, domain.dz];
+    for (axis, name) in ["x", "y", "z"].iter().enumerate() {
+        if size[axis] == 0 {
+            report.error(&format!("domain.n{}", name), "the domain needs at least one node per axis".to_string());
+        }
+        if spacing[axis].is_nan() || spacing[axis] <= 0.0 {
+            report.error(&format!("domain.d{}", name), format!("grid spacing must be positive, got {}", spacing[axis]));
+        }
+    }
+    if config.is_2d() && domain.nz != 1 {
+        report.error("domain.nz", format!("2D lattices need nz = 1, got {}", domain.nz));
+    }
+    let planar_axes = if config.is_2d() { 2 } else { 3 };
+    if spacing[..planar_axes].iter().any(|&d| (d - domain.dx).abs() > 1e-6 * domain.dx.abs()) {
+        report.warning(
+            "domain.dx",
+            format!("lattice cells are cubic, but dx, dy, dz = {:?}: the geometry is stretched", spacing),
+        );
+    }
+
+    let Some([low, high]) = body else { return };
+    // Node i covers the cell from i * dx to (i + 1) * dx
+    let extent = [0, 1, 2].map(|a| size[a] as Float * spacing[a]);
+    let outside = (0..planar_axes).any(|a| high[a] < 0.0 || low[a] > extent[a]);
+    if outside {
+        report.error(
+            "domain",
+            format!(
+                "the geometry ({:?} to {:?}) lies outside the domain (0 to {:?}); check domain.dx against the STL units",
+                low, high, extent
+            ),
+        );
+        return;
+    }
+    if (0..planar_axes).any(|a| low[a] < 0.0 || high[a] > extent[a]) {
+        report.warning(
+            "domain",
+            format!("the geometry ({:?} to {:?}) extends beyond the domain (0 to {:?}) and is clipped", low, high, extent),
+        );
+    }
+    let nodes = (0..planar_axes)
+        .map(|a| (high[a].min(extent[a]) - low[a].max(0.0)) / spacing[a])
+        .fold(Float::INFINITY, Float::min);
+    if nodes < MIN_BODY_NODES {
+        report.warning(
+            "domain.dx",
+            format!("the geometry is only {:.1} nodes across; refine the grid to resolve it", nodes),
+        );
+    }
+}
+
+fn check_output(config: &Config, report: &mut Validation) {
+    let output = &config.output;
+    if output.output_frequency == 0 {
+        report.error("output.output_frequency", "must be at least 1".to_string());
+    } else if output.output_frequency > config.simulation.max_iterations {
+        report.warning(
+            "output.output_frequency",
+            format!(
+                "exceeds simulation.max_iterations ({}): only the initial state is written",
+                config.simulation.max_iterations
+            ),
+        );
+    }
+    if output.checkpoint_frequency == Some(0) {
+        report.error("output.checkpoint_frequency", "must be at least 1".to_string());
+    }
+    if output.output_directory.trim().is_empty() {
+        report.error("output.output_directory", "must not be empty".to_string());
+    }
+    if !["vtk", "vtu"].contains(&output.output_format.as_str()) {
+        report.warning(
+            "output.output_format",
+            format!("'{}' is used as the file extension only, the files are legacy VTK", output.output_format),
+        );
+    }
+    if config.simulation.max_iterations == 0 {
+        report.warning("simulation.max_iterations", "no time steps are run".to_string());
+    }
+}
+
+fn check_stability(config: &Config, report: &mut Validation) {
+    let collision = match CollisionOperator::from_config(config) {
+        Ok(collision) => collision,
+        Err(e) => {
+            report.error("simulation.collision_model", e.to_string());
+            return;
+        }
+    };
+
+    let tau = collision.tau();
+    if tau.is_nan() || tau <= 0.5 {
+        report.error("simulation.tau", format!("relaxation time {} must exceed 0.5 (negative viscosity)", tau));
+        return;
+    }
+    if tau < MIN_SAFE_TAU {
+        report.warning(
+            "simulation.tau",
+            format!("relaxation time {:.6} is close to the stability limit 0.5", tau),
+        );
+    } else if tau > MAX_ACCURATE_TAU {
+        report.warning(
+            "simulation.tau",
+            format!("relaxation time {:.3} > {} reduces accuracy; lower the lattice viscosity", tau, MAX_ACCURATE_TAU),
+        );
+    }
+
+    let cs = config.simulation.lattice.lattice().cs2().sqrt();
+    let speed = max_speed(config);
+    let mach = speed / cs;
+    if mach > MAX_MACH {
+        report.error(
+            "physics.inlet_velocity",
+            format!("lattice Mach number {:.3} exceeds {}: lower the lattice velocity", mach, MAX_MACH),
+        );
+    } else if mach > WARN_MACH {
+        report.warning("physics.inlet_velocity", format!("lattice Mach number {:.3} gives compressibility errors", mach));
+    }
+
+    let viscosity = config.simulation.lattice.lattice().cs2() * (tau - 0.5);
+    let grid_reynolds = speed / viscosity;
+    if grid_reynolds > MAX_GRID_REYNOLDS && config.simulation.les.model == LesModel::None {
+        report.warning(
+            "simulation.tau",
+            format!(
+                "grid Reynolds number u dx / ν = {:.1} > {}: refine the grid, raise tau or enable simulation.les",
+                grid_reynolds, MAX_GRID_REYNOLDS
+            ),
+        );
+    }
+}
+
+/// Largest lattice speed set anywhere in the config, with velocity inlets
+/// at the peak of their waveform
+fn max_speed(config: &Config) -> Float {
+    let speed = |u: &[Float]| u.iter().map(|c| c * c).sum::<Float>().sqrt();
+    let peak = Waveform::from_config(config).map_or(1.0, |waveform| waveform.peak_factor());
+    let faces = config.boundaries.faces();
+    config
+        .boundaries
+        .face_values(&config.physics)
+        .iter()
+        .enumerate()
+        .map(|(face, values)| match faces.get(face) {
+            Some(Some(FaceBoundary::VelocityInlet { .. })) => speed(&values[..3]) * peak,
+            _ => speed(&values[..3]),
+        })
+        .fold(speed(&config.physics.inlet_velocity) * peak, Float::max)
+}
+
+/// The configured Reynolds number is only simulated when it sets the viscosity
+fn check_reynolds_number(config: &Config, units: &UnitConverter, report: &mut Validation) {
+    let configured = config.physics.reynolds_number;
+    let effective = units.reynolds_number;
+    let overridden_by = if config.physics.viscosity.is_some() {
+        "physics.viscosity"
+    } else if config.simulation.tau.is_some() {
+        "simulation.tau"
+    } else {
+        return;
+    };
+    if (effective - configured).abs() > 0.01 * configured.abs() {
+        report.warning(
+            "physics.reynolds_number",
+            format!(
+                "{} sets the viscosity: the simulated Reynolds number is {:.1}, not {}",
+                overridden_by, effective, configured
+            ),
+        );
+    }
+}
+
+/// Relaxation time that versions before `physics.characteristic_length`
+/// derived: ν from the largest inlet velocity component and L = `domain.dx`
+/// in the units of the config, and τ = 3ν / c_s² + ½
+fn legacy_tau(config: &Config) -> Float {
+    let [u, v, w] = config.physics.inlet_velocity;
+    let viscosity = config
+        .physics
+        .viscosity
+        .unwrap_or(u.max(v.max(w)) * config.domain.dx / config.physics.reynolds_number);
+    3.0 * viscosity / (1.0 / 3.0) + 0.5
+}
+
+/// Configs that leave the relaxation time to the solver get a different one
+/// than with earlier versions; point out how to keep either
+fn check_legacy_tau(config: &Config, report: &mut Validation) {
+    if config.units.is_some() || config.simulation.tau.is_some() || config.physics.characteristic_length.is_some() {
+        return;
+    }
+    let (tau, legacy) = (config.calculate_tau(), legacy_tau(config));
+    if (tau - legacy).abs() > 1e-4 {
+        report.warning(
+            "simulation.tau",
+            format!(
+                "the derived relaxation time {:.6} differs from {:.6} of earlier versions; set \
+                 physics.characteristic_length to choose the length of the Reynolds number, or \
+                 simulation.tau = {:.6} to keep the earlier value",
+                tau, legacy, legacy
+            ),
+        );
+    }
+}
+
+fn check_boundaries(config: &Config, report: &mut Validation) {
+    if let Err(e) = config.boundaries.periodic_axes() {
+        report.error("boundaries", e.to_string());
+    }
+    if let Err(e) = Waveform::from_config(config) {
+        report.error("boundaries.inlet_waveform", e.to_string());
+    }
+    if let Some(InletProfile::File { file }) = &config.boundaries.inlet_profile
+        && !std::path::Path::new(file).is_file()
+    {
+        report.error("boundaries.inlet_profile.file", format!("{} does not exist", file));
+    }
+    if let Some(u) = config.boundaries.convection_velocity
+        && u <= 0.0
+    {
+        report.warning(
+            "boundaries.convection_velocity",
+            format!("{} <= 0 falls back to the local outflow velocity", u),
+        );
+    }
+}
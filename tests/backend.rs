@@ -0,0 +1,158 @@
+use lattice_boltzmann_rs::{
+    backend::ComputeBackend, boundary::wall_links, config::{LatticeType, PowerPreference}, gpu::dispatch_grid, Config, CPUContext, GPUContext,
+    LatticePoint,
+};
+
+mod common;
+
+use common::test_config;
+
+/// Channel with an inlet at x=0, an outlet at x=nx-1 and a solid block in the middle
+fn channel_lattice(config: &Config) -> Vec<LatticePoint> {
+    let model = config.simulation.lattice.lattice();
+    let (nx, ny, nz) = (config.domain.nx, config.domain.ny, config.domain.nz);
+    let mut lattice = Vec::with_capacity(nx * ny * nz);
+    for _ in 0..nz {
+        for j in 0..ny {
+            for i in 0..nx {
+                let node_type = if i == 0 {
+                    2
+                } else if i == nx - 1 {
+                    3
+                } else if (nx / 3..nx / 2).contains(&i) && (ny / 3..2 * ny / 3).contains(&j) {
+                    1
+                } else {
+                    0
+                };
+                let velocity = if node_type == 1 { [0.0; 3] } else { config.physics.inlet_velocity };
+                lattice.push(LatticePoint::new_equilibrium(model, 1.0, velocity, node_type));
+            }
+        }
+    }
+    lattice
+}
+
+#[test]
+fn cpu_uniform_equilibrium_is_stationary() {
+    for lattice_type in [LatticeType::D2Q9, LatticeType::D3Q15, LatticeType::D3Q19, LatticeType::D3Q27] {
+        let nz = if lattice_type == LatticeType::D2Q9 { 1 } else { 4 };
+        let mut config = test_config(8, 6, nz);
+        config.simulation.lattice = lattice_type;
+        let model = lattice_type.lattice();
+        let lattice: Vec<LatticePoint> = (0..8 * 6 * nz)
+            .map(|_| LatticePoint::new_equilibrium(model, 1.0, [0.05, 0.0, 0.0], 0))
+            .collect();
+
+        let mut cpu = CPUContext::new(&config).unwrap();
+        cpu.upload_lattice_data(&lattice).unwrap();
+        for _ in 0..5 {
+            cpu.step().unwrap();
+        }
+
+        for (before, after) in lattice.iter().zip(cpu.read_lattice_data()) {
+            for i in 0..model.q() {
+                assert!((before.f[i] - after.f[i]).abs() < 1e-6, "{}: f[{}] drifted", model.name(), i);
+            }
+        }
+    }
+}
+
+#[test]
+fn lattice_moments_are_isotropic() {
+    for lattice_type in [LatticeType::D2Q9, LatticeType::D3Q15, LatticeType::D3Q19, LatticeType::D3Q27] {
+        let model = lattice_type.lattice();
+        let weights = model.weights();
+        let velocities = model.velocities();
+
+        let total: f32 = weights.iter().sum();
+        assert!((total - 1.0).abs() < 1e-6, "{} weights sum to {}", model.name(), total);
+
+        for a in 0..3 {
+            for b in 0..3 {
+                let second: f32 = weights.iter().zip(velocities)
+                    .map(|(w, c)| w * (c[a] * c[b]) as f32)
+                    .sum();
+                let expected = if a == b && a < model.dimensions() { model.cs2() } else { 0.0 };
+                assert!((second - expected).abs() < 1e-6, "{} second moment {}{}", model.name(), a, b);
+            }
+        }
+
+        for (i, &o) in model.opposite().iter().enumerate() {
+            let (c, co) = (velocities[i], velocities[o]);
+            assert_eq!([c[0], c[1], c[2]], [-co[0], -co[1], -co[2]], "{} opposite of {}", model.name(), i);
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with `cargo test -- --ignored`"]
+fn gpu_matches_cpu_reference() {
+    let mut config = test_config(16, 12, 6);
+    config.simulation.lattice = LatticeType::D3Q19;
+    let mut gpu = pollster::block_on(GPUContext::new(&config)).expect("No GPU adapter available");
+    let mut cpu = CPUContext::new(&config).unwrap();
+
+    let lattice = channel_lattice(&config);
+    ComputeBackend::upload_lattice_data(&mut gpu, &lattice).unwrap();
+    ComputeBackend::upload_lattice_data(&mut cpu, &lattice).unwrap();
+    let links = wall_links(config.simulation.lattice.lattice(), &lattice, [16, 12, 6], [false; 3]);
+    ComputeBackend::upload_wall_links(&mut gpu, &links).unwrap();
+    ComputeBackend::upload_wall_links(&mut cpu, &links).unwrap();
+    for _ in 0..10 {
+        ComputeBackend::step(&mut gpu).unwrap();
+        ComputeBackend::step(&mut cpu).unwrap();
+    }
+
+    let gpu_lattice = ComputeBackend::read_lattice_data(&gpu).unwrap();
+    let cpu_lattice = ComputeBackend::read_lattice_data(&cpu).unwrap();
+    for (g, c) in gpu_lattice.iter().zip(&cpu_lattice) {
+        assert_eq!(g.node_type, c.node_type);
+        for i in 0..19 {
+            assert!((g.f[i] - c.f[i]).abs() < 1e-4, "f[{}]: gpu {} cpu {}", i, g.f[i], c.f[i]);
+        }
+    }
+}
+
+#[test]
+fn wall_link_dispatch_stays_within_workgroup_limit() {
+    assert_eq!(dispatch_grid(1, 64, 65535), [1, 1]);
+    assert_eq!(dispatch_grid(64 * 65535, 64, 65535), [65535, 1]);
+
+    // 4.2M links and more spill over into y; the grid covers every link
+    for links in [64 * 65535 + 1, 5_000_000, 40_000_000] {
+        let [x, y] = dispatch_grid(links, 64, 65535);
+        assert!(x <= 65535 && y <= 65535, "{} links: {} x {}", links, x, y);
+        assert!(x as u64 * y as u64 * 64 >= links as u64, "{} links", links);
+        assert!((x as u64 * (y as u64 - 1)) * 64 < links as u64, "{} links: spare row of workgroups", links);
+    }
+}
+
+#[test]
+fn gpu_backend_lists_parse() {
+    assert_eq!(GPUContext::parse_backends("all").unwrap(), wgpu::Backends::all());
+    assert_eq!(GPUContext::parse_backends(" ALL ").unwrap(), wgpu::Backends::all());
+    assert_eq!(GPUContext::parse_backends("vulkan, Metal").unwrap(), wgpu::Backends::VULKAN | wgpu::Backends::METAL);
+    assert_eq!(GPUContext::parse_backends("gl").unwrap(), wgpu::Backends::GL);
+
+    for list in ["vulkan,cuda", "cuda", "", " , "] {
+        let err = GPUContext::parse_backends(list).unwrap_err().to_string();
+        assert!(err.contains("wgpu backend"), "'{}': {}", list, err);
+    }
+    let err = GPUContext::parse_backends("vulkan,cuda").unwrap_err().to_string();
+    assert_eq!(err, "Unknown wgpu backend 'cuda' in 'vulkan,cuda'");
+}
+
+#[test]
+fn power_preferences_parse() {
+    for (name, preference) in [
+        ("high", PowerPreference::HighPerformance),
+        ("High_Performance", PowerPreference::HighPerformance),
+        ("low", PowerPreference::LowPower),
+        ("low_power", PowerPreference::LowPower),
+        ("NONE", PowerPreference::None),
+    ] {
+        assert_eq!(name.parse::<PowerPreference>().unwrap(), preference, "{}", name);
+    }
+    let err = "fast".parse::<PowerPreference>().unwrap_err().to_string();
+    assert!(err.contains("Unknown power preference 'fast'"), "{}", err);
+}
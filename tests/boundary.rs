@@ -0,0 +1,770 @@
+use lattice_boltzmann_rs::{
+    boundary::{
+        convective_outflow, face_normal, free_slip, interior_neighbor, interpolated_bounce_back, wall_links,
+        zou_he_pressure, zou_he_velocity, WallLink,
+    },
+    config::{BoundaryConfig, FaceBoundary, InletScheme, LatticeType, OutletScheme, WallScheme},
+    solver::initial_lattice,
+    Config, CPUContext, Geometry, Lattice, LatticePoint,
+};
+
+mod common;
+
+use common::{test_config, TestConfig};
+
+const LATTICES: [LatticeType; 4] = [LatticeType::D2Q9, LatticeType::D3Q15, LatticeType::D3Q19, LatticeType::D3Q27];
+
+/// Faces of the lattice's domain (the z faces only exist in 3D)
+fn faces(model: &dyn Lattice) -> std::ops::Range<u32> {
+    0..2 * model.dimensions() as u32
+}
+
+/// Non-equilibrium state whose populations entering through `face` are garbage
+fn streamed_boundary_point(model: &dyn Lattice, face: u32) -> LatticePoint {
+    let mut point = LatticePoint::new_equilibrium(model, 1.03, [0.02, -0.01, 0.015], 2);
+    for i in 0..model.q() {
+        point.f[i] *= 1.0 + 0.04 * ((i * 5 % 7) as f32 - 3.0) / 3.0;
+    }
+    point.face = face;
+
+    let normal = face_normal(face);
+    for (i, c) in model.velocities().iter().enumerate() {
+        if c[0] * normal[0] + c[1] * normal[1] + c[2] * normal[2] > 0 {
+            point.f[i] = 0.5 + 0.1 * i as f32;
+        }
+    }
+    point
+}
+
+/// Inlet velocity along the inward normal of `face` with a tangential component
+fn inlet_velocity(model: &dyn Lattice, face: u32) -> [f32; 3] {
+    let normal = face_normal(face);
+    [0, 1, 2].map(|a| match normal[a] {
+        _ if a >= model.dimensions() => 0.0,
+        0 => 0.01 * (a + 1) as f32,
+        n => 0.06 * n as f32,
+    })
+}
+
+#[test]
+fn zou_he_recovers_imposed_velocity() {
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+        for face in faces(model) {
+            let velocity = inlet_velocity(model, face);
+            let mut point = streamed_boundary_point(model, face);
+            zou_he_velocity(model, &mut point, velocity);
+
+            let density = point.density;
+            point.calculate_macroscopic(model);
+            assert!((point.density - density).abs() < 1e-6, "{} face {}: density", model.name(), face);
+            for (a, (u, expected)) in point.velocity.iter().zip(velocity).enumerate() {
+                assert!((u - expected).abs() < 1e-6, "{} face {}: u[{}] = {} != {}", model.name(), face, a, u, expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn zou_he_reproduces_equilibrium() {
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+        for face in faces(model) {
+            let velocity = inlet_velocity(model, face);
+            let equilibrium = LatticePoint::new_equilibrium(model, 0.98, velocity, 2);
+
+            let mut point = equilibrium;
+            point.face = face;
+            let normal = face_normal(face);
+            for (i, c) in model.velocities().iter().enumerate() {
+                if c[0] * normal[0] + c[1] * normal[1] + c[2] * normal[2] > 0 {
+                    point.f[i] = 0.0;
+                }
+            }
+            zou_he_velocity(model, &mut point, velocity);
+
+            assert!((point.density - 0.98).abs() < 1e-6, "{} face {}", model.name(), face);
+            for (i, (a, b)) in point.f.iter().zip(&equilibrium.f).take(model.q()).enumerate() {
+                assert!((a - b).abs() < 1e-6, "{} face {}: f[{}] {} != {}", model.name(), face, i, a, b);
+            }
+        }
+    }
+}
+
+/// Upload `lattice` to the CPU backend along with its half-way wall links
+fn upload(cpu: &mut CPUContext, config: &Config, lattice: &[LatticePoint]) {
+    let model = config.simulation.lattice.lattice();
+    let dims = [config.domain.nx, config.domain.ny, config.domain.nz];
+    cpu.upload_lattice_data(lattice).unwrap();
+    cpu.upload_wall_links(&wall_links(model, lattice, dims, config.boundaries.periodic_axes().unwrap())).unwrap();
+}
+
+/// Geometry from the polygon (2D) or STL (3D) file `contents`
+fn geometry_from(config: &Config, contents: &str) -> Geometry {
+    let extension = if config.is_2d() { "txt" } else { "stl" };
+    let name = format!("lbm-geometry-{}-{:?}.{}", std::process::id(), std::thread::current().id(), extension);
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, contents).unwrap();
+    let geometry = Geometry::load(path.to_str().unwrap(), config).unwrap();
+    std::fs::remove_file(&path).ok();
+    geometry
+}
+
+/// Geometry of the domain faces alone, with a body outside the domain
+fn faces_geometry(config: &Config) -> Geometry {
+    if config.is_2d() {
+        geometry_from(config, "-3 -3\n-2 -3\n-2 -2\n")
+    } else {
+        geometry_from(
+            config,
+            "solid outside\nfacet normal 0 0 1\nouter loop\nvertex -3 -3 -3\nvertex -2 -3 -3\nvertex -2 -2 -3\n\
+             endloop\nendfacet\nendsolid outside\n",
+        )
+    }
+}
+
+/// Every node of `lattice` at equilibrium with the velocity of its index,
+/// keeping its node type, face and boundary scheme
+fn set_velocity(config: &Config, lattice: &mut [LatticePoint], velocity: impl Fn(usize) -> [f32; 3]) {
+    let model = config.simulation.lattice.lattice();
+    for (idx, point) in lattice.iter_mut().enumerate() {
+        let (node_type, face, boundary) = (point.node_type, point.face, point.boundary);
+        *point = LatticePoint::new_equilibrium(model, config.physics.density, velocity(idx), node_type);
+        (point.face, point.boundary) = (face, boundary);
+    }
+}
+
+/// Steady channel run on the CPU backend with inlet nodes at x=0 and outlet
+/// nodes at x=nx-1 using the configured boundary schemes
+fn run_channel(boundaries: serde_json::Value) -> (Config, Vec<LatticePoint>) {
+    let config = test_config(12, 6, 4).lattice(LatticeType::D3Q19).boundaries(boundaries);
+    let lattice = initial_lattice(&config, &faces_geometry(&config)).unwrap();
+
+    let mut cpu = CPUContext::new(&config).unwrap();
+    upload(&mut cpu, &config, &lattice);
+    for _ in 0..20 {
+        cpu.step().unwrap();
+    }
+    let lattice = cpu.read_lattice_data();
+    (config, lattice)
+}
+
+#[test]
+fn cpu_zou_he_inlet_imposes_velocity() {
+    let (config, lattice) = run_channel(serde_json::json!({ "inlet": "zou_he" }));
+    assert_eq!(config.boundaries.inlet, InletScheme::ZouHe);
+
+    let model = config.simulation.lattice.lattice();
+    for mut point in lattice.into_iter().filter(|p| p.node_type == 2) {
+        point.calculate_macroscopic(model);
+        for (a, (u, expected)) in point.velocity.iter().zip(config.physics.inlet_velocity).enumerate() {
+            assert!((u - expected).abs() < 1e-6, "u[{}] = {} != {}", a, u, expected);
+        }
+    }
+}
+
+#[test]
+fn zou_he_pressure_imposes_density() {
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+        for face in faces(model) {
+            let mut point = streamed_boundary_point(model, face);
+            zou_he_pressure(model, &mut point, 1.01);
+
+            let velocity = point.velocity;
+            point.calculate_macroscopic(model);
+            assert!((point.density - 1.01).abs() < 1e-6, "{} face {}: rho = {}", model.name(), face, point.density);
+
+            let normal = face_normal(face);
+            for a in 0..3 {
+                assert!((point.velocity[a] - velocity[a]).abs() < 1e-6, "{} face {}: u[{}]", model.name(), face, a);
+                if normal[a] == 0 {
+                    assert!(point.velocity[a].abs() < 1e-6, "{} face {}: tangential u[{}]", model.name(), face, a);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn cpu_pressure_outlet_imposes_density() {
+    let (config, lattice) = run_channel(serde_json::json!({
+        "inlet": "zou_he",
+        "outlet": "pressure",
+        "outlet_density": 0.995
+    }));
+    assert_eq!(config.boundaries.outlet, OutletScheme::Pressure);
+
+    let model = config.simulation.lattice.lattice();
+    for mut point in lattice.into_iter().filter(|p| p.node_type == 3) {
+        point.calculate_macroscopic(model);
+        assert!((point.density - 0.995).abs() < 1e-6, "rho = {}", point.density);
+        assert!(point.velocity[0] > 0.0, "outflow velocity {}", point.velocity[0]);
+        assert!(point.velocity[1].abs() < 1e-6 && point.velocity[2].abs() < 1e-6);
+    }
+}
+
+#[test]
+fn convective_outflow_advects_interior_populations() {
+    let model = LatticeType::D3Q19.lattice();
+    let previous = LatticePoint::new_equilibrium(model, 1.0, [0.04, 0.0, 0.0], 3);
+    let interior = LatticePoint::new_equilibrium(model, 1.02, [0.06, 0.01, 0.0], 0);
+    let mut point = previous;
+    point.face = 1;
+
+    // U = 1: average of the previous and the interior populations
+    convective_outflow(model, &mut point, &previous, Some(&interior), Some(1.0));
+    for i in 0..model.q() {
+        assert!((point.f[i] - 0.5 * (previous.f[i] + interior.f[i])).abs() < 1e-7, "f[{}]", i);
+    }
+    assert!((point.density - 1.01).abs() < 1e-6);
+
+    // Computed U from the interior outflow velocity (0.06 along +x)
+    let mut point = previous;
+    point.face = 1;
+    convective_outflow(model, &mut point, &previous, Some(&interior), None);
+    for i in 0..model.q() {
+        let expected = (previous.f[i] + 0.06 * interior.f[i]) / 1.06;
+        assert!((point.f[i] - expected).abs() < 1e-7, "f[{}]", i);
+    }
+
+    // No interior neighbour: keep the previous populations
+    let mut point = previous;
+    convective_outflow(model, &mut point, &previous, None, None);
+    assert_eq!(point.f, previous.f);
+}
+
+#[test]
+fn interior_neighbor_steps_along_inward_normal() {
+    let dims = [4, 3, 2];
+    let idx = |i: usize, j: usize, k: usize| i + j * 4 + k * 12;
+    assert_eq!(interior_neighbor(idx(0, 1, 1), 0, dims), Some(idx(1, 1, 1)));
+    assert_eq!(interior_neighbor(idx(3, 1, 1), 1, dims), Some(idx(2, 1, 1)));
+    assert_eq!(interior_neighbor(idx(2, 2, 0), 3, dims), Some(idx(2, 1, 0)));
+    assert_eq!(interior_neighbor(idx(2, 2, 1), 5, dims), Some(idx(2, 2, 0)));
+    assert_eq!(interior_neighbor(idx(3, 1, 1), 0, dims), None);
+}
+
+#[test]
+fn cpu_convective_outlet_lets_flow_leave() {
+    let (config, lattice) = run_channel(serde_json::json!({ "inlet": "zou_he", "outlet": "convective" }));
+    assert_eq!(config.boundaries.outlet, OutletScheme::Convective);
+
+    // The start-up flow leaves through the outlet with a bounded density
+    let model = config.simulation.lattice.lattice();
+    for mut point in lattice.into_iter().filter(|p| p.node_type == 3) {
+        point.calculate_macroscopic(model);
+        assert!(point.density.is_finite() && (point.density - 1.0).abs() < 0.05, "rho = {}", point.density);
+        assert!(point.velocity[0] > 0.0, "outflow velocity {}", point.velocity[0]);
+    }
+}
+
+#[test]
+fn cpu_zero_gradient_outlet_behind_wall_keeps_populations() {
+    // Outlet nodes at x = 5 behind a body filling the column x = 4, in a fluid at rest
+    let mut config = test_config(6, 4, 1).lattice(LatticeType::D2Q9).boundaries(serde_json::json!({ "outlet": "zero_gradient" }));
+    config.physics.inlet_velocity = [0.0; 3];
+    let geometry = geometry_from(&config, "4 -1\n5 -1\n5 5\n4 5\n");
+    assert!((0..4).all(|j| geometry.is_solid(4, j, 0) && geometry.is_outlet(5, j, 0)));
+    let lattice = initial_lattice(&config, &geometry).unwrap();
+
+    let mut cpu = CPUContext::new(&config).unwrap();
+    upload(&mut cpu, &config, &lattice);
+    for _ in 0..5 {
+        cpu.step().unwrap();
+    }
+    for point in cpu.read_lattice_data().into_iter().filter(|p| p.node_type == 3) {
+        assert!((point.density - 1.0).abs() < 1e-5, "rho = {}", point.density);
+        assert!(point.velocity.iter().all(|u| u.abs() < 1e-5), "u = {:?}", point.velocity);
+    }
+}
+
+#[test]
+fn faces_default_to_x_inlet_and_outlet() {
+    let config = test_config(4, 4, 4).lattice(LatticeType::D3Q19).boundaries(serde_json::json!({
+        "inlet": "zou_he", "outlet": "pressure", "outlet_density": 0.99, "z_max": { "type": "no_slip" }
+    }));
+
+    let faces = config.boundaries.faces();
+    assert_eq!(faces[0], Some(FaceBoundary::VelocityInlet { velocity: None, scheme: InletScheme::ZouHe }));
+    assert_eq!(faces[1], Some(FaceBoundary::PressureOutlet { density: Some(0.99) }));
+    assert_eq!(faces[2..5], [None, None, None]);
+    assert_eq!(faces[5], Some(FaceBoundary::NoSlip));
+
+    let values = config.boundaries.face_values(&config.physics);
+    assert_eq!(values[0], [0.05, 0.0, 0.0, 1.0]);
+    assert_eq!(values[1], [0.0, 0.0, 0.0, 0.99]);
+}
+
+/// 2D channel driven in +y: walls at x_min/x_max, a Zou-He inlet at y_min and
+/// a pressure outlet at y_max, around a small square obstacle
+fn y_channel_config() -> Config {
+    test_config(8, 16, 1).lattice(LatticeType::D2Q9).boundaries(serde_json::json!({
+        "x_min": { "type": "no_slip" },
+        "x_max": { "type": "no_slip" },
+        "y_min": { "type": "velocity_inlet", "velocity": [0.0, 0.04, 0.0], "scheme": "zou_he" },
+        "y_max": { "type": "pressure_outlet", "density": 1.0 }
+    }))
+}
+
+fn y_channel_geometry(config: &Config) -> Geometry {
+    geometry_from(config, "3 7\n5 7\n5 9\n3 9\n")
+}
+
+#[test]
+fn geometry_marks_configured_faces() {
+    let config = y_channel_config();
+    let geometry = y_channel_geometry(&config);
+
+    for j in 0..16 {
+        assert!(geometry.is_solid(0, j, 0) && geometry.is_solid(7, j, 0), "wall at y = {}", j);
+    }
+    for i in 1..7 {
+        assert!(geometry.is_inlet(i, 0, 0), "inlet at x = {}", i);
+        assert!(geometry.is_outlet(i, 15, 0), "outlet at x = {}", i);
+        assert_eq!(geometry.boundary_faces[&(i, 0, 0)], 2);
+        assert_eq!(geometry.boundary_faces[&(i, 15, 0)], 3);
+    }
+    assert!(geometry.is_solid(4, 8, 0), "obstacle");
+    assert!(!geometry.is_inlet(0, 0, 0) && !geometry.is_outlet(0, 15, 0), "walls win on shared edges");
+}
+
+#[test]
+fn cpu_runs_channel_along_y() {
+    let config = y_channel_config();
+    let model = config.simulation.lattice.lattice();
+    let lattice = initial_lattice(&config, &y_channel_geometry(&config)).unwrap();
+
+    let mut cpu = CPUContext::new(&config).unwrap();
+    upload(&mut cpu, &config, &lattice);
+    for _ in 0..50 {
+        cpu.step().unwrap();
+    }
+
+    let lattice = cpu.read_lattice_data();
+    for mut point in lattice.iter().copied().filter(|p| p.node_type == 2) {
+        point.calculate_macroscopic(model);
+        assert!(point.velocity[0].abs() < 1e-6 && (point.velocity[1] - 0.04).abs() < 1e-6, "{:?}", point.velocity);
+    }
+    for mut point in lattice.iter().copied().filter(|p| p.node_type == 3) {
+        point.calculate_macroscopic(model);
+        assert!((point.density - 1.0).abs() < 1e-6 && point.velocity[1] > 0.0, "{} {:?}", point.density, point.velocity);
+    }
+}
+
+#[test]
+fn periodic_faces_come_in_pairs() {
+    let boundaries = |value| serde_json::from_value::<BoundaryConfig>(value).unwrap();
+
+    let channel = boundaries(serde_json::json!({ "y_min": { "type": "periodic" }, "y_max": { "type": "periodic" } }));
+    assert_eq!(channel.periodic_axes().unwrap(), [false, true, false]);
+
+    let err = boundaries(serde_json::json!({ "x_min": { "type": "periodic" } })).periodic_axes().unwrap_err().to_string();
+    assert!(err.contains("x_min") && err.contains("x_max"), "{}", err);
+}
+
+#[test]
+fn geometry_leaves_periodic_faces_fluid() {
+    let mut config = y_channel_config();
+    config.boundaries.y_min = Some(FaceBoundary::Periodic);
+    config.boundaries.y_max = Some(FaceBoundary::Periodic);
+    let geometry = y_channel_geometry(&config);
+
+    for i in 1..7 {
+        for j in [0, 15] {
+            assert!(geometry.is_fluid(i, j, 0), "({}, {}) should be fluid", i, j);
+            assert!(!geometry.boundary_faces.contains_key(&(i, j, 0)));
+        }
+    }
+    assert!(geometry.is_solid(0, 0, 0) && geometry.is_solid(7, 15, 0), "walls");
+    assert!(geometry.is_solid(4, 8, 0), "obstacle");
+}
+
+#[test]
+fn cpu_periodic_shear_wave_decays_at_lattice_viscosity() {
+    // Fully periodic box with u_x = A sin(k y), which decays as exp(-nu k^2 t)
+    let (nx, ny, tau) = (4, 16, 0.8);
+    let periodic = serde_json::json!({ "type": "periodic" });
+    let config = test_config(nx, ny, 1).lattice(LatticeType::D2Q9).boundaries(serde_json::json!({
+        "x_min": periodic, "x_max": periodic, "y_min": periodic, "y_max": periodic
+    }));
+
+    let model = config.simulation.lattice.lattice();
+    let k = 2.0 * std::f32::consts::PI / ny as f32;
+    let amplitude = 0.02;
+    let mut lattice = initial_lattice(&config, &faces_geometry(&config)).unwrap();
+    set_velocity(&config, &mut lattice, |idx| [amplitude * (k * (idx / nx) as f32).sin(), 0.0, 0.0]);
+
+    let mut cpu = CPUContext::new(&config).unwrap();
+    upload(&mut cpu, &config, &lattice);
+    let steps = 50;
+    for _ in 0..steps {
+        cpu.step().unwrap();
+    }
+    let mut lattice = cpu.read_lattice_data();
+    for point in lattice.iter_mut() {
+        point.calculate_macroscopic(model);
+    }
+
+    let mass: f32 = lattice.iter().map(|p| p.density).sum();
+    assert!((mass - (nx * ny) as f32).abs() < 1e-3, "mass {}", mass);
+
+    // Project the velocity onto the initial mode
+    let projection: f32 = lattice
+        .iter()
+        .enumerate()
+        .map(|(idx, p)| p.velocity[0] * (k * (idx / nx) as f32).sin())
+        .sum::<f32>() * 2.0 / (nx * ny) as f32;
+    let nu = model.cs2() * (tau - 0.5);
+    let expected = amplitude * (-nu * k * k * steps as f32).exp();
+    assert!((projection - expected).abs() < 0.02 * expected, "amplitude {} != {}", projection, expected);
+}
+
+#[test]
+fn free_slip_reverses_only_normal_momentum() {
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+        for face in faces(model) {
+            let mut point = streamed_boundary_point(model, face);
+            point.calculate_macroscopic(model);
+            let (density, velocity) = (point.density, point.velocity);
+            free_slip(model, &mut point);
+
+            let mut momentum = [0.0; 3];
+            for (f, c) in point.f.iter().zip(model.velocities()) {
+                for a in 0..3 {
+                    momentum[a] += f * c[a] as f32;
+                }
+            }
+            let normal = face_normal(face);
+            let tolerance = 1e-6 * density;
+            assert!((point.f[..model.q()].iter().sum::<f32>() - density).abs() < tolerance, "{} face {}", model.name(), face);
+            for a in 0..3 {
+                let expected = if normal[a] == 0 { velocity[a] } else { -velocity[a] } * density;
+                assert!((momentum[a] - expected).abs() < tolerance, "{} face {}: momentum[{}]", model.name(), face, a);
+            }
+        }
+    }
+}
+
+/// Channel that is periodic in x between walls of the given types at y_min
+/// and y_max, started from a uniform flow; returns the macroscopic state of
+/// the column x = 1
+fn run_walled_channel([bottom, top]: [FaceBoundary; 2], steps: usize) -> Vec<LatticePoint> {
+    let (nx, ny) = (4, 10);
+    let config = test_config(nx, ny, 1).lattice(LatticeType::D2Q9).boundaries(serde_json::json!({
+        "x_min": { "type": "periodic" },
+        "x_max": { "type": "periodic" },
+        "y_min": bottom,
+        "y_max": top
+    }));
+
+    let model = config.simulation.lattice.lattice();
+    let mut lattice = initial_lattice(&config, &faces_geometry(&config)).unwrap();
+    set_velocity(&config, &mut lattice, |_| [0.05, 0.0, 0.0]);
+
+    let mut cpu = CPUContext::new(&config).unwrap();
+    upload(&mut cpu, &config, &lattice);
+    for _ in 0..steps {
+        cpu.step().unwrap();
+    }
+    let lattice = cpu.read_lattice_data();
+    (0..ny)
+        .map(|y| {
+            let mut point = lattice[1 + y * nx];
+            point.calculate_macroscopic(model);
+            point
+        })
+        .collect()
+}
+
+/// Off-diagonal non-equilibrium momentum flux, proportional to the shear stress
+fn shear_flux(model: &dyn Lattice, point: &LatticePoint) -> f32 {
+    model
+        .velocities()
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let equilibrium = LatticePoint::equilibrium_distribution(model, i, point.density, point.velocity);
+            (point.f[i] - equilibrium) * (c[0] * c[1]) as f32
+        })
+        .sum()
+}
+
+#[test]
+fn cpu_free_slip_walls_exert_no_shear() {
+    let model = LatticeType::D2Q9.lattice();
+    assert_eq!(FaceBoundary::FreeSlip.node_type(), (1, WallScheme::FreeSlip as u32));
+
+    let free_slip = run_walled_channel([FaceBoundary::FreeSlip; 2], 50)[1];
+    assert!((free_slip.velocity[0] - 0.05).abs() < 1e-6, "u = {}", free_slip.velocity[0]);
+    assert!(shear_flux(model, &free_slip).abs() < 1e-6, "shear {}", shear_flux(model, &free_slip));
+
+    // No-slip walls slow the flow down next to them
+    let no_slip = run_walled_channel([FaceBoundary::NoSlip; 2], 50)[1];
+    assert!(no_slip.velocity[0] < 0.04, "u = {}", no_slip.velocity[0]);
+    assert!(shear_flux(model, &no_slip).abs() > 1e-4, "shear {}", shear_flux(model, &no_slip));
+}
+
+#[test]
+fn moving_wall_reflects_wall_equilibrium() {
+    // Fluid moving with a y_min wall, whose links bounce its equilibrium back unchanged
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+        let velocity = [0.04, 0.0, if model.dimensions() == 3 { 0.01 } else { 0.0 }];
+        let dims = [3, 3, if model.dimensions() == 3 { 3 } else { 1 }];
+        let nodes: Vec<LatticePoint> = (0..dims.iter().product())
+            .map(|idx| {
+                let mut point = LatticePoint::new_equilibrium(model, 1.0, velocity, 0);
+                if (idx / dims[0]) % dims[1] == 0 {
+                    (point.node_type, point.boundary, point.face) = (1, WallScheme::MovingWall as u32, 2);
+                }
+                point
+            })
+            .collect();
+
+        let links = wall_links(model, &nodes, dims, [true; 3]);
+        let crossing = model.velocities().iter().filter(|c| c[1] == -1).count();
+        assert_eq!(links.len(), crossing * dims[0] * dims[2] * 2, "{}: both fluid rows see the wall", model.name());
+        for link in &links {
+            assert_eq!(link.q, 0.5);
+            let f = interpolated_bounce_back(model, &nodes, link, dims, [true; 3], 1.0, velocity);
+            let expected = nodes[link.node as usize].f[model.opposite()[link.direction as usize]];
+            assert!((f - expected).abs() < 1e-6, "{}: {} != {}", model.name(), f, expected);
+        }
+    }
+}
+
+#[test]
+fn cpu_couette_flow_has_linear_profile() {
+    // Walls moving with -U/2 at y_min and U/2 at y_max, both lying halfway
+    // between the wall nodes and the first fluid nodes
+    let speed = 0.04;
+    let wall = |u: f32| FaceBoundary::MovingWall { velocity: [u, 0.0, 0.0] };
+    let column = run_walled_channel([wall(-0.5 * speed), wall(0.5 * speed)], 3000);
+
+    let height = (column.len() - 2) as f32;
+    for (y, point) in column.iter().enumerate().take(column.len() - 1).skip(1) {
+        let expected = speed * ((y as f32 - 0.5) / height - 0.5);
+        assert!((point.velocity[0] - expected).abs() < 1e-5, "y = {}: u = {} != {}", y, point.velocity[0], expected);
+    }
+}
+
+/// Relative L2 deviation from the parabola of pressure-driven Poiseuille flow
+/// between no-slip walls half-way between the wall nodes and the fluid
+/// nodes, `height` nodes apart, at the centre of the channel
+fn poiseuille_error(height: usize) -> f32 {
+    let (nx, ny, tau) = (5, height + 2, 0.8);
+    let model = LatticeType::D2Q9.lattice();
+
+    // Pressure difference for a centreline velocity of 0.02: u_c = H^2 G / 8 rho nu
+    let viscosity = (tau - 0.5) * model.cs2();
+    let drop = 8.0 * viscosity * 0.02 * (nx - 1) as f32 / (model.cs2() * (height * height) as f32);
+    let mut config = test_config(nx, ny, 1).lattice(LatticeType::D2Q9).boundaries(serde_json::json!({
+        "x_min": { "type": "pressure_outlet", "density": 1.0 + 0.5 * drop },
+        "x_max": { "type": "pressure_outlet", "density": 1.0 - 0.5 * drop },
+        "y_min": { "type": "no_slip" },
+        "y_max": { "type": "no_slip" }
+    }));
+    config.simulation.tau = Some(tau);
+    config.physics.inlet_velocity = [0.0; 3];
+    let lattice = initial_lattice(&config, &faces_geometry(&config)).unwrap();
+
+    let mut cpu = CPUContext::new(&config).unwrap();
+    upload(&mut cpu, &config, &lattice);
+    for _ in 0..16 * height * height {
+        cpu.step().unwrap();
+    }
+    let lattice = cpu.read_lattice_data();
+
+    // Least-squares fit of the centreline velocity to the measured profile
+    let (profile, parabola): (Vec<f32>, Vec<f32>) = (1..ny - 1)
+        .map(|y| {
+            let mut point = lattice[nx / 2 + y * nx];
+            point.calculate_macroscopic(model);
+            let s = (y as f32 - 0.5) / height as f32;
+            (point.velocity[0], 4.0 * s * (1.0 - s))
+        })
+        .unzip();
+    let centreline = profile.iter().zip(&parabola).map(|(u, p)| u * p).sum::<f32>() / parabola.iter().map(|p| p * p).sum::<f32>();
+    let error: f32 = profile.iter().zip(&parabola).map(|(u, p)| (u - centreline * p).powi(2)).sum();
+    let norm: f32 = parabola.iter().map(|p| (centreline * p).powi(2)).sum();
+    (error / norm).sqrt()
+}
+
+#[test]
+fn cpu_poiseuille_flow_converges_at_second_order() {
+    let errors: Vec<f32> = [8, 16, 32].into_iter().map(poiseuille_error).collect();
+    for pair in errors.windows(2) {
+        let order = (pair[0] / pair[1]).log2();
+        assert!(order > 1.8, "errors {:?}: order {}", errors, order);
+    }
+}
+
+#[test]
+fn geometry_keeps_cavity_corners_stationary() {
+    let mut config = y_channel_config();
+    config.boundaries.y_min = Some(FaceBoundary::NoSlip);
+    config.boundaries.y_max = Some(FaceBoundary::MovingWall { velocity: [0.05, 0.0, 0.0] });
+    let geometry = y_channel_geometry(&config);
+
+    assert!(geometry.is_solid(4, 15, 0), "lid");
+    assert_eq!(geometry.boundary_faces[&(4, 15, 0)], 3);
+    assert_eq!(geometry.boundary_faces[&(0, 15, 0)], 0);
+    assert_eq!(geometry.boundary_faces[&(7, 15, 0)], 1);
+    assert!(!geometry.boundary_faces.contains_key(&(4, 8, 0)), "the obstacle belongs to the body");
+    assert_eq!(config.boundaries.body_scheme(), WallScheme::BounceBack);
+}
+
+#[test]
+fn geometry_finds_wall_link_fractions() {
+    // Square body from (3.2, 7.2) to (5.2, 9.2): solid nodes i = 3..=4, j = 7..=8
+    let config = y_channel_config();
+    let geometry = geometry_from(&config, "3.2 7.2\n5.2 7.2\n5.2 9.2\n3.2 9.2\n");
+    let model = config.simulation.lattice.lattice();
+    let nx = config.domain.nx;
+    let dims = [nx, config.domain.ny, 1];
+    let mut links = wall_links(model, &initial_lattice(&config, &geometry).unwrap(), dims, [false; 3]);
+    geometry.interpolate_wall_links(&config.domain, model, &mut links);
+
+    let direction = |c: [i32; 3]| model.velocities().iter().position(|v| *v == c).unwrap() as u32;
+    let link = |(i, j): (usize, usize), c: [i32; 3]| {
+        let node = (i + j * nx) as u32;
+        links.iter().find(|l| l.node == node && l.direction == direction(c)).copied()
+    };
+    for (node, c, q) in [((2, 8), [1, 0, 0], 0.7), ((5, 8), [-1, 0, 0], 0.3), ((3, 6), [0, 1, 0], 0.7), ((4, 9), [0, -1, 0], 0.3)] {
+        let link = link(node, c).unwrap_or_else(|| panic!("no link from {:?} along {:?}", node, c));
+        assert!((link.q - q).abs() < 1e-5, "{:?} along {:?}: q = {} != {}", node, c, link.q, q);
+    }
+
+    // Every link from a fluid node into the body crosses its surface, the walls stay half-way
+    let (body, walls): (Vec<WallLink>, Vec<WallLink>) = links.iter().partition(|l| {
+        let (i, j) = (l.solid as usize % nx, l.solid as usize / nx);
+        (3..=4).contains(&i) && (7..=8).contains(&j)
+    });
+    assert!(body.iter().all(|l| l.q > 0.0 && l.q <= 1.0 && l.q != 0.5));
+    assert!(walls.iter().all(|l| l.q == 0.5));
+    assert_eq!(link((1, 8), [-1, 0, 0]).map(|l| l.q), Some(0.5), "x_min wall");
+    assert_eq!(body.len(), 8 * 2 + 4, "axis and diagonal links of the side nodes, diagonals of the corners");
+}
+
+/// ASCII STL of a sphere faceted into `bands` latitude bands of 2 `bands` facets each
+fn sphere_stl(centre: [f32; 3], radius: f32, bands: usize) -> String {
+    let vertex = |band: usize, sector: usize| {
+        let (theta, phi) = (std::f32::consts::PI * band as f32 / bands as f32, std::f32::consts::PI * sector as f32 / bands as f32);
+        let direction = [theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()];
+        format!("vertex {} {} {}\n", centre[0] + radius * direction[0], centre[1] + radius * direction[1], centre[2] + radius * direction[2])
+    };
+    let mut stl = String::from("solid sphere\n");
+    for band in 0..bands {
+        for sector in 0..2 * bands {
+            let corners = [(band, sector), (band + 1, sector), (band + 1, sector + 1), (band, sector + 1)];
+            let mut triangles = vec![[corners[0], corners[1], corners[2]], [corners[0], corners[2], corners[3]]];
+            // Drop the degenerate triangles at the poles
+            if band + 1 == bands {
+                triangles.remove(0);
+            } else if band == 0 {
+                triangles.remove(1);
+            }
+            for triangle in triangles {
+                stl += "facet normal 0 0 0\nouter loop\n";
+                for (band, sector) in triangle {
+                    stl += &vertex(band, sector);
+                }
+                stl += "endloop\nendfacet\n";
+            }
+        }
+    }
+    stl + "endsolid sphere\n"
+}
+
+#[test]
+fn geometry_finds_wall_link_fractions_on_stl_body() {
+    // Off-grid sphere of radius 3.3: the voxelized shell reaches past the
+    // surface, and only the nodes inside it stay solid
+    let (centre, radius) = ([6.2f32, 6.1, 5.9], 3.3f32);
+    let config =
+        test_config(13, 13, 12).lattice(LatticeType::D3Q19).boundaries(serde_json::json!({ "interpolated_bounce_back": true }));
+    let geometry = geometry_from(&config, &sphere_stl(centre, radius, 32));
+    let model = config.simulation.lattice.lattice();
+    let (nx, ny) = (config.domain.nx, config.domain.ny);
+    // Node centre relative to the sphere centre
+    let offset = |idx: u32| {
+        let idx = idx as usize;
+        let position = [idx % nx, (idx / nx) % ny, idx / (nx * ny)];
+        [0, 1, 2].map(|a| position[a] as f32 + 0.5 - centre[a])
+    };
+    let dot = |a: [f32; 3], b: [f32; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+
+    let body: std::collections::HashSet<u32> = geometry
+        .solid_nodes
+        .iter()
+        .filter(|node| !geometry.boundary_faces.contains_key(node))
+        .map(|&(i, j, k)| (i + j * nx + k * nx * ny) as u32)
+        .collect();
+    assert!(body.iter().all(|&idx| dot(offset(idx), offset(idx)) < radius * radius));
+
+    let lattice = initial_lattice(&config, &geometry).unwrap();
+    let links = geometry.wall_links(&config, &lattice).unwrap();
+    let body_links: Vec<&WallLink> = links.iter().filter(|link| body.contains(&link.solid)).collect();
+    assert!(body_links.len() > 100, "{} body links", body_links.len());
+    for link in body_links {
+        // Nearest crossing of |origin + q c| = radius, which the facets move by a few hundredths
+        let c = model.velocities()[link.direction as usize].map(|c| c as f32);
+        let origin = offset(link.node);
+        let (a, b, d) = (dot(c, c), dot(origin, c), dot(origin, origin) - radius * radius);
+        let expected = (-b - (b * b - a * d).sqrt()) / a;
+        assert!(link.q > 0.0 && link.q <= 1.0, "q = {}", link.q);
+        assert!((link.q - expected).abs() < 0.04, "{} along {:?}: q = {} != {}", link.node, c, link.q, expected);
+    }
+}
+
+#[test]
+fn interpolated_bounce_back_preserves_wall_equilibrium() {
+    // A uniform equilibrium moving with the wall is a fixed point for any q
+    let model = LatticeType::D2Q9.lattice();
+    let dims = [4, 4, 1];
+    let velocity = [0.03, 0.0, 0.0];
+    let nodes: Vec<LatticePoint> = (0..16).map(|_| LatticePoint::new_equilibrium(model, 1.0, velocity, 0)).collect();
+    let down = model.velocities().iter().position(|c| *c == [1, -1, 0]).unwrap();
+
+    for q in [0.1, 0.5, 0.8, 1.0] {
+        let link = WallLink { node: 5, direction: down as u32, q, solid: 2 };
+        let f = interpolated_bounce_back(model, &nodes, &link, dims, [true, false, false], 1.0, velocity);
+        let expected = nodes[5].f[model.opposite()[down]];
+        assert!((f - expected).abs() < 1e-6, "q = {}: {} != {}", q, f, expected);
+    }
+}
+
+#[test]
+fn cpu_interpolated_bounce_back_places_wall_off_grid() {
+    // Couette flow between a body surface at y = 2.3 and a lid moving with U,
+    // which lies halfway between the last fluid and the wall nodes at y = 11
+    let (speed, wall) = (0.04, 2.3);
+    let config = test_config(4, 12, 1).lattice(LatticeType::D2Q9).boundaries(serde_json::json!({
+        "interpolated_bounce_back": true,
+        "x_min": { "type": "periodic" },
+        "x_max": { "type": "periodic" },
+        "y_max": { "type": "moving_wall", "velocity": [speed, 0.0, 0.0] }
+    }));
+    let geometry = geometry_from(&config, &format!("-1 -1\n5 -1\n5 {0}\n-1 {0}\n", wall));
+    let model = config.simulation.lattice.lattice();
+
+    let lattice = initial_lattice(&config, &geometry).unwrap();
+    let mut cpu = CPUContext::new(&config).unwrap();
+    cpu.upload_lattice_data(&lattice).unwrap();
+    cpu.upload_wall_links(&geometry.wall_links(&config, &lattice).unwrap()).unwrap();
+    // Re-uploading the state (as a resumed run does) keeps the interpolated links
+    cpu.upload_lattice_data(&lattice).unwrap();
+    for _ in 0..4000 {
+        cpu.step().unwrap();
+    }
+    let lattice = cpu.read_lattice_data();
+
+    for j in 2..11 {
+        let mut point = lattice[1 + j * 4];
+        point.calculate_macroscopic(model);
+        let expected = speed * (j as f32 + 0.5 - wall) / (11.0 - wall);
+        assert!((point.velocity[0] - expected).abs() < 1e-5, "y = {}: {} != {}", j, point.velocity[0], expected);
+    }
+}
@@ -0,0 +1,212 @@
+use lattice_boltzmann_rs::{
+    backend::memory_estimate,
+    checkpoint::Checkpoint,
+    config::{BackendType, FaceBoundary, InletWaveform, LatticeType},
+    Config, LBMSolver, LatticePoint,
+};
+use std::path::PathBuf;
+
+mod common;
+
+use common::{test_config, TestConfig};
+
+/// 40 × 20 CPU channel run for 20 iterations, written every 10
+fn channel_config(output_directory: &str) -> Config {
+    let mut config = test_config(40, 20, 1).spacing(0.001).lattice(LatticeType::D2Q9).output_directory(output_directory);
+    config.physics.reynolds_number = 50.0;
+    (config.simulation.max_iterations, config.simulation.convergence_tolerance) = (20, 1e-9);
+    config.simulation.backend = BackendType::Cpu;
+    config.output.output_frequency = 10;
+    config
+}
+
+/// Fresh scratch path, unique per test
+fn scratch(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("lbm-cli-{}-{}", std::process::id(), name));
+    std::fs::remove_dir_all(&path).ok();
+    path
+}
+
+#[test]
+fn overrides_set_nested_fields() {
+    let mut config = channel_config("./output");
+    config.apply_override("domain.nx=200").unwrap();
+    config.apply_override("physics.inlet_velocity.1 = 0.01").unwrap();
+    config.apply_override("simulation.backend=gpu").unwrap();
+    config.apply_override("units.mach=0.05").unwrap();
+    config.apply_override(r#"boundaries.y_min={"type": "free_slip"}"#).unwrap();
+    config.apply_override("output.output_directory=runs/a").unwrap();
+    assert_eq!(config.domain.nx, 200);
+    assert_eq!(config.physics.inlet_velocity, [0.05, 0.01, 0.0]);
+    assert_eq!(config.simulation.backend, BackendType::Gpu);
+    assert_eq!(config.units.as_ref().and_then(|units| units.mach), Some(0.05));
+    assert_eq!(config.boundaries.y_min, Some(FaceBoundary::FreeSlip));
+    assert_eq!(config.output.output_directory, "runs/a");
+
+    // Values that parse as JSON but name a string field stay strings
+    config.apply_override("output.output_directory=2024").unwrap();
+    config.apply_override("gpu.adapter_name=1080").unwrap();
+    assert_eq!(config.output.output_directory, "2024");
+    assert_eq!(config.gpu.adapter_name.as_deref(), Some("1080"));
+
+    // Optional fields left out when the config is written can still be set
+    config.apply_override(r#"boundaries.inlet_waveform={"type": "table", "file": "pulse.csv"}"#).unwrap();
+    config.apply_override("boundaries.inlet_waveform.time_scale=0.001").unwrap();
+    config.apply_override("boundaries.inlet_waveform.time_scale=null").unwrap();
+    assert!(matches!(config.boundaries.inlet_waveform, Some(InletWaveform::Table { time_scale: None, .. })));
+
+    for (assignment, message) in [
+        ("domain.nxx=1", "Unknown config field"),
+        ("units.machh=1", "Unknown config field"),
+        ("physics.inlet_velocity.3=1", "Invalid index"),
+        ("domain.nx=many", "Invalid value"),
+        ("domain.nx=1.5", "invalid type: floating point"),
+        ("domain.nx", "expected path=value"),
+    ] {
+        let err = config.apply_override(assignment).unwrap_err().to_string();
+        assert!(err.contains(message), "{}: {}", assignment, err);
+    }
+    assert_eq!(config.domain.nx, 200, "failed overrides leave the config unchanged");
+}
+
+#[test]
+fn memory_estimate_follows_backend() {
+    let mut config = channel_config("./output");
+    let cpu = memory_estimate(&config);
+    assert_eq!(cpu.nodes, 800);
+    assert_eq!(cpu.backend_bytes, 2 * cpu.lattice_bytes);
+
+    // The GPU stores only the 9 distributions in use, plus 8 words per node
+    config.simulation.backend = BackendType::Gpu;
+    let gpu = memory_estimate(&config);
+    assert_eq!(gpu.lattice_bytes, 800 * (9 + 8) * 4);
+    assert!(gpu.lattice_bytes < cpu.lattice_bytes);
+    assert_eq!(gpu.host_bytes, cpu.host_bytes);
+}
+
+#[test]
+fn resumed_run_matches_uninterrupted_run() {
+    let directory = scratch("resume");
+    std::fs::create_dir_all(&directory).unwrap();
+    let body = directory.join("body.txt");
+    std::fs::write(&body, "0.012 0.008\n0.018 0.008\n0.015 0.013\n").unwrap();
+    let body = body.to_str().unwrap();
+
+    let run = |name: &str, max_iterations: usize, resume: bool| {
+        let output = directory.join(name);
+        let mut config = channel_config(output.to_str().unwrap());
+        config.simulation.max_iterations = max_iterations;
+        config.output.checkpoint_frequency = Some(10);
+        let mut solver = pollster::block_on(LBMSolver::new(config.clone(), body)).unwrap();
+        if resume {
+            solver.resume(output.join("checkpoint.bin").to_str().unwrap()).unwrap();
+        }
+        pollster::block_on(solver.run()).unwrap();
+        Checkpoint::read(output.join("checkpoint.bin").to_str().unwrap(), &config).unwrap()
+    };
+
+    let reference = run("reference", 20, false);
+    assert_eq!(run("interrupted", 10, false).iteration, 10);
+    let resumed = run("interrupted", 20, true);
+    let collection = |name: &str| std::fs::read_to_string(directory.join(name).join("simulation.pvd")).unwrap();
+    let (reference_pvd, resumed_pvd) = (collection("reference"), collection("interrupted"));
+    std::fs::remove_dir_all(&directory).ok();
+
+    // The resumed collection also lists the snapshots of the first segment
+    assert_eq!(resumed_pvd.matches("<DataSet").count(), 3);
+    assert_eq!(resumed_pvd, reference_pvd);
+
+    assert_eq!((reference.iteration, resumed.iteration), (20, 20));
+    assert_eq!(reference.dims, [40, 20, 1]);
+    assert_eq!(bytemuck::cast_slice::<_, u8>(&reference.lattice), bytemuck::cast_slice::<_, u8>(&resumed.lattice));
+
+    // Checkpoints only load into the grid and lattice they were written for
+    let mut config = channel_config("./output");
+    assert!(reference.check_compatible(&config).is_ok());
+    config.domain.ny = 21;
+    assert!(reference.check_compatible(&config).is_err());
+}
+
+#[test]
+fn solver_validates_the_config() {
+    let directory = scratch("invalid");
+    std::fs::create_dir_all(&directory).unwrap();
+    let body = directory.join("body.txt");
+    std::fs::write(&body, "0.012 0.008\n0.018 0.008\n0.015 0.013\n").unwrap();
+
+    let mut config = channel_config(directory.join("output").to_str().unwrap());
+    config.simulation.tau = Some(0.5);
+    let err = pollster::block_on(LBMSolver::new(config, body.to_str().unwrap())).err().unwrap().to_string();
+    std::fs::remove_dir_all(&directory).ok();
+    assert!(err.starts_with("Invalid configuration") && err.contains("simulation.tau"), "{}", err);
+}
+
+#[test]
+fn checkpoint_header_is_checked_before_reading() {
+    let directory = scratch("checkpoint");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("checkpoint.bin");
+    let path = path.to_str().unwrap();
+    let config = channel_config("./output");
+    let model = config.simulation.lattice.lattice();
+    let lattice = vec![LatticePoint::new_equilibrium(model, 1.0, [0.0; 3], 0); 800];
+    Checkpoint::new(&config, 7, lattice).write(path).unwrap();
+    assert_eq!(Checkpoint::read(path, &config).unwrap().iteration, 7);
+
+    let mut other = config.clone();
+    other.domain.nx = 41;
+    let err = Checkpoint::read(path, &other).unwrap_err().to_string();
+    assert!(err.contains("does not match the domain"), "{}", err);
+
+    // A huge grid in the header fails without allocating the lattice
+    let mut bytes = std::fs::read(path).unwrap();
+    bytes[8..16].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+    std::fs::write(path, &bytes).unwrap();
+    let mut huge = config.clone();
+    huge.domain.nx = usize::MAX / 2;
+    let err = Checkpoint::read(path, &huge).unwrap_err().to_string();
+    assert!(err.contains("corrupt header"), "{}", err);
+
+    // Truncated files are rejected from their length alone
+    bytes[8..16].copy_from_slice(&40u64.to_le_bytes());
+    bytes.truncate(bytes.len() - 1);
+    std::fs::write(path, &bytes).unwrap();
+    let err = Checkpoint::read(path, &config).unwrap_err().to_string();
+    std::fs::remove_dir_all(&directory).ok();
+    assert!(err.contains("expected"), "{}", err);
+}
+
+#[test]
+fn validate_and_convert_commands() {
+    let directory = scratch("commands");
+    std::fs::create_dir_all(&directory).unwrap();
+    let config_path = directory.join("config.json");
+    std::fs::write(&config_path, serde_json::to_string(&channel_config("./output")).unwrap()).unwrap();
+    let binary = env!("CARGO_BIN_EXE_lattice-boltzmann-rs");
+
+    let validate = |extra: &[&str]| {
+        std::process::Command::new(binary)
+            .arg("validate")
+            .arg(&config_path)
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+    let ok = validate(&[]);
+    assert!(ok.status.success(), "{}", String::from_utf8_lossy(&ok.stderr));
+    assert!(String::from_utf8_lossy(&ok.stdout).contains("OK"));
+    let failed = validate(&["--set", "simulation.tau=0.5"]);
+    assert!(!failed.status.success());
+    assert!(String::from_utf8_lossy(&failed.stdout).contains("error: simulation.tau"));
+
+    let converted = directory.join("converted.json");
+    let status = std::process::Command::new(binary)
+        .args(["convert", config_path.to_str().unwrap(), "-o", converted.to_str().unwrap()])
+        .args(["--set", "domain.ny=32", "--output-dir", "runs/b"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let config = Config::from_file(converted.to_str().unwrap()).unwrap();
+    std::fs::remove_dir_all(&directory).ok();
+    assert_eq!((config.domain.ny, config.output.output_directory.as_str()), (32, "runs/b"));
+}
@@ -0,0 +1,260 @@
+use lattice_boltzmann_rs::{
+    collision::{CollisionOperator, CumulantRates, MomentGroup, MrtRates},
+    config::{CollisionModel, LatticeType, LesModel, TrtConfig},
+    turbulence::{non_equilibrium_flux_norm, velocity_gradient, vreman_viscosity, wale_viscosity, SubgridModel},
+    Lattice, LatticePoint,
+};
+
+mod common;
+
+use common::test_config;
+
+const LATTICES: [LatticeType; 4] = [LatticeType::D2Q9, LatticeType::D3Q15, LatticeType::D3Q19, LatticeType::D3Q27];
+
+/// Equilibrium at a moving state with a deterministic non-equilibrium perturbation
+fn perturbed_point(model: &dyn Lattice) -> LatticePoint {
+    let mut point = LatticePoint::new_equilibrium(model, 1.02, [0.04, -0.02, 0.01], 0);
+    for i in 0..model.q() {
+        point.f[i] *= 1.0 + 0.05 * ((i * 7 % 5) as f32 - 2.0) / 2.0;
+    }
+    point.calculate_macroscopic(model);
+    point
+}
+
+#[test]
+fn moment_basis_round_trip() {
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+        let basis = model.moment_basis();
+        let point = perturbed_point(model);
+
+        let moments = basis.to_moments(&point.f[..model.q()]);
+        let f = basis.to_populations(&moments[..model.q()]);
+        for (i, (a, b)) in f.iter().zip(&point.f).take(model.q()).enumerate() {
+            assert!((a - b).abs() < 1e-6, "{}: f[{}] {} != {}", model.name(), i, a, b);
+        }
+
+        let groups = basis.groups();
+        let conserved = groups.iter().filter(|&&g| g == MomentGroup::Conserved).count();
+        let shear = groups.iter().filter(|&&g| g == MomentGroup::Shear).count();
+        let dims = model.dimensions();
+        assert_eq!(conserved, 1 + dims, "{}", model.name());
+        assert_eq!(shear, dims * (dims + 1) / 2 - 1, "{}", model.name());
+    }
+}
+
+#[test]
+fn mrt_with_equal_rates_matches_bgk() {
+    let tau = 0.7;
+    let rates = MrtRates { shear: 1.0 / tau, bulk: 1.0 / tau, ghost: 1.0 / tau };
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+        let mut bgk = perturbed_point(model);
+        let mut mrt = bgk;
+
+        bgk.collide(model, tau);
+        mrt.collide_mrt(model, &rates);
+        for (i, (a, b)) in bgk.f.iter().zip(&mrt.f).take(model.q()).enumerate() {
+            assert!((a - b).abs() < 1e-6, "{}: f[{}] {} != {}", model.name(), i, a, b);
+        }
+    }
+}
+
+#[test]
+fn mrt_conserves_mass_and_momentum() {
+    let rates = MrtRates { shear: 1.6, bulk: 1.1, ghost: 1.3 };
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+        let before = perturbed_point(model);
+        let mut after = before;
+        after.collide_mrt(model, &rates);
+        after.calculate_macroscopic(model);
+
+        assert!((after.density - before.density).abs() < 1e-6, "{}", model.name());
+        for a in 0..3 {
+            assert!((after.velocity[a] - before.velocity[a]).abs() < 1e-6, "{}", model.name());
+        }
+    }
+}
+
+#[test]
+fn trt_with_symmetric_magic_parameter_matches_bgk() {
+    // Λ = (tau - 1/2)^2 makes the antisymmetric rate equal to 1/tau
+    let tau = 0.8;
+    let rates = TrtConfig { magic_parameter: (tau - 0.5) * (tau - 0.5) }.rates(tau).unwrap();
+    assert!((rates.plus - rates.minus).abs() < 1e-6);
+
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+        let mut bgk = perturbed_point(model);
+        let mut trt = bgk;
+
+        bgk.collide(model, tau);
+        trt.collide_trt(model, &rates);
+        for (i, (a, b)) in bgk.f.iter().zip(&trt.f).take(model.q()).enumerate() {
+            assert!((a - b).abs() < 1e-6, "{}: f[{}] {} != {}", model.name(), i, a, b);
+        }
+    }
+}
+
+#[test]
+fn trt_conserves_mass_and_momentum() {
+    for magic_parameter in [0.25, 3.0 / 16.0] {
+        let rates = TrtConfig { magic_parameter }.rates(0.6).unwrap();
+        for lattice_type in LATTICES {
+            let model = lattice_type.lattice();
+            let before = perturbed_point(model);
+            let mut after = before;
+            after.collide_trt(model, &rates);
+            after.calculate_macroscopic(model);
+
+            assert!((after.density - before.density).abs() < 1e-6, "{}", model.name());
+            for a in 0..3 {
+                assert!((after.velocity[a] - before.velocity[a]).abs() < 1e-6, "{}", model.name());
+            }
+        }
+    }
+}
+
+#[test]
+fn trt_rejects_invalid_magic_parameter() {
+    let err = TrtConfig { magic_parameter: 0.0 }.rates(0.8).unwrap_err().to_string();
+    assert!(err.contains("1/4") && err.contains("3/16"), "{}", err);
+}
+
+const CUMULANT_RATES: CumulantRates = CumulantRates {
+    shear: 1.5,
+    bulk: 1.2,
+    third: 1.1,
+    fourth: 0.9,
+    fifth: 1.3,
+    sixth: 1.0,
+};
+
+#[test]
+fn cumulant_conserves_mass_and_momentum() {
+    let model = LatticeType::D3Q27.lattice();
+    let before = perturbed_point(model);
+    let mut after = before;
+    after.collide_cumulant(model, &CUMULANT_RATES);
+    after.calculate_macroscopic(model);
+
+    assert!((after.density - before.density).abs() < 1e-6);
+    for a in 0..3 {
+        assert!((after.velocity[a] - before.velocity[a]).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn cumulant_preserves_factorized_equilibrium() {
+    // Product of the 1D equilibria, whose cumulants above second order vanish
+    let model = LatticeType::D3Q27.lattice();
+    let (density, velocity) = (1.05, [0.12, -0.07, 0.04]);
+    let phi = |c: i32, u: f32| match c {
+        0 => 2.0 / 3.0 - u * u,
+        _ => 0.5 * (1.0 / 3.0 + u * u + c as f32 * u),
+    };
+
+    let mut point = LatticePoint::new_equilibrium(model, density, velocity, 0);
+    for (f, c) in point.f.iter_mut().zip(model.velocities()) {
+        *f = density * phi(c[0], velocity[0]) * phi(c[1], velocity[1]) * phi(c[2], velocity[2]);
+    }
+    point.calculate_macroscopic(model);
+    let before = point;
+
+    point.collide_cumulant(model, &CUMULANT_RATES);
+    for (i, (a, b)) in point.f.iter().zip(&before.f).enumerate() {
+        assert!((a - b).abs() < 1e-6, "f[{}] {} != {}", i, a, b);
+    }
+}
+
+#[test]
+fn cumulant_requires_d3q27() {
+    let mut config = test_config(4, 4, 4);
+    config.simulation.collision_model = serde_json::from_value(serde_json::json!("cumulant")).unwrap();
+    assert_eq!(config.simulation.collision_model, CollisionModel::Cumulant);
+    assert!(CollisionOperator::from_config(&config).is_ok());
+
+    config.simulation.lattice = LatticeType::D3Q19;
+    assert!(CollisionOperator::from_config(&config).is_err());
+}
+
+#[test]
+fn smagorinsky_eddy_viscosity_matches_strain_rate() {
+    let les = SubgridModel { model: LesModel::Smagorinsky, constant: 0.17 };
+    let tau0 = 0.52;
+    for lattice_type in LATTICES {
+        let model = lattice_type.lattice();
+
+        // No strain at equilibrium
+        let equilibrium = LatticePoint::new_equilibrium(model, 1.0, [0.05, 0.02, 0.0], 0);
+        assert!((les.effective_tau(model, &equilibrium, &[[0.0; 3]; 3], tau0) - tau0).abs() < 1e-5, "{}", model.name());
+
+        // nu_t = (Cs)^2 |S| with |S| = 3 |Pi| / (sqrt(2) rho tau)
+        let point = perturbed_point(model);
+        let tau = les.effective_tau(model, &point, &[[0.0; 3]; 3], tau0);
+        let strain = 3.0 * non_equilibrium_flux_norm(model, &point) / (2.0f32.sqrt() * point.density * tau);
+        let nu_t = (tau - tau0) * model.cs2();
+        assert!(tau > tau0, "{}", model.name());
+        assert!((nu_t - 0.17 * 0.17 * strain).abs() < 1e-5, "{}: {} != {}", model.name(), nu_t, 0.17 * 0.17 * strain);
+    }
+}
+
+#[test]
+fn velocity_gradient_avoids_solid_nodes() {
+    // Linear shear u_x = 0.01 y above a solid floor at y = 0
+    let model = LatticeType::D3Q19.lattice();
+    let dims = [4, 5, 3];
+    let nodes: Vec<LatticePoint> = (0..dims[0] * dims[1] * dims[2])
+        .map(|idx| {
+            let y = (idx / dims[0]) % dims[1];
+            let node_type = if y == 0 { 1 } else { 0 };
+            let velocity = if y == 0 { [0.5, 0.0, 0.0] } else { [0.01 * y as f32, 0.0, 0.0] };
+            LatticePoint::new_equilibrium(model, 1.0, velocity, node_type)
+        })
+        .collect();
+
+    for y in 1..dims[1] {
+        let idx = 1 + y * dims[0] + dims[0] * dims[1];
+        let gradient = velocity_gradient(model, &nodes, idx, nodes[idx].velocity, dims, [false; 3]);
+        for (a, row) in gradient.iter().enumerate() {
+            for (b, &value) in row.iter().enumerate() {
+                let expected = if (a, b) == (0, 1) { 0.01 } else { 0.0 };
+                assert!((value - expected).abs() < 1e-6, "y = {}: g[{}][{}] = {}", y, a, b, value);
+            }
+        }
+    }
+}
+
+#[test]
+fn wale_and_vreman_vanish_for_pure_shear() {
+    let shear = [[0.0, 0.02, 0.0], [0.0; 3], [0.0; 3]];
+    assert_eq!(wale_viscosity(&shear, 0.5), 0.0);
+    assert_eq!(vreman_viscosity(&shear, 0.07), 0.0);
+    assert_eq!(wale_viscosity(&[[0.0; 3]; 3], 0.5), 0.0);
+    assert_eq!(vreman_viscosity(&[[0.0; 3]; 3], 0.07), 0.0);
+}
+
+#[test]
+fn gradient_models_raise_tau_for_strain_and_rotation() {
+    // Plane strain: Vreman gives c |a| / sqrt(2)
+    let a = 0.01;
+    let strain = [[a, 0.0, 0.0], [0.0, -a, 0.0], [0.0; 3]];
+    let expected = 0.07 * a / 2.0f32.sqrt();
+    assert!((vreman_viscosity(&strain, 0.07) - expected).abs() < 1e-7);
+
+    let gradient = [[0.01, 0.02, 0.0], [-0.01, -0.01, 0.03], [0.01, 0.0, 0.0]];
+    let model = LatticeType::D3Q27.lattice();
+    let point = LatticePoint::new_equilibrium(model, 1.0, [0.05, 0.0, 0.0], 0);
+    let tau0 = 0.52;
+    for (les_model, constant, nu_t) in [
+        (LesModel::Wale, 0.5, wale_viscosity(&gradient, 0.5)),
+        (LesModel::Vreman, 0.07, vreman_viscosity(&gradient, 0.07)),
+    ] {
+        assert!(nu_t > 0.0, "{:?}", les_model);
+        let les = SubgridModel { model: les_model, constant };
+        assert!(les.uses_velocity_gradient());
+        let tau = les.effective_tau(model, &point, &gradient, tau0);
+        assert!((tau - tau0 - nu_t / model.cs2()).abs() < 1e-6, "{:?}", les_model);
+    }
+}
@@ -0,0 +1,68 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)] // Each test crate uses a subset
+
+use lattice_boltzmann_rs::{config::LatticeType, Config};
+
+/// Config of an nx × ny × nz domain in lattice units: dx = 1, tau = 0.8,
+/// Re = 100, density 1, inlet velocity 0.05 along x, one iteration and the
+/// default lattice and boundaries
+pub fn test_config(nx: usize, ny: usize, nz: usize) -> Config {
+    serde_json::from_value(serde_json::json!({
+        "domain": { "nx": nx, "ny": ny, "nz": nz, "dx": 1.0, "dy": 1.0, "dz": 1.0 },
+        "physics": { "reynolds_number": 100.0, "inlet_velocity": [0.05, 0.0, 0.0], "density": 1.0, "viscosity": null },
+        "simulation": { "max_iterations": 1, "convergence_tolerance": 1e-6, "tau": 0.8 },
+        "output": { "output_directory": "./output", "output_frequency": 1, "output_format": "vtk" }
+    }))
+    .unwrap()
+}
+
+/// Chained setters for the fields the tests vary most
+pub trait TestConfig {
+    fn lattice(self, lattice: LatticeType) -> Self;
+    /// Grid spacing along every axis
+    fn spacing(self, dx: f32) -> Self;
+    fn inlet_velocity(self, velocity: [f32; 3]) -> Self;
+    fn tau(self, tau: Option<f32>) -> Self;
+    /// `boundaries` section, as in a config file
+    fn boundaries(self, boundaries: serde_json::Value) -> Self;
+    /// `units` section, as in a config file
+    fn units(self, units: serde_json::Value) -> Self;
+    fn output_directory(self, directory: &str) -> Self;
+}
+
+impl TestConfig for Config {
+    fn lattice(mut self, lattice: LatticeType) -> Self {
+        self.simulation.lattice = lattice;
+        self
+    }
+
+    fn spacing(mut self, dx: f32) -> Self {
+        (self.domain.dx, self.domain.dy, self.domain.dz) = (dx, dx, dx);
+        self
+    }
+
+    fn inlet_velocity(mut self, velocity: [f32; 3]) -> Self {
+        self.physics.inlet_velocity = velocity;
+        self
+    }
+
+    fn tau(mut self, tau: Option<f32>) -> Self {
+        self.simulation.tau = tau;
+        self
+    }
+
+    fn boundaries(mut self, boundaries: serde_json::Value) -> Self {
+        self.boundaries = serde_json::from_value(boundaries).unwrap();
+        self
+    }
+
+    fn units(mut self, units: serde_json::Value) -> Self {
+        self.units = serde_json::from_value(units).unwrap();
+        self
+    }
+
+    fn output_directory(mut self, directory: &str) -> Self {
+        self.output.output_directory = directory.to_string();
+        self
+    }
+}
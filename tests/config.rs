@@ -0,0 +1,176 @@
+use lattice_boltzmann_rs::{
+    config::{ConfigFormat, FaceBoundary, InletProfile, InletWaveform, LatticeType},
+    Config,
+};
+
+const TOML_CONFIG: &str = r#"
+[domain]
+nx = 40
+ny = 20
+nz = 1
+dx = 0.001
+dy = 0.001
+dz = 0.001
+
+[physics]
+reynolds_number = 50
+inlet_velocity = [0.05, 0.0, 0.0]
+density = 1.0
+
+[simulation]
+max_iterations = 100
+convergence_tolerance = 1e-6
+lattice = "D2Q9"
+
+[output]
+output_directory = "./output"
+output_frequency = 10
+output_format = "vtk"
+
+[boundaries]
+y_min = { type = "no_slip" }
+y_max = { type = "free_slip" }
+"#;
+
+const YAML_CONFIG: &str = r#"
+domain: { nx: 40, ny: 20, nz: 1, dx: 0.001, dy: 0.001, dz: 0.001 }
+physics:
+  reynolds_number: 50
+  inlet_velocity: [0.05, 0.0, 0.0]
+  density: 1.0
+simulation:
+  max_iterations: 100
+  convergence_tolerance: 1.0e-6
+  lattice: D2Q9
+output:
+  output_directory: ./output
+  output_frequency: 10
+  output_format: vtk
+boundaries:
+  y_min: { type: no_slip }
+  y_max: { type: free_slip }
+"#;
+
+fn toml_config() -> Config {
+    Config::parse(TOML_CONFIG, ConfigFormat::Toml).unwrap()
+}
+
+#[test]
+fn formats_are_chosen_by_extension() {
+    assert_eq!(ConfigFormat::from_path("vortex_config.json"), ConfigFormat::Json);
+    assert_eq!(ConfigFormat::from_path("runs/channel.TOML"), ConfigFormat::Toml);
+    assert_eq!(ConfigFormat::from_path("channel.yml"), ConfigFormat::Yaml);
+    assert_eq!(ConfigFormat::from_path("channel.yaml"), ConfigFormat::Yaml);
+    assert_eq!(ConfigFormat::from_path("channel"), ConfigFormat::Json);
+
+    let config = toml_config();
+    assert_eq!(config.simulation.lattice, LatticeType::D2Q9);
+    assert_eq!(config.physics.viscosity, None, "optional fields may be left out");
+    assert_eq!(config.boundaries.y_max, Some(FaceBoundary::FreeSlip));
+    let yaml = Config::parse(YAML_CONFIG, ConfigFormat::Yaml).unwrap();
+    assert_eq!(serde_json::to_value(&yaml).unwrap(), serde_json::to_value(&config).unwrap());
+
+    // Written configs read back the same in every format
+    let directory = std::env::temp_dir().join(format!("lbm-config-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    for extension in ["json", "toml", "yaml"] {
+        let path = directory.join(format!("channel.{}", extension));
+        let path = path.to_str().unwrap();
+        config.to_file(path).unwrap();
+        let written = Config::from_file(path).unwrap();
+        assert_eq!(serde_json::to_value(&written).unwrap(), serde_json::to_value(&config).unwrap(), "{}", extension);
+    }
+    std::fs::remove_dir_all(&directory).ok();
+}
+
+#[test]
+fn errors_name_the_field_path() {
+    let mut value = serde_json::to_value(toml_config()).unwrap();
+    value["physics"]["inlet_velocity"][1] = serde_json::json!("fast");
+    let err = Config::parse(&value.to_string(), ConfigFormat::Json).unwrap_err().to_string();
+    assert!(err.starts_with("physics.inlet_velocity[1]: invalid type"), "{}", err);
+
+    let err = Config::parse(&TOML_CONFIG.replace("density = 1.0", ""), ConfigFormat::Toml)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "physics: missing field `density`");
+
+    let err = Config::parse(&TOML_CONFIG.replace("density", "densty = 1.0\ndensity"), ConfigFormat::Toml)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("unknown field `physics.densty` (expected one of:"), "{}", err);
+    assert!(err.contains("reynolds_number"), "{}", err);
+
+    let err = Config::parse(&YAML_CONFIG.replace("{ type: no_slip }", "{ type: no_slip, speed: 1 }"), ConfigFormat::Yaml)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("`boundaries.y_min.speed`"), "{}", err);
+
+    let err = Config::parse(&YAML_CONFIG.replace("{ type: no_slip }", "{ type: moving_wall, velocity: [0, 0, 0], speed: 1 }"), ConfigFormat::Yaml)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("`boundaries.y_min.speed` (expected velocity)"), "{}", err);
+
+    // Editors' schema reference is not a config field
+    let mut value = serde_json::to_value(toml_config()).unwrap();
+    value["$schema"] = serde_json::json!("./config.schema.json");
+    assert!(Config::parse(&value.to_string(), ConfigFormat::Json).is_ok());
+}
+
+#[test]
+fn explicit_null_optional_fields_are_accepted() {
+    let mut value = serde_json::to_value(toml_config()).unwrap();
+    value["boundaries"]["inlet_waveform"] = serde_json::json!({ "type": "table", "file": "pulse.csv", "time_scale": null });
+    value["physics"]["characteristic_length"] = serde_json::Value::Null;
+    let config = Config::parse(&value.to_string(), ConfigFormat::Json).unwrap();
+    assert_eq!(
+        config.boundaries.inlet_waveform,
+        Some(InletWaveform::Table { file: "pulse.csv".into(), repeat: false, time_scale: None })
+    );
+    assert!(config.physics.characteristic_length.is_none());
+}
+
+#[test]
+fn input_files_are_relative_to_the_config() {
+    let directory = std::env::temp_dir().join(format!("lbm-config-paths-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let mut config = toml_config();
+    config.boundaries.inlet_waveform = Some(InletWaveform::Table { file: "pulse.csv".into(), repeat: false, time_scale: None });
+    config.boundaries.inlet_profile = Some(InletProfile::File { file: "/data/profile.csv".into() });
+    let path = directory.join("channel.toml");
+    config.to_file(path.to_str().unwrap()).unwrap();
+    let loaded = Config::from_file(path.to_str().unwrap()).unwrap();
+    std::fs::remove_dir_all(&directory).ok();
+
+    let Some(InletWaveform::Table { file, .. }) = loaded.boundaries.inlet_waveform else { panic!("table") };
+    assert_eq!(std::path::PathBuf::from(file), directory.join("pulse.csv"));
+    assert_eq!(loaded.boundaries.inlet_profile, Some(InletProfile::File { file: "/data/profile.csv".into() }), "absolute paths are kept");
+}
+
+#[test]
+fn repository_configs_load() {
+    for path in [
+        "vortex_config.json",
+        "ultra_high_res_config.json",
+        "examples/example_config.json",
+        "examples/example_2d_config.json",
+    ] {
+        let config = Config::from_file(path).unwrap_or_else(|e| panic!("{}", e));
+        let validation = config.validate(None);
+        assert!(!validation.has_errors(), "{}: {:?}", path, validation.issues);
+    }
+}
+
+#[test]
+fn committed_schema_is_current() {
+    let schema = Config::json_schema();
+    for section in ["domain", "physics", "units", "simulation", "output", "boundaries", "gpu"] {
+        assert!(schema["properties"][section].is_object(), "{}", section);
+    }
+    assert_eq!(schema["required"], serde_json::json!(["domain", "physics", "simulation", "output"]));
+
+    // Regenerate with `lattice-boltzmann-rs schema -o config.schema.json`
+    let committed: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("config.schema.json").unwrap()).unwrap();
+    assert_eq!(committed, schema, "config.schema.json is out of date");
+}
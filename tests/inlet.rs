@@ -0,0 +1,219 @@
+use lattice_boltzmann_rs::{
+    config::{CrossSection, FaceBoundary, InletProfile, InletScheme, InletWaveform, LatticeType},
+    inlet::{inlet_profile, Waveform},
+    Config, CPUContext, LatticePoint,
+};
+
+mod common;
+
+use common::{test_config, TestConfig};
+
+/// 6 × 4 channel, periodic in y, with a Zou-He inlet at x_min following `waveform`
+fn channel_config(waveform: InletWaveform) -> Config {
+    let mut config = test_config(6, 4, 1).lattice(LatticeType::D2Q9).inlet_velocity([0.04, 0.01, 0.0]).boundaries(serde_json::json!({
+        "x_min": { "type": "velocity_inlet", "scheme": "zou_he" },
+        "y_min": { "type": "periodic" },
+        "y_max": { "type": "periodic" }
+    }));
+    config.boundaries.inlet_waveform = Some(waveform);
+    config
+}
+
+/// Zou-He inlet nodes at x = 0 and zero-gradient outlet nodes at x = nx - 1
+fn channel_lattice(config: &Config) -> Vec<LatticePoint> {
+    let model = config.simulation.lattice.lattice();
+    let nx = config.domain.nx;
+    (0..nx * config.domain.ny * config.domain.nz)
+        .map(|idx| {
+            let mut point = LatticePoint::new_equilibrium(model, 1.0, [0.0; 3], 0);
+            if idx % nx == 0 {
+                (point.node_type, point.boundary, point.face) = (2, InletScheme::ZouHe as u32, 0);
+            } else if idx % nx == nx - 1 {
+                (point.node_type, point.face) = (3, 1);
+            }
+            point
+        })
+        .collect()
+}
+
+#[test]
+fn ramp_and_sinusoid_factors() {
+    let config = channel_config(InletWaveform::Ramp { duration: 100.0 });
+    let ramp = Waveform::from_config(&config).unwrap();
+    assert_eq!(ramp.factor(0), 0.0);
+    assert!((ramp.factor(25) - 0.25).abs() < 1e-6);
+    assert_eq!(ramp.factor(100), 1.0);
+    assert_eq!(ramp.factor(1000), 1.0);
+
+    let config = channel_config(InletWaveform::Sinusoidal { period: 40.0, amplitude: 0.5, phase: 0.0 });
+    let pulse = Waveform::from_config(&config).unwrap();
+    assert!((pulse.factor(0) - 1.0).abs() < 1e-6);
+    assert!((pulse.factor(10) - 1.5).abs() < 1e-6);
+    assert!((pulse.factor(30) - 0.5).abs() < 1e-6);
+    assert!((pulse.factor(50) - pulse.factor(10)).abs() < 1e-5, "periodic");
+
+    let config = channel_config(InletWaveform::Sinusoidal { period: 0.0, amplitude: 0.5, phase: 0.0 });
+    assert!(Waveform::from_config(&config).is_err());
+    assert!(Waveform::Constant.is_constant() && !ramp.is_constant());
+}
+
+#[test]
+fn table_interpolates_csv_samples() {
+    let samples = Waveform::parse_table("time,factor\n# start-up\n0, 0.0\n10, 1.0\n30 0.5\n", "pulse.csv").unwrap();
+    assert_eq!(samples, vec![(0.0, 0.0), (10.0, 1.0), (30.0, 0.5)]);
+
+    let table = Waveform::Table { samples: samples.clone(), repeat: false };
+    assert!((table.factor(5) - 0.5).abs() < 1e-6);
+    assert!((table.factor(20) - 0.75).abs() < 1e-6);
+    assert_eq!(table.factor(100), 0.5, "held after the last sample");
+
+    let repeated = Waveform::Table { samples, repeat: true };
+    assert!((repeated.factor(35) - 0.5).abs() < 1e-6, "t = 35 maps to t = 5");
+
+    let err = Waveform::parse_table("0 1\n0 2\n", "pulse.csv").unwrap_err().to_string();
+    assert!(err.contains("pulse.csv:2") && err.contains("increase"), "{}", err);
+    let err = Waveform::parse_table("# pulse\n0 1\n5 1 2\n", "pulse.csv").unwrap_err().to_string();
+    assert_eq!(err, "pulse.csv:3: expected 'time, factor'");
+    assert!(Waveform::parse_table("0 1\n5 x\n", "pulse.csv").is_err());
+    assert!(Waveform::parse_table("# empty\n", "pulse.csv").is_err());
+}
+
+#[test]
+fn waveform_scales_only_velocity_inlets() {
+    let mut config = channel_config(InletWaveform::Ramp { duration: 10.0 });
+    config.boundaries.y_min = Some(FaceBoundary::MovingWall { velocity: [0.02, 0.0, 0.0] });
+    config.boundaries.y_max = Some(FaceBoundary::PressureOutlet { density: Some(1.01) });
+    let waveform = Waveform::from_config(&config).unwrap();
+
+    let values = waveform.face_values(&config, 5);
+    assert_eq!(values[0], [0.02, 0.005, 0.0, 0.5], "velocity and profile factor");
+    assert_eq!(values[2], [0.02, 0.0, 0.0, 0.0], "moving wall");
+    assert_eq!(values[3], [0.0, 0.0, 0.0, 1.01], "outlet density");
+}
+
+#[test]
+fn cpu_inlet_follows_ramp() {
+    let config = channel_config(InletWaveform::Ramp { duration: 20.0 });
+    let waveform = Waveform::from_config(&config).unwrap();
+    let nx = config.domain.nx;
+    let lattice = channel_lattice(&config);
+
+    let mut cpu = CPUContext::new(&config).unwrap();
+    cpu.upload_lattice_data(&lattice).unwrap();
+    for step in 1..=30 {
+        cpu.update_face_values(&waveform.face_values(&config, step));
+        cpu.step().unwrap();
+
+        let inlet = cpu.read_lattice_data()[2 * nx];
+        let expected = 0.04 * waveform.factor(step);
+        assert!((inlet.velocity[0] - expected).abs() < 1e-6, "step {}: u = {} != {}", step, inlet.velocity[0], expected);
+    }
+}
+
+#[test]
+fn parabolic_and_power_law_profiles() {
+    let mut config = channel_config(InletWaveform::Ramp { duration: 1.0 });
+    let lattice = channel_lattice(&config);
+    let nx = config.domain.nx;
+    assert!(inlet_profile(&config, &lattice).unwrap().is_none(), "uniform by default");
+
+    // Four inlet nodes, walls half-way beyond them: r = 0.75 and 0.25
+    config.boundaries.inlet_profile = Some(InletProfile::Parabolic { cross_section: CrossSection::Rectangular });
+    let profile = inlet_profile(&config, &lattice).unwrap().unwrap();
+    let expected = [0.4375, 0.9375, 0.9375, 0.4375];
+    for (j, shape) in expected.iter().enumerate() {
+        let [u, v, w] = profile[j * nx];
+        assert!((u - 0.04 * shape).abs() < 1e-7 && (v - 0.01 * shape).abs() < 1e-7 && w == 0.0, "y = {}: {:?}", j, profile[j * nx]);
+        assert_eq!(profile[j * nx + 2], [0.0; 3], "interior node");
+    }
+
+    config.boundaries.inlet_profile = Some(InletProfile::PowerLaw { cross_section: CrossSection::Rectangular, exponent: 7.0 });
+    let profile = inlet_profile(&config, &lattice).unwrap().unwrap();
+    assert!((profile[0][0] - 0.04 * 0.25f32.powf(1.0 / 7.0)).abs() < 1e-7);
+    assert!((profile[nx][0] - 0.04 * 0.75f32.powf(1.0 / 7.0)).abs() < 1e-7);
+}
+
+#[test]
+fn circular_profile_is_radial() {
+    let mut config = channel_config(InletWaveform::Ramp { duration: 1.0 });
+    (config.domain.nx, config.domain.ny, config.domain.nz) = (2, 5, 5);
+    config.simulation.lattice = LatticeType::D3Q19;
+    config.boundaries.y_min = None;
+    config.boundaries.y_max = None;
+    let lattice = channel_lattice(&config);
+    let node = |j: usize, k: usize| (k * 5 + j) * 2;
+
+    config.boundaries.inlet_profile = Some(InletProfile::Parabolic { cross_section: CrossSection::Circular });
+    let profile = inlet_profile(&config, &lattice).unwrap().unwrap();
+    assert!((profile[node(2, 2)][0] - 0.04).abs() < 1e-7, "centreline");
+    assert!((profile[node(0, 2)][0] - 0.04 * 0.36).abs() < 1e-7, "r = 0.8");
+    assert!((profile[node(4, 2)][0] - profile[node(2, 0)][0]).abs() < 1e-7, "axisymmetric");
+    assert_eq!(profile[node(0, 0)][0], 0.0, "corner outside the circle");
+
+    config.boundaries.inlet_profile = Some(InletProfile::Parabolic { cross_section: CrossSection::Rectangular });
+    let profile = inlet_profile(&config, &lattice).unwrap().unwrap();
+    assert!((profile[node(0, 0)][0] - 0.04 * 0.36 * 0.36).abs() < 1e-7, "product across the corner");
+}
+
+#[test]
+fn file_profile_maps_nearest_samples() {
+    let mut config = channel_config(InletWaveform::Ramp { duration: 1.0 });
+    let lattice = channel_lattice(&config);
+    let nx = config.domain.nx;
+    let path = |extension: &str| std::env::temp_dir().join(format!("lbm-profile-{}-{:?}.{}", std::process::id(), std::thread::current().id(), extension));
+
+    // CSV samples in the (y, z) plane of the x_min face; node j lies at the
+    // cell centre y = j + 0.5, so the node at 1.5 is nearer to the sample at 2
+    let csv = path("csv");
+    std::fs::write(&csv, "y,z,u,v,w\n0.4 0 0.01 0 0\n2, 0, 0.03, 0.001, 0\n").unwrap();
+    config.boundaries.inlet_profile = Some(InletProfile::File { file: csv.to_str().unwrap().to_string() });
+    let profile = inlet_profile(&config, &lattice).unwrap().unwrap();
+    std::fs::remove_file(&csv).ok();
+    let u: Vec<f32> = (0..4).map(|j| profile[j * nx][0]).collect();
+    assert_eq!(u, [0.01, 0.03, 0.03, 0.03]);
+    assert_eq!(profile[3 * nx][1], 0.001);
+
+    // VTK points: the velocity field is picked by name and, at equal in-plane
+    // distance, the sample nearest to the inlet plane wins
+    let vtk = path("vtk");
+    std::fs::write(
+        &vtk,
+        "# vtk DataFile Version 3.0\nslice\nASCII\nDATASET UNSTRUCTURED_GRID\nPOINTS 3 float\n\
+         5 0.2 0\n0 0.2 0\n0 3 0\nPOINT_DATA 3\nVECTORS vorticity float\n9 9 9\n9 9 9\n9 9 9\n\
+         VECTORS velocity float\n0.5 0 0\n0.02 0 0\n0.04 0 0\n",
+    )
+    .unwrap();
+    config.boundaries.inlet_profile = Some(InletProfile::File { file: vtk.to_str().unwrap().to_string() });
+    let profile = inlet_profile(&config, &lattice).unwrap().unwrap();
+    std::fs::remove_file(&vtk).ok();
+    let u: Vec<f32> = (0..4).map(|j| profile[j * nx][0]).collect();
+    assert_eq!(u, [0.02, 0.02, 0.04, 0.04]);
+
+    config.boundaries.inlet_profile = Some(InletProfile::File { file: "missing-profile.csv".to_string() });
+    assert!(inlet_profile(&config, &lattice).is_err());
+}
+
+#[test]
+fn cpu_inlet_follows_profile() {
+    let mut config = channel_config(InletWaveform::Ramp { duration: 10.0 });
+    config.boundaries.inlet_profile = Some(InletProfile::Parabolic { cross_section: CrossSection::Rectangular });
+    let waveform = Waveform::from_config(&config).unwrap();
+    let nx = config.domain.nx;
+    let lattice = channel_lattice(&config);
+    let profile = inlet_profile(&config, &lattice).unwrap().unwrap();
+
+    let mut cpu = CPUContext::new(&config).unwrap();
+    cpu.upload_lattice_data(&lattice).unwrap();
+    cpu.upload_inlet_profile(&profile).unwrap();
+    for step in 1..=15 {
+        cpu.update_face_values(&waveform.face_values(&config, step));
+        cpu.step().unwrap();
+
+        let points = cpu.read_lattice_data();
+        for j in 0..4 {
+            let expected = profile[j * nx][0] * waveform.factor(step);
+            let u = points[j * nx].velocity[0];
+            assert!((u - expected).abs() < 1e-6, "step {}, y = {}: u = {} != {}", step, j, u, expected);
+        }
+    }
+}
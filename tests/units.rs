@@ -0,0 +1,231 @@
+use lattice_boltzmann_rs::{
+    config::{Axis, CharacteristicLength, FaceBoundary, InletWaveform, LatticeType},
+    inlet::Waveform,
+    units::{UnitConverter, DEFAULT_MACH},
+    Config, LatticePoint, VTKWriter,
+};
+
+mod common;
+
+use common::{test_config, TestConfig};
+
+/// Air at 1 m/s past a 0.1 m body on a 1 mm grid
+fn air_config(units: serde_json::Value, tau: Option<f32>) -> Config {
+    let mut config = test_config(4, 3, 1)
+        .spacing(0.001)
+        .lattice(LatticeType::D2Q9)
+        .inlet_velocity([1.0, 0.0, 0.0])
+        .tau(tau)
+        .units(units)
+        .boundaries(serde_json::json!({
+            "outlet": "pressure",
+            "outlet_density": 1.2,
+            "y_min": { "type": "no_slip" },
+            "y_max": { "type": "moving_wall", "velocity": [0.5, 0.0, 0.0] }
+        }));
+    (config.physics.density, config.physics.viscosity) = (1.2, Some(1.5e-5));
+    config
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() <= 1e-4 * b.abs()
+}
+
+#[test]
+fn mach_number_sets_time_step() {
+    let config = air_config(serde_json::json!({ "length": 0.1, "mach": 0.1 }), None);
+    let units = UnitConverter::from_config(&config).unwrap();
+    assert!(units.is_physical());
+
+    // u_lb = Ma cs, dt = u_lb dx / U, ν_lb = ν dt / dx²
+    let u_lb = 0.1 / 3.0f32.sqrt();
+    assert!(close(units.dt, u_lb * 0.001), "dt = {}", units.dt);
+    assert!(close(units.tau, 3.0 * 1.5e-5 * units.dt / 1e-6 + 0.5), "tau = {}", units.tau);
+    assert!(close(units.reynolds_number, 1.0 * 0.1 / 1.5e-5));
+    assert!(close(units.velocity(), 1.0 / u_lb));
+    assert!(close(units.pressure(), 1.2 / (u_lb * u_lb)));
+    assert!(close(units.time(1000) as f32, 1000.0 * units.dt));
+
+    let lattice = units.lattice_config(&config);
+    assert!(lattice.units.is_none());
+    assert!(close(lattice.physics.inlet_velocity[0], u_lb));
+    assert_eq!(lattice.physics.density, 1.0);
+    assert_eq!(lattice.boundaries.outlet_density, Some(1.0));
+    assert_eq!(lattice.simulation.tau, Some(units.tau));
+    assert!(close(lattice.calculate_tau(), units.tau));
+    let Some(FaceBoundary::MovingWall { velocity }) = lattice.boundaries.y_max else { panic!("moving wall") };
+    assert!(close(velocity[0], 0.5 * u_lb));
+    assert_eq!(lattice.domain.dx, 0.001, "grid spacing stays in metres for the geometry");
+
+    // Without a Mach number the default applies
+    let config = air_config(serde_json::json!({ "length": 0.1 }), None);
+    let units = UnitConverter::from_config(&config).unwrap();
+    assert!(close(units.dt, DEFAULT_MACH / 3.0f32.sqrt() * 0.001));
+}
+
+#[test]
+fn waveform_times_convert_from_seconds() {
+    let mut config = air_config(serde_json::json!({ "length": 0.1, "mach": 0.1 }), None);
+    let units = UnitConverter::from_config(&config).unwrap();
+    let steps = |seconds: f32| seconds / units.dt;
+
+    config.boundaries.inlet_waveform = Some(InletWaveform::Ramp { duration: 0.01 });
+    let lattice = units.lattice_config(&config);
+    assert_eq!(lattice.boundaries.inlet_waveform, Some(InletWaveform::Ramp { duration: steps(0.01) }));
+    let ramp = Waveform::from_config(&lattice).unwrap();
+    assert!(close(ramp.factor(100), 100.0 / steps(0.01)));
+
+    config.boundaries.inlet_waveform = Some(InletWaveform::Sinusoidal { period: 0.02, amplitude: 0.5, phase: 0.0 });
+    let Some(InletWaveform::Sinusoidal { period, .. }) = units.lattice_config(&config).boundaries.inlet_waveform else {
+        panic!("sinusoid")
+    };
+    assert!(close(period, steps(0.02)));
+
+    // Table times in milliseconds
+    let path = std::env::temp_dir().join(format!("lbm-units-table-{}.csv", std::process::id()));
+    std::fs::write(&path, "0 0\n10 1\n").unwrap();
+    let file = path.to_str().unwrap().to_string();
+    config.boundaries.inlet_waveform = Some(InletWaveform::Table { file, repeat: false, time_scale: Some(0.001) });
+    let table = Waveform::from_config(&units.lattice_config(&config));
+    std::fs::remove_file(&path).ok();
+    let Waveform::Table { samples, .. } = table.unwrap() else { panic!("table") };
+    assert!(close(samples[1].0, steps(0.01)), "{:?}", samples);
+}
+
+#[test]
+fn relaxation_time_sets_time_step() {
+    let mut config = air_config(serde_json::json!({ "length": 0.1, "velocity": 2.0 }), Some(0.6));
+    config.physics.viscosity = Some(1e-3);
+    let units = UnitConverter::from_config(&config).unwrap();
+
+    // ν_lb = cs² (τ - 1/2) = ν dt / dx²
+    assert!(close(units.dt, (0.1 / 3.0) * 1e-6 / 1e-3), "dt = {}", units.dt);
+    assert!(close(units.tau, 0.6));
+    assert!(close(units.reynolds_number, 2.0 * 0.1 / 1e-3));
+
+    // Viscosity from the Reynolds number when unset
+    config.physics.viscosity = None;
+    let units = UnitConverter::from_config(&config).unwrap();
+    assert!(close(units.reynolds_number, 100.0));
+    assert!(close(units.dt, (0.1 / 3.0) * 1e-6 / (2.0 * 0.1 / 100.0)));
+}
+
+#[test]
+fn invalid_physical_inputs_are_rejected() {
+    let config = air_config(serde_json::json!({ "length": 0.1, "mach": 0.1 }), Some(0.6));
+    let err = UnitConverter::from_config(&config).unwrap_err().to_string();
+    assert!(err.contains("units.mach") && err.contains("simulation.tau"), "{}", err);
+
+    let config = air_config(serde_json::json!({ "length": -0.1 }), None);
+    assert!(UnitConverter::from_config(&config).unwrap_err().to_string().contains("units.length"));
+
+    let mut config = air_config(serde_json::json!({ "length": 0.1 }), None);
+    config.physics.inlet_velocity = [0.0; 3];
+    assert!(UnitConverter::from_config(&config).is_err(), "no characteristic velocity");
+}
+
+#[test]
+fn lattice_configs_convert_with_unit_factors() {
+    let mut config = air_config(serde_json::Value::Null, Some(0.8));
+    config.physics.inlet_velocity = [0.05, 0.0, 0.0];
+    let units = UnitConverter::from_config(&config).unwrap();
+    assert!(!units.is_physical());
+    assert_eq!((units.dx, units.dt, units.density, units.tau), (1.0, 1.0, 1.0, 0.8));
+    assert_eq!(units.velocity(), 1.0);
+    assert_eq!(units.time(250), 250.0);
+    assert_eq!(units.describe(), "lattice units");
+
+    let lattice = units.lattice_config(&config);
+    assert_eq!(lattice.physics.inlet_velocity, config.physics.inlet_velocity);
+    assert_eq!(lattice.boundaries.outlet_density, Some(1.2));
+}
+
+#[test]
+fn output_is_written_in_physical_units() {
+    let config = air_config(serde_json::json!({ "length": 0.1, "mach": 0.1 }), None);
+    let units = UnitConverter::from_config(&config).unwrap();
+    let lattice_config = units.lattice_config(&config);
+    let model = lattice_config.simulation.lattice.lattice();
+    let velocity = lattice_config.physics.inlet_velocity;
+    let points = vec![LatticePoint::new_equilibrium(model, 1.01, velocity, 0); 12];
+
+    let path = std::env::temp_dir().join(format!("lbm-units-{}-{:?}.vtk", std::process::id(), std::thread::current().id()));
+    let mut writer = VTKWriter::new(&lattice_config, &units);
+    writer.write(path.to_str().unwrap(), &points, 100).unwrap();
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let lines: Vec<&str> = content.lines().collect();
+    assert!(lines[1].contains(&format!("Time {:.6e}", units.time(100))), "{}", lines[1]);
+    assert!(lines[1].contains("dt") && lines[1].contains("Pa per lattice unit"), "{}", lines[1]);
+
+    let field = |name: &str| {
+        let at = lines.iter().position(|l| l.contains(name)).unwrap();
+        let first = if lines[at + 1].starts_with("LOOKUP_TABLE") { at + 2 } else { at + 1 };
+        lines[first].split_whitespace().map(|v| v.parse::<f32>().unwrap()).collect::<Vec<_>>()
+    };
+    assert!(close(field("VECTORS Velocity")[0], 1.0), "inlet speed in m/s");
+    assert!(close(field("SCALARS Density")[0], 1.01 * 1.2), "density in kg/m³");
+    assert!(close(field("SCALARS Pressure")[0], 0.01 / 3.0 * units.pressure()), "gauge pressure in Pa");
+}
+
+#[test]
+fn reynolds_number_uses_characteristic_length() {
+    // 0.05 past a body 10 nodes across at Re 50: ν = 0.01, τ = 3ν + 1/2
+    let mut config = air_config(serde_json::Value::Null, None);
+    config.physics.viscosity = None;
+    config.physics.inlet_velocity = [0.03, 0.04, 0.0];
+    config.physics.reynolds_number = 50.0;
+    config.physics.characteristic_length = Some(CharacteristicLength::Length(0.01));
+    assert!(close(config.calculate_tau(), 0.53));
+    assert!(close(config.effective_reynolds_number(), 50.0));
+    assert!(close(UnitConverter::from_config(&config).unwrap().reynolds_number, 50.0));
+
+    // One grid spacing by default
+    config.physics.characteristic_length = None;
+    assert!(close(config.calculate_tau(), 0.503));
+
+    // An explicit relaxation time overrides the configured Reynolds number
+    config.physics.characteristic_length = Some(CharacteristicLength::Length(0.01));
+    config.simulation.tau = Some(0.8);
+    assert!(close(config.effective_reynolds_number(), 0.05 * 10.0 / 0.1));
+    let validation = config.validate(None);
+    assert!(validation.warnings().any(|issue| issue.field == "physics.reynolds_number"), "{:?}", validation.issues);
+
+    // In physical units the length of the Reynolds number carries over
+    let mut config = air_config(serde_json::json!({ "mach": 0.1 }), None);
+    config.physics.characteristic_length = Some(CharacteristicLength::Length(0.1));
+    let units = UnitConverter::from_config(&config).unwrap();
+    assert!(close(units.reynolds_number, 0.1 / 1.5e-5));
+    assert!(close(units.lattice_config(&config).effective_reynolds_number(), units.reynolds_number));
+
+    // The deprecated units.length may not compete with it
+    let validation = config.validate(None);
+    assert!(!validation.mentions("units.length"), "{:?}", validation.issues);
+    config.units.as_mut().unwrap().length = Some(0.2);
+    let issue = config.validate(None).errors().find(|issue| issue.field == "units.length").cloned();
+    assert!(issue.is_some_and(|issue| issue.message.contains("not both")));
+    config.physics.characteristic_length = None;
+    assert!(config.validate(None).warnings().any(|issue| issue.field == "units.length"));
+}
+
+#[test]
+fn characteristic_length_from_geometry() {
+    let mut config = air_config(serde_json::Value::Null, Some(0.8));
+    config.physics.characteristic_length =
+        serde_json::from_value(serde_json::json!({ "from_geometry": "y" })).unwrap();
+    assert_eq!(config.physics.characteristic_length, Some(CharacteristicLength::FromGeometry { from_geometry: Axis::Y }));
+    assert_eq!(config.characteristic_length(), 0.001, "grid spacing until resolved");
+
+    assert!(config.clone().resolve_characteristic_length(None).is_err());
+    assert!(config.validate(None).mentions("physics.characteristic_length"));
+
+    let body = [[0.01, 0.02, 0.0], [0.03, 0.025, 0.001]];
+    assert!(!config.validate(Some(body)).mentions("physics.characteristic_length"));
+    config.resolve_characteristic_length(Some(body)).unwrap();
+    assert!(close(config.characteristic_length(), 0.005));
+    assert!(matches!(config.physics.characteristic_length, Some(CharacteristicLength::Length(_))));
+
+    let parsed: Option<CharacteristicLength> = serde_json::from_value(serde_json::json!(0.02)).unwrap();
+    assert_eq!(parsed, Some(CharacteristicLength::Length(0.02)));
+}
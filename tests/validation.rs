@@ -0,0 +1,131 @@
+use lattice_boltzmann_rs::{
+    config::{CharacteristicLength, CollisionModel, InletWaveform, LatticeType},
+    validation::Severity,
+    Config, Geometry,
+};
+
+mod common;
+
+use common::{test_config, TestConfig};
+
+/// 50 × 20 × 20 D3Q19 channel at Re 50 of a 10 mm body, with the derived relaxation time
+fn channel_config() -> Config {
+    let mut config = test_config(50, 20, 20).spacing(0.001).lattice(LatticeType::D3Q19).tau(None);
+    config.physics.reynolds_number = 50.0;
+    config.physics.characteristic_length = Some(CharacteristicLength::Length(0.01));
+    (config.simulation.max_iterations, config.output.output_frequency) = (100, 10);
+    config
+}
+
+#[test]
+fn stable_config_passes() {
+    let body = [[0.01, 0.005, 0.005], [0.02, 0.015, 0.015]];
+    let validation = channel_config().validate(Some(body));
+    assert!(validation.issues.is_empty(), "{:?}", validation.issues);
+    assert!(validation.into_result().is_ok());
+}
+
+#[test]
+fn unstable_settings_are_errors() {
+    let mut config = channel_config();
+    config.simulation.tau = Some(0.5);
+    config.output.output_frequency = 0;
+    let validation = config.validate(None);
+    assert!(validation.has_errors());
+    assert!(validation.mentions("simulation.tau") && validation.mentions("output.output_frequency"));
+
+    let mut config = channel_config();
+    config.physics.inlet_velocity = [0.3, 0.0, 0.0];
+    let validation = config.validate(None);
+    let issue = validation.errors().next().unwrap();
+    assert_eq!((issue.severity, issue.field.as_str()), (Severity::Error, "physics.inlet_velocity"));
+    assert!(issue.to_string().starts_with("error: physics.inlet_velocity: lattice Mach number 0.520"), "{}", issue);
+
+    config.output.output_frequency = 0;
+    let err = config.validate(None).into_result().unwrap_err().to_string();
+    assert!(err.contains("physics.inlet_velocity") && err.contains("output.output_frequency"), "{}", err);
+
+    let mut config = channel_config();
+    config.simulation.collision_model = CollisionModel::Cumulant;
+    assert!(config.validate(None).mentions("simulation.collision_model"), "cumulant needs D3Q27");
+}
+
+#[test]
+fn marginal_settings_are_warnings() {
+    let mut config = channel_config();
+    config.simulation.tau = Some(0.505);
+    let validation = config.validate(None);
+    assert!(!validation.has_errors());
+    let warnings: Vec<String> = validation.warnings().map(|issue| issue.to_string()).collect();
+    assert!(warnings.iter().any(|w| w.contains("close to the stability limit")), "{:?}", warnings);
+    assert!(warnings.iter().any(|w| w.contains("grid Reynolds number")), "{:?}", warnings);
+
+    // A pulsating inlet peaks above Mach 0.2
+    let mut config = channel_config();
+    config.physics.inlet_velocity = [0.1, 0.0, 0.0];
+    assert!(config.validate(None).issues.is_empty());
+    config.boundaries.inlet_waveform = Some(InletWaveform::Sinusoidal { period: 100.0, amplitude: 0.5, phase: 0.0 });
+    let validation = config.validate(None);
+    assert!(validation.warnings().any(|issue| issue.field == "physics.inlet_velocity"), "{:?}", validation.issues);
+
+    let mut config = channel_config();
+    config.output.output_frequency = 1000;
+    config.output.output_format = "csv".to_string();
+    let validation = config.validate(None);
+    assert!(validation.warnings().count() == 2 && !validation.has_errors(), "{:?}", validation.issues);
+}
+
+#[test]
+fn geometry_must_fit_the_domain() {
+    let config = channel_config();
+
+    // An STL in millimetres on a grid in metres
+    let validation = config.validate(Some([[10.0, 5.0, 5.0], [20.0, 15.0, 15.0]]));
+    let issue = validation.errors().next().unwrap();
+    assert!(issue.field == "domain" && issue.message.contains("domain.dx"), "{}", issue);
+
+    let validation = config.validate(Some([[0.04, 0.005, 0.005], [0.06, 0.015, 0.015]]));
+    assert!(!validation.has_errors());
+    assert!(validation.warnings().any(|issue| issue.message.contains("clipped")), "{:?}", validation.issues);
+
+    // The domain spans nx * dx, so a body reaching its far face is not clipped
+    let validation = config.validate(Some([[0.04, 0.005, 0.005], [0.05, 0.015, 0.015]]));
+    assert!(!validation.mentions("domain"), "{:?}", validation.issues);
+
+    let validation = config.validate(Some([[0.01, 0.01, 0.01], [0.013, 0.013, 0.013]]));
+    let issue = validation.warnings().next().unwrap();
+    assert!(issue.field == "domain.dx" && issue.message.contains("3.0 nodes"), "{}", issue);
+}
+
+#[test]
+fn surface_bounds_of_stl_and_polygon_files() {
+    let config = channel_config();
+    let [low, high] = Geometry::surface_bounds("example_cylinder.stl", &config).unwrap();
+    assert!((0..3).all(|axis| low[axis] < high[axis]), "{:?} {:?}", low, high);
+
+    let mut config = channel_config();
+    config.simulation.lattice = LatticeType::D2Q9;
+    config.domain.nz = 1;
+    let path = std::env::temp_dir().join(format!("lbm-bounds-{}-{:?}.txt", std::process::id(), std::thread::current().id()));
+    std::fs::write(&path, "0.01 0.002\n0.03 0.002\n0.02 0.012\n").unwrap();
+    let bounds = Geometry::surface_bounds(path.to_str().unwrap(), &config).unwrap();
+    let geometry = Geometry::load(path.to_str().unwrap(), &config).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(bounds, [[0.01, 0.002, 0.0], [0.03, 0.012, 0.0]]);
+    assert_eq!(geometry.bounds(), bounds, "same box once loaded");
+}
+
+#[test]
+fn derived_tau_of_shipped_config_is_pinned() {
+    // Re 150 at 0.15 over one grid spacing: ν = 0.001, τ = 3ν + 1/2
+    let mut config = Config::from_file("vortex_config.json").unwrap();
+    assert!((config.calculate_tau() - 0.503).abs() < 1e-6, "tau = {}", config.calculate_tau());
+
+    // Earlier versions derived 9 u dx / Re + 1/2 with dx in the units of the config
+    let validation = config.validate(None);
+    let issue = validation.warnings().find(|issue| issue.message.contains("earlier versions"));
+    assert!(issue.is_some_and(|issue| issue.field == "simulation.tau" && issue.message.contains("0.500009")));
+
+    config.physics.characteristic_length = Some(CharacteristicLength::Length(0.001));
+    assert!(!config.validate(None).warnings().any(|issue| issue.message.contains("earlier versions")));
+}